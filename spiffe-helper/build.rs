@@ -0,0 +1,89 @@
+//! Records build details for `spiffe-helper version --verbose`.
+
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=GIT_HASH");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    // Release pipelines pass GIT_HASH; local builds fall back to the checkout's HEAD
+    let git_hash = std::env::var("GIT_HASH")
+        .ok()
+        .filter(|hash| !hash.is_empty())
+        .or_else(git_head)
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=SPIFFE_HELPER_GIT_HASH={git_hash}");
+
+    // SOURCE_DATE_EPOCH keeps reproducible builds reproducible
+    let timestamp = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs())
+        });
+    println!(
+        "cargo:rustc-env=SPIFFE_HELPER_BUILD_TIMESTAMP={}",
+        utc_timestamp(timestamp)
+    );
+
+    let target = std::env::var("TARGET").unwrap_or_else(|_| "unknown".to_string());
+    println!("cargo:rustc-env=SPIFFE_HELPER_TARGET={target}");
+
+    let lock_file = Path::new(env!("CARGO_MANIFEST_DIR")).join("../Cargo.lock");
+    println!("cargo:rerun-if-changed={}", lock_file.display());
+    let spiffe_version = locked_version(&lock_file, "spiffe").unwrap_or_else(|| "unknown".into());
+    println!("cargo:rustc-env=SPIFFE_HELPER_SPIFFE_VERSION={spiffe_version}");
+}
+
+fn git_head() -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let hash = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (!hash.is_empty()).then_some(hash)
+}
+
+/// The version of `package` resolved in `Cargo.lock`.
+fn locked_version(lock_file: &Path, package: &str) -> Option<String> {
+    let lock = std::fs::read_to_string(lock_file).ok()?;
+    let name_line = format!("name = \"{package}\"");
+    let mut lines = lock.lines();
+    while let Some(line) = lines.next() {
+        if line == name_line {
+            let version = lines.next()?.strip_prefix("version = \"")?;
+            return Some(version.trim_end_matches('"').to_string());
+        }
+    }
+    None
+}
+
+/// Formats seconds since the Unix epoch as an RFC 3339 UTC timestamp.
+fn utc_timestamp(seconds: u64) -> String {
+    let days = i64::try_from(seconds / 86_400).unwrap_or(0);
+    let secs_of_day = seconds % 86_400;
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs_of_day / 3_600,
+        secs_of_day % 3_600 / 60,
+        secs_of_day % 60
+    )
+}
@@ -0,0 +1,50 @@
+//! Detaches the daemon from its terminal for `background = true`.
+
+use anyhow::{Context, Result};
+use nix::unistd::{dup2, fork, setsid, ForkResult};
+use std::fs::{self, File, OpenOptions};
+use std::os::fd::AsRawFd;
+use std::path::Path;
+
+/// Double-forks into a new session, writes the daemon's PID to `pid_file`, and redirects stdout
+/// and stderr to `log_file` (or `/dev/null`) and stdin from `/dev/null`. The working directory
+/// is kept so relative paths in the config still resolve.
+///
+/// Returns only in the detached process; the original process exits with status 0. Must be
+/// called before any other thread is started, such as the Tokio runtime's workers.
+pub fn daemonize(pid_file: Option<&Path>, log_file: Option<&Path>) -> Result<()> {
+    // Opened up front so a bad path is still reported on the terminal
+    let stdin = File::open("/dev/null").context("Failed to open /dev/null")?;
+    let output = match log_file {
+        Some(path) => OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open background log file {}", path.display()))?,
+        None => OpenOptions::new()
+            .write(true)
+            .open("/dev/null")
+            .context("Failed to open /dev/null")?,
+    };
+
+    // SAFETY: the caller guarantees the process is still single-threaded
+    if let ForkResult::Parent { .. } = unsafe { fork() }.context("Failed to fork")? {
+        std::process::exit(0);
+    }
+    setsid().context("Failed to start a new session")?;
+    // The session leader exits so the daemon can never reacquire a controlling terminal
+    // SAFETY: still single-threaded after the first fork
+    if let ForkResult::Parent { .. } = unsafe { fork() }.context("Failed to fork")? {
+        std::process::exit(0);
+    }
+
+    if let Some(pid_file) = pid_file {
+        fs::write(pid_file, format!("{}\n", std::process::id()))
+            .with_context(|| format!("Failed to write PID file {}", pid_file.display()))?;
+    }
+
+    dup2(stdin.as_raw_fd(), 0).context("Failed to redirect stdin")?;
+    dup2(output.as_raw_fd(), 1).context("Failed to redirect stdout")?;
+    dup2(output.as_raw_fd(), 2).context("Failed to redirect stderr")?;
+    Ok(())
+}
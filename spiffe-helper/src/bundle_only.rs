@@ -0,0 +1,207 @@
+//! Bundle-only mode: streams the X.509 bundles from the agent's `FetchX509Bundles` RPC and
+//! writes the bundle of `primary_bundle_trust_domain`, for workloads such as verifiers that
+//! need the trust bundle but no SVID of their own.
+
+use anyhow::{anyhow, Context, Result};
+use spiffe::bundle::x509::{X509Bundle, X509BundleSet};
+use spiffe::spiffe_id::TrustDomain;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+#[cfg(unix)]
+use tokio::signal::unix::{signal, SignalKind};
+use tonic::codegen::http::uri::PathAndQuery;
+
+use crate::cli::Config;
+use crate::file_system::{CredentialSink, LocalFileSystem, MultiSink};
+use crate::info;
+use crate::jwt::{self, WORKLOAD_API_HEADER};
+use crate::workload_api::{self, Backoff};
+
+const FETCH_X509_BUNDLES_PATH: &str = "/SpiffeWorkloadAPI/FetchX509Bundles";
+
+/// Wire types for the `FetchX509Bundles` RPC.
+///
+/// The spiffe crate's `X509Source` waits for an SVID before serving bundles, so the bundles are
+/// streamed directly for workloads the agent issues no SVID to.
+mod pb {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct X509BundlesRequest {}
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct X509BundlesResponse {
+        #[prost(bytes = "vec", repeated, tag = "1")]
+        pub crl: Vec<Vec<u8>>,
+        #[prost(map = "string, bytes", tag = "2")]
+        pub bundles: std::collections::HashMap<String, Vec<u8>>,
+    }
+}
+
+/// Runs the bundle-only mode, writing to the `output` blocks or to `cert_dir`.
+pub async fn run(config: Config) -> Result<()> {
+    if config.outputs.is_some() {
+        let outputs = MultiSink::local(&config)?;
+        return run_with_sink(config, outputs).await;
+    }
+    let local_fs = LocalFileSystem::new(&config)?.ensure()?;
+    run_with_sink(config, local_fs).await
+}
+
+/// Like [`run`], but writes the bundle to `sink`. In daemon mode the bundle is rewritten on
+/// every change until SIGTERM or SIGINT, reconnecting with backoff when the stream fails;
+/// otherwise the first bundle is written and the call returns.
+pub async fn run_with_sink<S: CredentialSink>(config: Config, sink: S) -> Result<()> {
+    let primary = config
+        .primary_bundle_trust_domain
+        .as_deref()
+        .context("bundle_only requires primary_bundle_trust_domain")?;
+    let trust_domain = TrustDomain::new(primary)
+        .with_context(|| format!("Invalid primary_bundle_trust_domain {primary:?}"))?;
+
+    if !config.is_daemon_mode() {
+        return write_streamed_bundles(&config, &trust_domain, &sink, &mut None).await;
+    }
+
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+    let mut backoff = Backoff::new(config.connect_backoff_jitter());
+    let mut written = None;
+    let mut attempt = 0;
+    info!("Bundle-only mode running. Waiting for SIGTERM or SIGINT to shutdown...");
+
+    loop {
+        let error = tokio::select! {
+            res = &mut shutdown => {
+                res?;
+                info!("Received shutdown signal, shutting down gracefully...");
+                return Ok(());
+            }
+            res = write_streamed_bundles(&config, &trust_domain, &sink, &mut written) => {
+                res.expect_err("the daemon keeps streaming until the stream fails")
+            }
+        };
+        attempt += 1;
+        let delay = backoff.delay(attempt);
+        eprintln!(
+            "X.509 bundle stream failed: {error:#}; reconnecting in {}ms",
+            delay.as_millis()
+        );
+        tokio::select! {
+            res = &mut shutdown => {
+                res?;
+                info!("Received shutdown signal, shutting down gracefully...");
+                return Ok(());
+            }
+            () = tokio::time::sleep(delay) => {}
+        }
+    }
+}
+
+/// Streams the X.509 bundles and writes the bundle of `trust_domain` whenever it changes,
+/// tracking the written authorities in `written`. Outside daemon mode, returns once the first
+/// bundle is written; in daemon mode, only with the error that ended the stream.
+async fn write_streamed_bundles<S: CredentialSink>(
+    config: &Config,
+    trust_domain: &TrustDomain,
+    sink: &S,
+    written: &mut Option<u64>,
+) -> Result<()> {
+    let channel = jwt::connect(config.agent_address()?).await?;
+    let mut grpc = tonic::client::Grpc::new(channel);
+    grpc.ready()
+        .await
+        .map_err(|e| anyhow!("SPIRE agent is not ready: {e}"))?;
+
+    let mut request = tonic::Request::new(pb::X509BundlesRequest {});
+    request
+        .metadata_mut()
+        .insert(WORKLOAD_API_HEADER, "true".parse()?);
+
+    let mut stream = grpc
+        .server_streaming::<_, pb::X509BundlesResponse, _>(
+            request,
+            PathAndQuery::from_static(FETCH_X509_BUNDLES_PATH),
+            tonic::codec::ProstCodec::default(),
+        )
+        .await
+        .map_err(|e| anyhow!("Failed to fetch X.509 bundles: {}", e.message()))?
+        .into_inner();
+
+    while let Some(response) = stream
+        .message()
+        .await
+        .map_err(|e| anyhow!("Failed to fetch X.509 bundles: {}", e.message()))?
+    {
+        let bundles = bundle_set(response)?;
+        let bundle = workload_api::bundle_for_writing(
+            trust_domain,
+            &bundles,
+            config.include_federated_domains(),
+        )?;
+        let bundle = workload_api::with_extra_ca(bundle, config)?;
+
+        let digest = authorities_digest(&bundle);
+        if *written != Some(digest) {
+            sink.write_bundle(&bundle)?;
+            *written = Some(digest);
+            info!(
+                "Wrote bundle for trust domain {trust_domain} ({} authorities)",
+                bundle.authorities().len()
+            );
+        }
+
+        if !config.is_daemon_mode() {
+            return Ok(());
+        }
+    }
+
+    anyhow::bail!("SPIRE agent closed the X.509 bundle stream")
+}
+
+/// Parses the bundles of a `FetchX509Bundles` response, each the concatenated DER of its
+/// authorities keyed by trust domain ID.
+fn bundle_set(response: pb::X509BundlesResponse) -> Result<X509BundleSet> {
+    let mut bundles = X509BundleSet::new();
+    for (id, der) in response.bundles {
+        let trust_domain = TrustDomain::new(&id)
+            .with_context(|| format!("Invalid trust domain {id:?} in X.509 bundles"))?;
+        let bundle = X509Bundle::parse_from_der(trust_domain, &der)
+            .with_context(|| format!("Invalid X.509 bundle for {id}"))?;
+        bundles.add_bundle(bundle);
+    }
+    Ok(bundles)
+}
+
+fn authorities_digest(bundle: &X509Bundle) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for authority in bundle.authorities() {
+        authority.as_ref().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Resolves on SIGTERM or SIGINT, or their Windows counterparts.
+async fn shutdown_signal() -> Result<()> {
+    #[cfg(unix)]
+    {
+        let mut sigterm =
+            signal(SignalKind::terminate()).context("Failed to register SIGTERM handler")?;
+        let mut sigint =
+            signal(SignalKind::interrupt()).context("Failed to register SIGINT handler")?;
+        tokio::select! {
+            _ = sigterm.recv() => {}
+            _ = sigint.recv() => {}
+        }
+    }
+    #[cfg(windows)]
+    {
+        let mut shutdown = tokio::signal::windows::ctrl_shutdown()
+            .context("Failed to register shutdown handler")?;
+        let mut ctrl_c =
+            tokio::signal::windows::ctrl_c().context("Failed to register Ctrl-C handler")?;
+        tokio::select! {
+            _ = shutdown.recv() => {}
+            _ = ctrl_c.recv() => {}
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,200 @@
+//! Prints the certificates in the files the helper wrote so operators can troubleshoot the
+//! identity on disk without contacting the SPIRE agent.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use x509_parser::extensions::GeneralName;
+use x509_parser::time::ASN1Time;
+
+use crate::jwt_inspect::format_timestamp;
+
+/// The details `dump-chain` reports for one certificate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertificateDetails {
+    pub subject: String,
+    pub issuer: String,
+    /// URI SANs with the `spiffe` scheme
+    pub spiffe_ids: Vec<String>,
+    /// Serial number as colon-separated hex bytes
+    pub serial: String,
+    /// Start of the validity period, in seconds since the Unix epoch
+    pub not_before: i64,
+    /// End of the validity period, in seconds since the Unix epoch
+    pub not_after: i64,
+    /// The key usage extension, e.g. `Digital Signature, Key Encipherment`, if present
+    pub key_usage: Option<String>,
+    pub is_ca: bool,
+}
+
+impl CertificateDetails {
+    /// Whether the certificate's validity ended before `now`, in seconds since the Unix epoch.
+    #[must_use]
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.not_after < now
+    }
+}
+
+/// Parses every certificate in the PEM `content`, in order.
+pub fn describe_pem(content: &str) -> Result<Vec<CertificateDetails>> {
+    pem::parse_many(content)
+        .context("Invalid PEM")?
+        .iter()
+        .filter(|block| block.tag == "CERTIFICATE")
+        .enumerate()
+        .map(|(index, block)| {
+            describe_der(&block.contents)
+                .with_context(|| format!("Invalid certificate {}", index + 1))
+        })
+        .collect()
+}
+
+pub(crate) fn describe_der(der: &[u8]) -> Result<CertificateDetails> {
+    let (_, cert) = x509_parser::parse_x509_certificate(der)?;
+
+    let spiffe_ids = cert
+        .subject_alternative_name()?
+        .map(|san| {
+            san.value
+                .general_names
+                .iter()
+                .filter_map(|name| match name {
+                    GeneralName::URI(uri) if uri.starts_with("spiffe://") => Some(uri.to_string()),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(CertificateDetails {
+        subject: cert.subject().to_string(),
+        issuer: cert.issuer().to_string(),
+        spiffe_ids,
+        serial: cert.raw_serial_as_string(),
+        not_before: cert.validity().not_before.timestamp(),
+        not_after: cert.validity().not_after.timestamp(),
+        key_usage: cert.key_usage()?.map(|usage| usage.value.to_string()),
+        is_ca: cert.is_ca(),
+    })
+}
+
+/// Renders `details` as the indented lines printed under each certificate's heading.
+fn format_details(details: &CertificateDetails) -> String {
+    let or_none = |value: &str| {
+        if value.is_empty() {
+            "none".to_string()
+        } else {
+            value.to_string()
+        }
+    };
+
+    [
+        format!("  Subject: {}", or_none(&details.subject)),
+        format!("  Issuer: {}", or_none(&details.issuer)),
+        format!("  SPIFFE IDs: {}", or_none(&details.spiffe_ids.join(", "))),
+        format!("  Serial: {}", details.serial),
+        format!("  Not before: {}", format_timestamp(details.not_before)),
+        format!("  Not after: {}", format_timestamp(details.not_after)),
+        format!(
+            "  Key usage: {}",
+            details.key_usage.as_deref().unwrap_or("none")
+        ),
+        format!("  CA: {}", details.is_ca),
+    ]
+    .join("\n")
+}
+
+/// Runs the `dump-chain` operation: prints each certificate in `file_names` under `cert_dir`,
+/// warning about any that expired. Missing files are skipped, but at least one must exist.
+pub fn run(cert_dir: &Path, file_names: &[&str]) -> Result<()> {
+    let now = ASN1Time::now().timestamp();
+    let mut found = false;
+
+    for file_name in file_names {
+        let path = cert_dir.join(file_name);
+        if !path.exists() {
+            println!("{}: not found", path.display());
+            continue;
+        }
+        found = true;
+
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let certificates = describe_pem(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+        println!("{} ({} certificates)", path.display(), certificates.len());
+        for (index, details) in certificates.iter().enumerate() {
+            println!("[{}]", index + 1);
+            println!("{}", format_details(details));
+            if details.is_expired(now) {
+                eprintln!(
+                    "Warning: certificate {} in {} expired at {}",
+                    index + 1,
+                    path.display(),
+                    format_timestamp(details.not_after)
+                );
+            }
+        }
+    }
+
+    if !found {
+        anyhow::bail!("No certificate files found in {}", cert_dir.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workload_api::tests::TEST_CERT_PEM;
+
+    #[test]
+    fn test_describe_pem_reports_spiffe_id() {
+        let certificates = describe_pem(TEST_CERT_PEM).unwrap();
+
+        assert_eq!(certificates.len(), 1);
+        let details = &certificates[0];
+        assert_eq!(details.spiffe_ids, ["spiffe://localhost/test"]);
+        assert_eq!(details.subject, "CN=localhost");
+        assert_eq!(
+            details.serial,
+            "1a:af:e8:36:77:17:6a:6d:00:f5:58:32:54:43:42:f0:6b:90:9f:f8"
+        );
+        assert_eq!(
+            details.key_usage.as_deref(),
+            Some("Digital Signature, Key Encipherment")
+        );
+        assert!(!details.is_ca);
+
+        let formatted = format_details(details);
+        assert!(formatted.contains("  SPIFFE IDs: spiffe://localhost/test"));
+    }
+
+    #[test]
+    fn test_is_expired() {
+        let details = &describe_pem(TEST_CERT_PEM).unwrap()[0];
+
+        assert!(!details.is_expired(details.not_after));
+        assert!(details.is_expired(details.not_after + 1));
+    }
+
+    #[test]
+    fn test_describe_pem_rejects_invalid_certificate() {
+        let invalid = pem::encode(&pem::Pem {
+            tag: "CERTIFICATE".to_string(),
+            contents: b"not DER".to_vec(),
+        });
+
+        let error = describe_pem(&invalid).unwrap_err();
+        assert!(format!("{error:#}").contains("Invalid certificate 1"));
+    }
+
+    #[test]
+    fn test_run_fails_without_certificate_files() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+
+        let error = run(temp_dir.path(), &["svid.pem"]).unwrap_err();
+        assert!(error.to_string().contains("No certificate files found"));
+    }
+}
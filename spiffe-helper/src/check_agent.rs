@@ -0,0 +1,56 @@
+//! Checks that the SPIRE agent is reachable and serving an SVID, for liveness scripts and
+//! manual debugging. Nothing is written to disk.
+
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+
+use crate::cli::Config;
+use crate::health::SvidInfo;
+use crate::jwt_inspect::{describe_time_to_expiry, format_timestamp};
+use crate::workload_api;
+
+/// How long to wait for the agent's first response before reporting it unreachable
+const CHECK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Connects to the agent in `config`, fetches one X.509 SVID, and summarizes it. Applies
+/// `expected_trust_domain` like a real fetch would.
+pub async fn check_agent(config: &Config) -> Result<SvidInfo> {
+    let agent_address = config.agent_address()?;
+    let source = tokio::time::timeout(
+        CHECK_TIMEOUT,
+        workload_api::create_x509_source(agent_address),
+    )
+    .await
+    .map_err(|_| {
+        anyhow!(
+            "Timed out after {}s waiting for the SPIRE agent at {agent_address}",
+            CHECK_TIMEOUT.as_secs()
+        )
+    })??;
+
+    let info = workload_api::fetch_x509_svid_for_config(&source, config)
+        .and_then(|(svid, _)| workload_api::svid_info(&svid));
+    source.shutdown().await;
+    info
+}
+
+/// Runs the `check-agent` operation: prints the SPIFFE ID and expiry of the agent's current
+/// SVID.
+pub async fn run(config: &Config) -> Result<()> {
+    let info = check_agent(config).await?;
+    let now = x509_parser::time::ASN1Time::now().timestamp();
+
+    println!("Agent: {}", config.agent_address()?);
+    println!("SPIFFE ID: {}", info.spiffe_id);
+    println!(
+        "Expires at: {} ({})",
+        format_timestamp(info.not_after),
+        info.not_after
+    );
+    println!(
+        "Time to expiry: {}",
+        describe_time_to_expiry(info.not_after, now)
+    );
+    Ok(())
+}
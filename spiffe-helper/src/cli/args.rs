@@ -1,7 +1,7 @@
 use crate::cli::config::{self, Config};
 use anyhow::{anyhow, Context, Result};
-use clap::Parser;
-use std::path::PathBuf;
+use clap::{Parser, Subcommand};
+use std::path::{Path, PathBuf};
 
 pub const DEFAULT_CONFIG_FILE: &str = "helper.conf";
 
@@ -11,35 +11,209 @@ pub const DEFAULT_CONFIG_FILE: &str = "helper.conf";
 #[command(about = "SPIFFE Helper - Fetch and manage X.509 SVID certificates", long_about = None)]
 pub struct Args {
     /// Path to the configuration file
-    #[arg(short, long, default_value = DEFAULT_CONFIG_FILE)]
+    #[arg(short, long, global = true, default_value = DEFAULT_CONFIG_FILE)]
     pub config: String,
 
+    /// Read the configuration from standard input instead of a file. Cannot be combined with
+    /// --config.
+    #[arg(long, global = true, conflicts_with = "config")]
+    pub config_stdin: bool,
+
+    /// Address of the SPIRE agent's Workload API, starting with `unix://` or `tcp://`. Overrides
+    /// `agent_address` in the config file.
+    #[arg(
+        short = 's',
+        long,
+        visible_alias = "socket",
+        value_name = "ADDRESS",
+        value_parser = parse_agent_address
+    )]
+    pub agent_address: Option<String>,
+
     /// Boolean true or false. Overrides `daemon_mode` in the config file.
     #[arg(long, value_parser = clap::value_parser!(bool), value_name = "BOOL")]
     pub daemon_mode: Option<bool>,
 
+    /// Detach from the terminal and run the daemon in the background. Overrides `background`
+    /// in the config file. Unix only.
+    #[arg(long, conflicts_with = "foreground")]
+    pub background: bool,
+
+    /// Keep the daemon attached to the terminal, even if `background = true` in the config file.
+    #[arg(long)]
+    pub foreground: bool,
+
+    /// Fetch, wait until the certificate is valid for at least `watch_once_min_validity_seconds`,
+    /// write it, and exit. Overrides `daemon_mode`.
+    #[arg(long)]
+    pub watch_once: bool,
+
+    /// In one-shot mode, try connecting to the SPIRE agent up to N times in total (10 when N is
+    /// omitted) with exponential backoff, then fail instead of waiting indefinitely. Overrides
+    /// `oneshot_max_attempts`.
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "10")]
+    pub oneshot_retry: Option<u32>,
+
+    /// In one-shot mode, print the certificate chain PEM to stdout instead of writing files.
+    #[arg(long)]
+    pub stdout: bool,
+
+    /// With --stdout, also print the private key PEM after the certificate chain.
+    #[arg(long, requires = "stdout")]
+    pub stdout_key: bool,
+
+    /// In daemon mode, fetch credentials and log the files that would be written, with their
+    /// sizes, SPIFFE ID, and expiry, without writing files, running commands, or sending
+    /// signals.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Print only errors and warnings, on stderr, instead of progress messages. Same as
+    /// `quiet = true` in the config file.
+    #[arg(short, long)]
+    pub quiet: bool,
+
+    /// Reject unknown keys in the config file instead of ignoring them. Same as `strict = true`
+    /// in the config file.
+    #[arg(long)]
+    pub strict_config: bool,
+
+    /// Validate the config file, including that each managed process command is executable,
+    /// then exit without contacting the SPIRE agent.
+    #[arg(long)]
+    pub config_check: bool,
+
     /// Print version number
     #[arg(short = 'v', long)]
     pub version: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Operations that run instead of fetching credentials from the SPIRE agent.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Decode a JWT SVID file and print its SPIFFE ID, audiences, issue and expiry times, and
+    /// time to expiry. The signature is not verified unless --jwks is given.
+    ValidateJwt {
+        /// Path to the JWT SVID file
+        file: PathBuf,
+
+        /// JWKS document, or a JWT bundle file written by the helper, to verify the signature
+        /// against
+        #[arg(long, value_name = "FILE")]
+        jwks: Option<PathBuf>,
+    },
+
+    /// Print the version number, like --version
+    Version {
+        /// Also print the git commit, build time, target triple, and spiffe crate version
+        #[arg(long)]
+        verbose: bool,
+    },
+
+    /// Connect to the SPIRE agent in the config file, fetch one X.509 SVID, and print its SPIFFE
+    /// ID and expiry without writing any files. Exits 0 when the agent served an SVID.
+    CheckAgent,
+
+    /// Print a JSON Schema describing every config file setting, its type, default, and
+    /// description, for editor autocompletion and validating configs in CI.
+    Schema,
+
+    /// Print the subject, SPIFFE IDs, serial, validity, and key usage of each certificate in
+    /// the certificate and bundle files already written to a directory, warning about expired
+    /// certificates. Does not contact the SPIRE agent.
+    DumpChain {
+        /// Directory holding the certificate files
+        #[arg(long, value_name = "DIR")]
+        cert_dir: PathBuf,
+
+        /// Name of the certificate chain file
+        #[arg(long, value_name = "NAME", default_value = "svid.pem")]
+        svid_file_name: String,
+
+        /// Name of the bundle file
+        #[arg(long, value_name = "NAME", default_value = "svid_bundle.pem")]
+        svid_bundle_file_name: String,
+    },
 }
 
 impl Args {
     pub fn get_operation_config(&self) -> Result<Config> {
+        self.get_operation_config_from(std::io::stdin().lock())
+    }
+
+    /// Like [`Args::get_operation_config`], but reads the config from `stdin` when
+    /// `--config-stdin` is set.
+    pub fn get_operation_config_from(&self, stdin: impl std::io::Read) -> Result<Config> {
         if self.version {
             return Err(anyhow!("Unexpected error: should return version"));
         }
 
         // Parse config file
         let config_path = PathBuf::from(&self.config);
-        let mut config = config::parse_hcl_config(config_path.as_path())
-            .with_context(|| format!("Failed to parse config file: {}", self.config))?;
+        let parsed = if self.config_stdin {
+            config::parse_hcl_config_reader(stdin, self.strict_config)
+        } else if self.strict_config {
+            config::parse_hcl_config_strict(config_path.as_path())
+        } else {
+            config::parse_hcl_config(config_path.as_path())
+        };
+        let mut config = parsed
+            .with_context(|| format!("Failed to parse config file: {}", self.config_name()))?;
+        if self.strict_config {
+            config.strict = Some(true);
+        }
+
+        // A config from stdin has no directory, so relative paths stay relative to the
+        // working directory
+        if config.paths_relative_to_config() && !self.config_stdin {
+            let config_dir = config_path
+                .parent()
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .unwrap_or(Path::new("."));
+            config.resolve_relative_paths(config_dir);
+        }
 
         // Merge CLI flag with config value and default to true
         config.reconcile_daemon_mode(self.daemon_mode);
+        config.reconcile_watch_once(self.watch_once);
+        config.reconcile_oneshot_max_attempts(self.oneshot_retry);
+        config.reconcile_stdout(self.stdout);
+        config.reconcile_dry_run(self.dry_run);
+        config.reconcile_background(self.background, self.foreground);
+        config.reconcile_quiet(self.quiet);
+
+        // The flag takes precedence over the config file, then socket_dir, then
+        // SPIFFE_ENDPOINT_SOCKET
+        config.reconcile_agent_address(
+            self.agent_address.clone(),
+            std::env::var(config::SPIFFE_ENDPOINT_SOCKET_ENV).ok(),
+        )?;
 
         // Validate required configuration fields early
         config.validate()?;
 
         Ok(config)
     }
+
+    /// The config file path, or `<stdin>` with `--config-stdin`, for messages.
+    #[must_use]
+    pub fn config_name(&self) -> &str {
+        if self.config_stdin {
+            config::STDIN_CONFIG_NAME
+        } else {
+            &self.config
+        }
+    }
+}
+
+/// Accepts `--agent-address` values with a `unix://` or `tcp://` scheme.
+fn parse_agent_address(value: &str) -> std::result::Result<String, String> {
+    if value.starts_with("unix://") || value.starts_with("tcp://") {
+        Ok(value.to_string())
+    } else {
+        Err(format!("{value:?} must start with unix:// or tcp://"))
+    }
 }
@@ -1,14 +1,49 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 const DEFAULT_LIVENESS_PATH: &str = "/health/live";
 const DEFAULT_READINESS_PATH: &str = "/health/ready";
+const DEFAULT_INFO_PATH: &str = "/info";
+const DEFAULT_HEARTBEAT_MESSAGE: &str = "spiffe-helper daemon is alive";
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// The health check HTTP server, configured with a `health_checks` block.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct HealthChecksConfig {
+    /// Whether the health check server runs. Defaults to false.
+    #[schemars(default)]
     pub listener_enabled: bool,
+    /// Port the server binds to. Defaults to 8080.
+    #[schemars(default = "default_bind_port")]
     pub bind_port: u16,
+    /// HTTP path of the liveness probe. Defaults to `/health/live`.
     pub liveness_path: Option<String>,
+    /// HTTP path of the readiness probe. Defaults to `/health/ready`.
     pub readiness_path: Option<String>,
+    /// HTTP path of the certificate info endpoint. Defaults to `/info`.
+    pub info_path: Option<String>,
+    /// Fail the readiness probe when the certificate expires within this many seconds.
+    /// Defaults to 0.
+    pub readiness_expiry_threshold_seconds: Option<u64>,
+    /// Fail the readiness probe once a managed process has exited. Defaults to false.
+    pub readiness_requires_child: Option<bool>,
+    /// How many times in a row a credential write must fail before the liveness probe fails.
+    /// Defaults to 1.
+    pub liveness_failure_threshold: Option<u32>,
+    /// Log a line every 30 seconds while the server runs. Defaults to true.
+    pub heartbeat_enabled: Option<bool>,
+    /// Text of the periodic log line. Defaults to `spiffe-helper daemon is alive`.
+    pub heartbeat_message: Option<String>,
+    /// Serve the endpoints over HTTPS with the current SVID. Defaults to false.
+    pub tls_enabled: Option<bool>,
+    /// Require clients to present a certificate issued by the trust bundle. Defaults to false.
+    pub tls_require_client_cert: Option<bool>,
+    /// SPIFFE IDs of the clients allowed to connect over TLS. Defaults to any.
+    pub allowed_client_ids: Option<Vec<String>>,
+}
+
+fn default_bind_port() -> u16 {
+    8080
 }
 
 impl HealthChecksConfig {
@@ -30,4 +65,65 @@ impl HealthChecksConfig {
             .clone()
             .unwrap_or_else(|| DEFAULT_READINESS_PATH.to_string())
     }
+
+    #[must_use]
+    pub fn info_path(&self) -> String {
+        self.info_path
+            .clone()
+            .unwrap_or_else(|| DEFAULT_INFO_PATH.to_string())
+    }
+
+    /// Whether the server uses TLS with the current SVID. Defaults to false.
+    #[must_use]
+    pub fn is_tls_enabled(&self) -> bool {
+        self.tls_enabled.unwrap_or(false)
+    }
+
+    /// Whether TLS clients must present a certificate issued by the trust bundle. Defaults to
+    /// false.
+    #[must_use]
+    pub fn requires_client_cert(&self) -> bool {
+        self.tls_require_client_cert.unwrap_or(false)
+    }
+
+    /// The client SPIFFE IDs allowed to reach the endpoints over mutual TLS. Empty, the
+    /// default, allows any client with a certificate from the trust bundle.
+    #[must_use]
+    pub fn allowed_client_ids(&self) -> &[String] {
+        self.allowed_client_ids.as_deref().unwrap_or_default()
+    }
+
+    /// How close to expiry the leaf may get before readiness fails. Zero, the default,
+    /// disables the check.
+    #[must_use]
+    pub fn readiness_expiry_threshold(&self) -> Duration {
+        Duration::from_secs(self.readiness_expiry_threshold_seconds.unwrap_or(0))
+    }
+
+    /// Whether readiness fails once a managed process has exited. Defaults to false.
+    #[must_use]
+    pub fn readiness_requires_child(&self) -> bool {
+        self.readiness_requires_child.unwrap_or(false)
+    }
+
+    /// How many consecutive failed writes of a credential the liveness probe tolerates before
+    /// failing, at least one. Defaults to 1, failing on the first.
+    #[must_use]
+    pub fn liveness_failure_threshold(&self) -> u32 {
+        self.liveness_failure_threshold.unwrap_or(1).max(1)
+    }
+
+    /// The periodic liveness log line, or `None` when `heartbeat_enabled = false`. Defaults to
+    /// `spiffe-helper daemon is alive`.
+    #[must_use]
+    pub fn heartbeat_message(&self) -> Option<String> {
+        if !self.heartbeat_enabled.unwrap_or(true) {
+            return None;
+        }
+        Some(
+            self.heartbeat_message
+                .clone()
+                .unwrap_or_else(|| DEFAULT_HEARTBEAT_MESSAGE.to_string()),
+        )
+    }
 }
@@ -2,6 +2,8 @@ pub mod args;
 pub mod config;
 pub mod health_check;
 
-pub use args::{Args, DEFAULT_CONFIG_FILE};
-pub use config::{parse_hcl_config, Config, JwtSvid};
+pub use args::{Args, Command, DEFAULT_CONFIG_FILE};
+pub use config::{
+    parse_hcl_config, Config, ConfigError, JwtSvid, ManagedProcess, OutputConfig, SignalTarget,
+};
 pub use health_check::HealthChecksConfig;
@@ -0,0 +1,98 @@
+//! Fetches the certificate revocation lists the SPIRE agent sends alongside X.509 SVIDs.
+
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use tonic::codegen::http::uri::PathAndQuery;
+
+use crate::cli::Config;
+use crate::file_system::CredentialSink;
+use crate::info;
+use crate::jwt;
+
+const FETCH_X509_SVID_PATH: &str = "/SpiffeWorkloadAPI/FetchX509SVID";
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Wire types for the `FetchX509SVID` RPC, reduced to the CRL field.
+///
+/// The spiffe crate's X509Source drops the `crl` field of the response, so the CRLs are fetched
+/// directly. Prost skips the SVID and bundle fields it does not declare.
+mod pb {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct X509SvidRequest {}
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct X509SvidResponse {
+        #[prost(bytes = "vec", repeated, tag = "2")]
+        pub crl: Vec<Vec<u8>>,
+    }
+}
+
+/// Fetches the DER-encoded CRLs from the agent's current X.509 SVID response.
+pub async fn fetch_crls(agent_address: &str) -> Result<Vec<Vec<u8>>> {
+    let channel = jwt::connect(agent_address).await?;
+    let mut grpc = tonic::client::Grpc::new(channel);
+    grpc.ready()
+        .await
+        .map_err(|e| anyhow!("SPIRE agent is not ready: {e}"))?;
+
+    let mut request = tonic::Request::new(pb::X509SvidRequest {});
+    request
+        .metadata_mut()
+        .insert(jwt::WORKLOAD_API_HEADER, "true".parse()?);
+
+    let mut stream = grpc
+        .server_streaming::<_, pb::X509SvidResponse, _>(
+            request,
+            PathAndQuery::from_static(FETCH_X509_SVID_PATH),
+            tonic::codec::ProstCodec::default(),
+        )
+        .await
+        .map_err(|e| anyhow!("Failed to fetch CRLs: {}", e.message()))?
+        .into_inner();
+
+    let response = stream
+        .message()
+        .await
+        .map_err(|e| anyhow!("Failed to fetch CRLs: {}", e.message()))?
+        .ok_or_else(|| anyhow!("SPIRE agent closed the X.509 SVID stream without a response"))?;
+
+    Ok(response.crl)
+}
+
+/// Fetches the CRLs from the configured agent, giving up after 30 seconds.
+pub async fn fetch_crls_for_config(config: &Config) -> Result<Vec<Vec<u8>>> {
+    let agent_address = config.agent_address()?;
+    tokio::time::timeout(FETCH_TIMEOUT, fetch_crls(agent_address))
+        .await
+        .map_err(|_| anyhow!("Timed out after {}s fetching CRLs", FETCH_TIMEOUT.as_secs()))?
+}
+
+/// Writes `crls` to `crl_file_name` through `sink`. An empty list is written as an empty file
+/// unless `write_empty_crl` is false. Returns whether anything was written.
+pub fn write_crls<S: CredentialSink>(config: &Config, sink: &S, crls: &[Vec<u8>]) -> Result<bool> {
+    if crls.is_empty() && !config.write_empty_crl() {
+        info!("SPIRE agent sent no CRLs, leaving the CRL file unchanged");
+        return Ok(false);
+    }
+
+    sink.write_crl(crls)?;
+    Ok(true)
+}
+
+/// Fetches and writes the CRLs when `crl_file_name` is configured, logging rather than failing
+/// so that a CRL problem does not stop the daemon from delivering SVIDs.
+pub async fn refresh<S: CredentialSink>(config: &Config, sink: &S) {
+    if config.crl_file_name.is_none() {
+        return;
+    }
+    let result = match fetch_crls_for_config(config).await {
+        Ok(crls) => write_crls(config, sink, &crls).map(|written| (written, crls.len())),
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok((true, count)) => info!("Wrote {count} CRL(s)"),
+        Ok((false, _)) => {}
+        Err(e) => eprintln!("Failed to update CRL file: {e:#}"),
+    }
+}
@@ -1,20 +1,112 @@
 use anyhow::{Context, Result};
-use spiffe::X509Source;
-use std::path::Path;
+use spiffe::bundle::x509::X509Bundle;
+use spiffe::svid::x509::X509Svid;
+use spiffe::{X509Source, X509SourceUpdates};
+use std::collections::hash_map::DefaultHasher;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 use tokio::process::Command;
+#[cfg(unix)]
 use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::{mpsc, watch, Notify};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
 
-use crate::cli::Config;
-use crate::file_system::LocalFileSystem;
+use crate::cli::config::{ON_WRITE_ERROR_EXIT, RENEW_ON_BUNDLE, RENEW_ON_JWT, RENEW_ON_X509};
+use crate::cli::{Config, ManagedProcess};
+use crate::file_system::{self, CredentialSink, LocalFileSystem, MultiSink};
 use crate::health;
+use crate::info;
+use crate::jwt;
 use crate::process;
 use crate::signal;
 use crate::workload_api;
 
+/// What woke the daemon loop up to refresh credentials.
+enum Refresh {
+    /// Re-read the X.509 credentials; `force` rewrites them even if unchanged
+    X509 {
+        force: bool,
+    },
+    Jwt(JwtRefresh),
+}
+
+/// The registered `refresh_signal`. Only Unix has one, as validation rejects it elsewhere.
+#[cfg(unix)]
+type RefreshSignal = tokio::signal::unix::Signal;
+#[cfg(not(unix))]
+type RefreshSignal = std::convert::Infallible;
+
 /// Runs the daemon mode: fetches initial certificate, starts health server,
-/// and waits for SIGTERM.
+/// and waits for SIGTERM or SIGINT.
 pub async fn run(source: X509Source, config: Config) -> Result<()> {
-    println!("Starting spiffe-helper daemon...");
+    let config = workload_api::config_for_source(config, &source)?;
+    if config.is_dry_run() {
+        let sink = file_system::DryRunSink::new(&config);
+        return run_with_sink(source, config, sink).await;
+    }
+    if config.outputs.is_some() {
+        let outputs = MultiSink::local(&config)?;
+        return run_with_sink(source, config, outputs).await;
+    }
+    let local_fs = LocalFileSystem::new(&config)?.ensure()?;
+    run_with_sink(source, config, local_fs).await
+}
+
+/// Like [`run`], but writes credentials to `sink` instead of `cert_dir`.
+pub async fn run_with_sink<S: CredentialSink + 'static>(
+    source: X509Source,
+    config: Config,
+    sink: S,
+) -> Result<()> {
+    run_with_sink_and_health(source, config, sink, None).await
+}
+
+/// The health status and server a caller started before the agent issued an SVID, so that
+/// probes are answered while the helper waits for one.
+pub struct StartupHealth {
+    pub status: health::SharedHealthStatus,
+    pub server: health::HealthCheckServer,
+}
+
+impl StartupHealth {
+    /// Creates the daemon's health status and starts the health check server, if enabled.
+    pub async fn start(config: &Config) -> Result<Self> {
+        let status = create_health_status(config).await;
+        let server =
+            health::HealthCheckServer::new(config.health_checks.as_ref(), status.clone()).await?;
+        Ok(Self { status, server })
+    }
+}
+
+/// Like [`run_with_sink`], but carries on with the health status and server in
+/// `startup_health` when given, instead of starting its own once the credentials are written.
+pub async fn run_with_sink_and_health<S: CredentialSink + 'static>(
+    source: X509Source,
+    config: Config,
+    sink: S,
+    startup_health: Option<StartupHealth>,
+) -> Result<()> {
+    info!("Starting spiffe-helper daemon...");
+
+    // Restored when the daemon returns
+    #[cfg(unix)]
+    let umask_guard = config.umask().map(|mask| {
+        info!("Setting umask to {mask:04o}");
+        crate::file_system::UmaskGuard::set(mask)
+    });
+    // Managed processes and the post-rotation command keep the umask the helper started with
+    #[cfg(unix)]
+    let child_umask = umask_guard
+        .as_ref()
+        .map(crate::file_system::UmaskGuard::previous);
+    #[cfg(not(unix))]
+    let child_umask = None;
 
     // Parse renew signal if configured
     let renew_signal = config
@@ -24,81 +116,212 @@ pub async fn run(source: X509Source, config: Config) -> Result<()> {
         .transpose()
         .context("Failed to parse renew_signal")?;
 
-    println!("Connected to SPIRE agent");
-
-    let local_fs = LocalFileSystem::new(&config)?.ensure()?;
+    let mut processes = config.managed_processes();
+    if config.is_dry_run() {
+        for process in processes.drain(..) {
+            info!("Dry run: not starting managed process {}", process.cmd);
+        }
+    }
+    let process_signals = processes
+        .iter()
+        .map(|p| {
+            p.renew_signal
+                .as_deref()
+                .map(signal::parse_signal_name)
+                .transpose()
+                .with_context(|| format!("Failed to parse renew_signal for process {}", p.cmd))
+        })
+        .collect::<Result<Vec<_>>>()?;
 
-    // Initial fetch and write
-    workload_api::fetch_and_write_x509_svid(&source, &local_fs)?;
+    info!("Connected to SPIRE agent");
 
-    // Spawn managed child process if configured
-    let mut child = if let Some(cmd) = &config.cmd {
-        let mut command = Command::new(cmd);
-        if let Some(args_str) = &config.cmd_args {
-            let args = process::parse_cmd_args(args_str)?;
-            command.args(args);
-        }
-        println!(
-            "Spawning managed process: {cmd} {:?}",
-            config.cmd_args.as_deref().unwrap_or("")
-        );
-        Some(command.spawn().context("Failed to spawn managed process")?)
-    } else {
-        None
+    let (health_status, health_server) = match startup_health {
+        Some(startup) => (startup.status, Some(startup.server)),
+        None => (create_health_status(&config).await, None),
     };
 
-    let mut child_pid = None;
-    if let Some(pid) = child.as_ref().and_then(|c| c.id()) {
-        match i32::try_from(pid) {
-            Ok(pid_i32) => {
-                child_pid = Some(pid_i32);
-            }
-            Err(e) => {
-                eprintln!("Failed to convert PID {pid} to i32: {e}");
-            }
-        }
+    // A ready file left by a previous run would report readiness before this run has written
+    let mut ready_file = config
+        .ready_file
+        .as_deref()
+        .filter(|_| !config.is_dry_run())
+        .map(Path::new);
+    if let Some(path) = ready_file {
+        remove_stale_ready_file(path);
     }
 
-    let mut health_server = health::HealthCheckServer::new(config.health_checks.as_ref()).await?;
+    // Initial fetch and write; the digests let later updates skip unchanged files. Either
+    // failing fails startup before any managed process is spawned.
+    let mut written = workload_api::WrittenDigests::default();
+    let (svid, bundle) = workload_api::fetch_x509_svid_for_config(&source, &config)
+        .inspect_err(|e| eprintln!("Initial X.509 SVID fetch failed: {e}"))?;
+    workload_api::write_changed_x509_svid(&svid, &bundle, &sink, &mut written)
+        .inspect_err(|e| eprintln!("Initial X.509 SVID write failed: {e}"))?;
+    let initial_write = InitialWrite(());
+    crate::crl::refresh(&config, &sink).await;
+    record_x509_success(&health_status, &svid, &bundle).await;
+    let mut agent_svids_task = sync_agent_svids(&config, &health_status, svid.clone(), None);
+    let mut x509_error_log = RateLimitedErrorLog::new(config.write_error_log_interval());
+    let mut x509_write_failures: u32 = 0;
+
+    // Safety net for an agent that stops pushing updates while the leaf approaches expiry
+    let fallback_refresh = tokio::time::sleep(fallback_refresh_interval(&config, &svid));
+    tokio::pin!(fallback_refresh);
+
+    // JWT credentials are not pushed by the X509Source, so they are refreshed on their own timer
+    let jwt_enabled = config.jwt_svids.as_ref().is_some_and(|s| !s.is_empty())
+        || config.jwt_bundle_file_name.is_some();
+    if jwt_enabled {
+        let mut status = health_status.write().await;
+        status.jwt_svids =
+            vec![health::CredentialStatus::default(); config.jwt_svids.iter().flatten().count()];
+        status.jwt_bundle = config
+            .jwt_bundle_file_name
+            .as_ref()
+            .map(|_| health::CredentialStatus::default());
+    }
+
+    let sink = Arc::new(sink);
+    let shutdown = CancellationToken::new();
+    let (jwt_fallback_tx, jwt_fallback_rx) =
+        watch::channel(fallback_refresh_interval(&config, &svid));
+    let (jwt_refresh_tx, mut jwt_refresh_rx) = mpsc::unbounded_channel();
+    let jwt_refresh_now = Arc::new(Notify::new());
+    // A JWT fetch that keeps failing, e.g. for a misconfigured audience, runs in its own task so
+    // it never holds up X.509 rotation
+    let jwt_worker = jwt_enabled.then(|| {
+        tokio::spawn(run_jwt_worker(
+            config.clone(),
+            Arc::clone(&sink),
+            health_status.clone(),
+            jwt_fallback_rx,
+            jwt_refresh_tx,
+            Arc::clone(&jwt_refresh_now),
+            shutdown.clone(),
+        ))
+    });
+
+    // Spawn managed child processes if configured
+    let (exit_tx, mut exit_rx) = mpsc::unbounded_channel();
+    let mut output_relays = Vec::new();
+    let mut children = Vec::with_capacity(processes.len());
+    for (index, (process, renew_signal)) in processes.iter().zip(process_signals).enumerate() {
+        let (child, relays) = spawn_managed_process(
+            &initial_write,
+            process,
+            renew_signal,
+            ChildSettings {
+                capture_output: config.capture_child_output(),
+                umask: child_umask,
+            },
+            &shutdown,
+            exit_tx.clone(),
+            index,
+        )?;
+        children.push(child);
+        output_relays.extend(relays);
+    }
+    // Only the child tasks hold senders, so the channel closes once every child has exited
+    drop(exit_tx);
+
+    let mut health_server = match health_server {
+        Some(server) => server,
+        None => {
+            health::HealthCheckServer::new(config.health_checks.as_ref(), health_status.clone())
+                .await?
+        }
+    };
 
     // Set up signal handling for graceful shutdown
+    #[cfg(unix)]
     let mut sigterm =
         signal(SignalKind::terminate()).context("Failed to register SIGTERM handler")?;
+    #[cfg(unix)]
+    let mut sigint =
+        signal(SignalKind::interrupt()).context("Failed to register SIGINT handler")?;
+    // Windows services and consoles receive a shutdown event in place of SIGTERM
+    #[cfg(windows)]
+    let mut sigterm =
+        tokio::signal::windows::ctrl_shutdown().context("Failed to register shutdown handler")?;
+    #[cfg(windows)]
+    let mut sigint =
+        tokio::signal::windows::ctrl_c().context("Failed to register Ctrl-C handler")?;
+
+    let mut refresh_signal = register_refresh_signal(&config)?;
 
     let mut update_channel = source.updated();
-    println!("Daemon running. Waiting for SIGTERM to shutdown...");
+    info!("Daemon running. Waiting for SIGTERM or SIGINT to shutdown...");
 
     let mut result: Result<()> = Ok(());
+    let mut health_transitions = health::HealthTransitions::default();
+
+    // Armed by the first shutdown signal when a drain period is configured
+    let drain_deadline = tokio::time::sleep(Duration::ZERO);
+    tokio::pin!(drain_deadline);
+    let mut draining = false;
+    let mut post_rotation_hook: Option<JoinHandle<()>> = None;
 
     loop {
-        tokio::select! {
+        let outputs = sink.output_statuses();
+        if !outputs.is_empty() {
+            health_status.write().await.outputs = outputs;
+        }
+        log_health_transitions(&mut health_transitions, &health_status).await;
+        if let Some(path) = ready_file {
+            if health_status.read().await.is_ready() && write_ready_file(path) {
+                ready_file = None;
+            }
+        }
+
+        let refresh = tokio::select! {
             _ = sigterm.recv() => {
-                println!("Received SIGTERM, shutting down gracefully...");
+                if start_drain("SIGTERM", &config, &mut draining, drain_deadline.as_mut()) {
+                    continue;
+                }
+                break;
+            }
+            _ = sigint.recv() => {
+                if start_drain("SIGINT", &config, &mut draining, drain_deadline.as_mut()) {
+                    continue;
+                }
+                break;
+            }
+            () = &mut drain_deadline, if draining => {
+                info!("Drain period elapsed, shutting down gracefully...");
                 break;
             }
             res = update_channel.changed() => {
+                // Coalesce a burst of notifications into a single write and signal
+                let res = match res {
+                    Ok(_) => debounce_updates(&mut update_channel, config.update_debounce()).await,
+                    Err(e) => Err(e.into()),
+                };
                 if let Err(e) = res {
                     eprintln!("Update channel closed: {e}");
                     result = Err(anyhow::anyhow!("X509Source update channel closed"));
                     break;
                 }
 
-                println!("Received X.509 update notification");
-                if let Err(e) = workload_api::fetch_and_write_x509_svid(&source, &local_fs) {
-                    eprintln!("Failed to handle X.509 update: {e}");
-                    continue;
-                }
-
-                send_renew_signal(
-                    renew_signal,
-                    child_pid,
-                    config.pid_file_name.as_deref(),
+                info!("Received X.509 update notification");
+                Refresh::X509 { force: false }
+            }
+            () = &mut fallback_refresh => {
+                info!("No X.509 update received within the refresh interval; re-reading SVID");
+                Refresh::X509 { force: false }
+            }
+            () = refresh_requested(&mut refresh_signal) => {
+                info!(
+                    "Received {}, manual refresh requested; rewriting all credentials",
+                    config.refresh_signal.as_deref().unwrap_or_default()
                 );
+                jwt_refresh_now.notify_one();
+                Refresh::X509 { force: true }
             }
+            Some(refreshed) = jwt_refresh_rx.recv() => Refresh::Jwt(refreshed),
             res = health_server.wait(), if health_server.is_enabled() => {
                 match res {
                     Ok(()) => {
-                        println!("Health check server exited unexpectedly");
+                        info!("Health check server exited unexpectedly");
                     }
                     Err(e) => {
                         eprintln!("Health check server failed: {e}");
@@ -107,22 +330,114 @@ pub async fn run(source: X509Source, config: Config) -> Result<()> {
                 }
                 break;
             }
-            status = async {
-                match child.as_mut() {
-                    Some(child) => child.wait().await,
-                    None => unreachable!(),
+            Some(index) = exit_rx.recv() => {
+                // The exit is logged by the process task; stop signalling the stale PID
+                children[index].pid = None;
+                health_status
+                    .write()
+                    .await
+                    .exited_processes
+                    .push(children[index].cmd.clone());
+                continue;
+            }
+        };
+
+        let (x509_rotated, notify) = match refresh {
+            Refresh::X509 { force } => {
+                if force {
+                    // Forgetting what is on disk makes every file count as changed
+                    written = workload_api::WrittenDigests::default();
                 }
-            }, if child.is_some() => {
-                let status_str = match status {
-                    Ok(s) => s.to_string(),
-                    Err(e) => format!("error: {e}"),
+                let refreshed = refresh_x509(
+                    &source,
+                    &config,
+                    &*sink,
+                    &mut written,
+                    &mut x509_error_log,
+                    &health_status,
+                    fallback_refresh.as_mut(),
+                )
+                .await;
+                let changed = match refreshed {
+                    Ok((changed, svid)) => {
+                        x509_write_failures = 0;
+                        agent_svids_task = sync_agent_svids(
+                            &config,
+                            &health_status,
+                            svid,
+                            agent_svids_task.take(),
+                        );
+                        changed
+                    }
+                    Err(e) => {
+                        x509_write_failures += 1;
+                        if config.on_persistent_write_error() == ON_WRITE_ERROR_EXIT
+                            && x509_write_failures >= config.persistent_write_error_threshold()
+                        {
+                            eprintln!(
+                                "X.509 update failed {x509_write_failures} times in a row and \
+                                 on_persistent_write_error is exit, shutting down"
+                            );
+                            result = Err(e.context(format!(
+                                "X.509 update failed {x509_write_failures} times in a row"
+                            )));
+                            break;
+                        }
+                        false
+                    }
                 };
+                if let Ok(svid) = source.svid() {
+                    jwt_fallback_tx.send_replace(fallback_refresh_interval(&config, &svid));
+                }
+                if !changed {
+                    continue;
+                }
+                (true, config.renew_signal_on(RENEW_ON_X509))
+            }
+            Refresh::Jwt(refreshed) => {
+                if refreshed.failed && config.fail_fast() {
+                    eprintln!("JWT credential refresh failed and fail_fast is set, shutting down");
+                    result = Err(anyhow::anyhow!("JWT credential refresh failed"));
+                    break;
+                }
+                let notify = (refreshed.svids_written && config.renew_signal_on(RENEW_ON_JWT))
+                    || (refreshed.bundle_written && config.renew_signal_on(RENEW_ON_BUNDLE));
+                (false, notify)
+            }
+        };
 
-                child = None;
-                child_pid = None;
-                println!("Managed process exited: {status_str}");
-                // Depending on requirements, we might want to restart it or exit.
-                // For now, we'll just stop managing it and continue running the daemon.
+        if config.is_dry_run() {
+            if notify || (x509_rotated && config.post_rotation_cmd.is_some()) {
+                info!("Dry run: not notifying processes or running post_rotation_cmd");
+            }
+            continue;
+        }
+
+        if notify {
+            settle_before_notify(&config).await;
+            notify_rotation(&config, &children, renew_signal, &health_status);
+        }
+
+        if x509_rotated {
+            if let Some(cmd) = &config.post_rotation_cmd {
+                // The hook runs in its own task so a hung command never holds up the loop
+                if post_rotation_hook
+                    .as_ref()
+                    .is_some_and(|hook| !hook.is_finished())
+                {
+                    eprintln!(
+                        "Post-rotation command {cmd} is still running from an earlier rotation; \
+                         not starting it again"
+                    );
+                } else {
+                    post_rotation_hook = Some(tokio::spawn(run_post_rotation_cmd(
+                        cmd.clone(),
+                        config.post_rotation_cmd_args.clone(),
+                        child_umask,
+                        health_status.clone(),
+                        shutdown.clone(),
+                    )));
+                }
             }
         }
     }
@@ -130,43 +445,1046 @@ pub async fn run(source: X509Source, config: Config) -> Result<()> {
     // Shutdown health check server if it was started and still running
     health_server.shutdown();
 
-    if let Some(mut child) = child {
-        println!("Stopping managed process...");
-        let _ = child.kill().await;
-        let _ = child.wait().await;
+    shutdown.cancel();
+    if let Some(jwt_worker) = jwt_worker {
+        let _ = jwt_worker.await;
+    }
+    if let Some(hook) = post_rotation_hook {
+        let _ = hook.await;
+    }
+    if let Some(task) = agent_svids_task {
+        task.abort();
+    }
+    for child in children {
+        let _ = child.task.await;
+    }
+
+    // Relays end on their own at EOF; abort any still held open (e.g. by a grandchild)
+    for relay in output_relays {
+        relay.abort();
     }
 
-    println!("Daemon shutdown complete");
+    info!("Daemon shutdown complete");
     result
 }
 
-fn send_renew_signal(
-    renew_signal: Option<signal::Signal>,
-    child_pid: Option<i32>,
-    pid_file: Option<&str>,
+/// Creates the health status for `config`, with its liveness failure threshold and, when the
+/// health check server uses TLS, a placeholder identity filled in once an SVID is written.
+async fn create_health_status(config: &Config) -> health::SharedHealthStatus {
+    let health_status = health::create_health_status();
+    if let Some(health_checks) = &config.health_checks {
+        health_status.write().await.liveness_failure_threshold =
+            health_checks.liveness_failure_threshold();
+    }
+    if config
+        .health_checks
+        .as_ref()
+        .is_some_and(|hc| hc.listener_enabled && hc.is_tls_enabled())
+    {
+        health_status.write().await.tls_identity = Some(Default::default());
+    }
+    health_status
+}
+
+/// Handles a shutdown signal: starts the `drain_seconds` period on the first signal and returns
+/// `true` to keep running through it. Returns `false` to shut down now, without a drain period
+/// or on a second signal during one.
+fn start_drain(
+    signal_name: &str,
+    config: &Config,
+    draining: &mut bool,
+    deadline: std::pin::Pin<&mut tokio::time::Sleep>,
+) -> bool {
+    let drain = config.drain_period();
+    if *draining {
+        info!("Received {signal_name} while draining, shutting down immediately...");
+        return false;
+    }
+    if drain.is_zero() {
+        info!("Received {signal_name}, shutting down gracefully...");
+        return false;
+    }
+    info!(
+        "Received {signal_name}, draining for {}s before shutting down...",
+        drain.as_secs()
+    );
+    deadline.reset(Instant::now() + drain);
+    *draining = true;
+    true
+}
+
+/// Registers the handler for `refresh_signal`, if one is configured.
+#[cfg(unix)]
+fn register_refresh_signal(config: &Config) -> Result<Option<RefreshSignal>> {
+    let Some(name) = &config.refresh_signal else {
+        return Ok(None);
+    };
+    let refresh_signal =
+        signal::parse_signal_name(name).context("Failed to parse refresh_signal")?;
+    signal(SignalKind::from_raw(refresh_signal as i32))
+        .with_context(|| format!("Failed to register {name} handler"))
+        .map(Some)
+}
+
+#[cfg(not(unix))]
+fn register_refresh_signal(_config: &Config) -> Result<Option<RefreshSignal>> {
+    Ok(None)
+}
+
+/// Resolves each time the `refresh_signal` arrives, and never when none is registered.
+async fn refresh_requested(refresh_signal: &mut Option<RefreshSignal>) {
+    match refresh_signal {
+        #[cfg(unix)]
+        Some(refresh_signal) => {
+            if refresh_signal.recv().await.is_none() {
+                std::future::pending::<()>().await;
+            }
+        }
+        #[cfg(not(unix))]
+        Some(never) => match *never {},
+        None => std::future::pending().await,
+    }
+}
+
+/// Re-reads the X.509 SVID from `source` and writes it, rescheduling `fallback_refresh`.
+/// Returns whether any file was rewritten along with the SVID written, or the error once it is
+/// logged and recorded in the health status.
+async fn refresh_x509<S: CredentialSink>(
+    source: &X509Source,
+    config: &Config,
+    sink: &S,
+    written: &mut workload_api::WrittenDigests,
+    error_log: &mut RateLimitedErrorLog,
+    health_status: &health::SharedHealthStatus,
+    mut fallback_refresh: std::pin::Pin<&mut tokio::time::Sleep>,
+) -> Result<(bool, Arc<X509Svid>)> {
+    let write_result =
+        workload_api::fetch_x509_svid_for_config(source, config).and_then(|(svid, bundle)| {
+            let changed = if config.write_on_unchanged() {
+                workload_api::write_x509_svid_on_update(&svid, &bundle, sink).map(|()| true)?
+            } else {
+                workload_api::write_changed_x509_svid(&svid, &bundle, sink, written)?
+            };
+            Ok((changed, svid, bundle))
+        });
+    match write_result {
+        Ok((changed, svid, bundle)) => {
+            if let Some(summary) = error_log.success() {
+                eprintln!("{summary}");
+            }
+            crate::crl::refresh(config, sink).await;
+            record_x509_success(health_status, &svid, &bundle).await;
+            fallback_refresh
+                .as_mut()
+                .reset(Instant::now() + fallback_refresh_interval(config, &svid));
+            // Nothing changed on disk, so there is nothing for the process to reload
+            Ok((changed, svid))
+        }
+        Err(e) => {
+            for line in error_log.failure(
+                &format!("Failed to handle X.509 update: {e}"),
+                Instant::now(),
+            ) {
+                eprintln!("{line}");
+            }
+            fallback_refresh
+                .as_mut()
+                .reset(Instant::now() + config.min_refresh_interval());
+            health_status
+                .write()
+                .await
+                .x509_svid
+                .record_failure(e.to_string());
+            Err(e)
+        }
+    }
+}
+
+/// Fetches every SVID the agent issues to the workload after an update that wrote `selected`,
+/// aborting `previous`, the task of an earlier update. With `write_all_svids`, rewrites the
+/// subdirectory of each; while the health check listener is enabled, lists them alongside the
+/// written one on the info endpoint.
+///
+/// The SVIDs are fetched on their own task, bounded by `connect_timeout`, so an unresponsive
+/// agent holds up neither rotation nor shutdown. They are only used if they still include
+/// `selected`; otherwise the agent has rotated since, and its next update fetches them again.
+/// A failure is logged without failing the update, since the selected SVID is already written.
+fn sync_agent_svids(
+    config: &Config,
+    health_status: &health::SharedHealthStatus,
+    selected: Arc<X509Svid>,
+    previous: Option<JoinHandle<()>>,
+) -> Option<JoinHandle<()>> {
+    if let Some(previous) = previous {
+        previous.abort();
+    }
+    // The per-SVID directories are written directly, bypassing the dry run sink
+    let write_all = config.write_all_svids() && !config.is_dry_run();
+    let observe = config
+        .health_checks
+        .as_ref()
+        .is_some_and(|hc| hc.listener_enabled);
+    if !write_all && !observe {
+        return None;
+    }
+    let config = config.clone();
+    let health_status = health_status.clone();
+    Some(tokio::spawn(async move {
+        let context = match config.agent_address() {
+            Ok(agent_address) => {
+                workload_api::fetch_x509_context(agent_address, config.connect_timeout()).await
+            }
+            Err(e) => Err(e),
+        };
+        let context = match context {
+            Ok(context) if workload_api::context_holds_svid(&context, &selected) => context,
+            Ok(_) => {
+                info!(
+                    "The agent no longer issues the SVID of this update; its next update \
+                     fetches every SVID again"
+                );
+                return;
+            }
+            Err(e) => {
+                eprintln!("Failed to fetch every SVID issued to the workload: {e:#}");
+                return;
+            }
+        };
+
+        if write_all {
+            match workload_api::write_all_x509_svids(&config, &context) {
+                Ok(names) => info!("Wrote {} SVIDs: {}", names.len(), names.join(", ")),
+                Err(e) => eprintln!("Failed to write all SVIDs: {e:#}"),
+            }
+        }
+        if observe {
+            let serial = workload_api::svid_info(&selected)
+                .ok()
+                .map(|info| info.serial);
+            let mut status = health_status.write().await;
+            // A later update may already have replaced the SVID on the info endpoint
+            if let Some(info) = status
+                .svid_info
+                .as_mut()
+                .filter(|info| Some(&info.serial) == serial.as_ref())
+            {
+                info.observed_svids = workload_api::observed_svids(&context);
+            }
+        }
+    }))
+}
+
+/// Logs repeated identical errors at most once per interval, so an outage that fails every
+/// update does not flood the log. Suppressed repeats are counted and summarized when the
+/// error changes or clears.
+struct RateLimitedErrorLog {
+    interval: Duration,
+    last_error: Option<String>,
+    last_logged: Option<Instant>,
+    suppressed: u64,
+}
+
+impl RateLimitedErrorLog {
+    fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_error: None,
+            last_logged: None,
+            suppressed: 0,
+        }
+    }
+
+    /// Records `error` at `now`, returning the lines to log: nothing for a suppressed repeat,
+    /// otherwise the error, preceded by a summary of the previous error's suppressed repeats
+    /// when the error changed.
+    fn failure(&mut self, error: &str, now: Instant) -> Vec<String> {
+        let repeat = self.last_error.as_deref() == Some(error);
+        if repeat
+            && self
+                .last_logged
+                .is_some_and(|logged| now.duration_since(logged) < self.interval)
+        {
+            self.suppressed += 1;
+            return Vec::new();
+        }
+
+        let mut lines = Vec::new();
+        if repeat {
+            lines.push(format!(
+                "{error} (repeated {} more times since last logged)",
+                self.suppressed
+            ));
+        } else {
+            lines.extend(self.summary("changed"));
+            lines.push(error.to_string());
+        }
+        self.last_error = Some(error.to_string());
+        self.last_logged = Some(now);
+        self.suppressed = 0;
+        lines
+    }
+
+    /// Records a success, returning the summary of any suppressed repeats to log.
+    fn success(&mut self) -> Option<String> {
+        let summary = self.summary("cleared");
+        self.last_error = None;
+        self.last_logged = None;
+        self.suppressed = 0;
+        summary
+    }
+
+    fn summary(&self, outcome: &str) -> Option<String> {
+        let error = self.last_error.as_ref().filter(|_| self.suppressed > 0)?;
+        Some(format!(
+            "Error {outcome} after {} suppressed repeats: {error}",
+            self.suppressed
+        ))
+    }
+}
+
+/// Outcome of refreshing the JWT SVIDs and the JWT bundle.
+struct JwtRefresh {
+    svids_written: bool,
+    bundle_written: bool,
+    /// Earliest expiry among the written JWT SVIDs, in seconds since the Unix epoch
+    earliest_expiry: Option<i64>,
+    failed: bool,
+}
+
+/// Fetches and writes every configured JWT SVID, and the JWT bundle when it changed since
+/// `bundle_digest`, recording each outcome in the health status.
+async fn refresh_jwt_credentials<S: CredentialSink>(
+    config: &Config,
+    sink: &S,
+    health_status: &health::SharedHealthStatus,
+    bundle_digest: &mut Option<u64>,
+) -> JwtRefresh {
+    let mut refreshed = JwtRefresh {
+        svids_written: false,
+        bundle_written: false,
+        earliest_expiry: None,
+        failed: false,
+    };
+
+    for (index, jwt_svid) in config.jwt_svids.iter().flatten().enumerate() {
+        let result = match jwt::fetch_jwt_svid_for_config(config, jwt_svid).await {
+            Ok(fetched) => sink
+                .write_jwt(&jwt_svid.jwt_svid_file_name, fetched.token())
+                .map(|()| fetched.expiry().unix_timestamp()),
+            Err(e) => Err(e),
+        };
+        let mut status = health_status.write().await;
+        match result {
+            Ok(expiry) => {
+                info!(
+                    "Updated JWT SVID: audience={}, file={}",
+                    jwt_svid.jwt_audience, jwt_svid.jwt_svid_file_name
+                );
+                refreshed.svids_written = true;
+                refreshed.earliest_expiry =
+                    Some(refreshed.earliest_expiry.map_or(expiry, |e| e.min(expiry)));
+                status.jwt_svids[index].record_success();
+            }
+            Err(e) => {
+                eprintln!(
+                    "Failed to refresh JWT SVID {}: {e:#}",
+                    jwt_svid.jwt_svid_file_name
+                );
+                refreshed.failed = true;
+                status.jwt_svids[index].record_failure(format!("{e:#}"));
+            }
+        }
+    }
+
+    if config.jwt_bundle_file_name.is_some() {
+        let result = jwt::fetch_jwt_bundles_for_config(config)
+            .await
+            .and_then(|bundles| {
+                let mut hasher = DefaultHasher::new();
+                bundles.hash(&mut hasher);
+                let digest = hasher.finish();
+                if *bundle_digest == Some(digest) {
+                    return Ok(false);
+                }
+                sink.write_jwt_bundle(&bundles)?;
+                *bundle_digest = Some(digest);
+                Ok(true)
+            });
+        let mut status = health_status.write().await;
+        let bundle_status = status.jwt_bundle.get_or_insert_with(Default::default);
+        match result {
+            Ok(written) => {
+                if written {
+                    info!("Updated JWT bundle");
+                }
+                refreshed.bundle_written = written;
+                bundle_status.record_success();
+            }
+            Err(e) => {
+                eprintln!("Failed to refresh JWT bundle: {e:#}");
+                refreshed.failed = true;
+                bundle_status.record_failure(format!("{e:#}"));
+            }
+        }
+    }
+
+    refreshed
+}
+
+/// Refreshes the JWT credentials from startup until `shutdown`, independently of the X.509
+/// updates handled by the daemon loop, and reports each refresh on `refreshes`.
+///
+/// `fallback_interval` holds the current X.509 fallback refresh interval, used when no JWT
+/// SVID was written. `refresh_now` cuts the wait before the next refresh short.
+async fn run_jwt_worker<S: CredentialSink>(
+    config: Config,
+    sink: Arc<S>,
+    health_status: health::SharedHealthStatus,
+    fallback_interval: watch::Receiver<Duration>,
+    refreshes: mpsc::UnboundedSender<JwtRefresh>,
+    refresh_now: Arc<Notify>,
+    shutdown: CancellationToken,
 ) {
-    let Some(sig) = renew_signal else {
+    let mut bundle_digest = None;
+    loop {
+        let refresh = refresh_jwt_credentials(&config, &*sink, &health_status, &mut bundle_digest);
+        let refreshed = tokio::select! {
+            () = shutdown.cancelled() => return,
+            refreshed = refresh => refreshed,
+        };
+        let interval = jwt_refresh_interval(&config, *fallback_interval.borrow(), &refreshed);
+        if refreshes.send(refreshed).is_err() {
+            return;
+        }
+        tokio::select! {
+            () = shutdown.cancelled() => return,
+            () = tokio::time::sleep(interval) => {}
+            () = refresh_now.notified() => {}
+        }
+    }
+}
+
+/// Time until the JWT credentials are refreshed: half of the shortest-lived JWT SVID's
+/// remaining lifetime, or `fallback` when no JWT SVID was written, clamped by the configured
+/// refresh bounds. A failed refresh is retried after `min_refresh_interval`.
+fn jwt_refresh_interval(config: &Config, fallback: Duration, refreshed: &JwtRefresh) -> Duration {
+    if refreshed.failed {
+        return config.min_refresh_interval();
+    }
+
+    let Some(expiry) = refreshed.earliest_expiry else {
+        return fallback;
+    };
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| i64::try_from(d.as_secs()).unwrap_or(i64::MAX));
+    let remaining = Duration::from_secs(u64::try_from(expiry - now).unwrap_or(0));
+    config.clamp_refresh_interval(remaining / 2)
+}
+
+/// Signals the managed processes, the `pid_file_name` process, and the `signal_targets`
+/// processes and writes the renew sentinel after a rotation, then watches for the confirmation
+/// file if one is configured.
+/// With `renew_signal_delay_ms` set, syncs each output directory and then waits out the delay,
+/// so that a process reloading on the signal does not read a file a slow filesystem has not
+/// made visible yet.
+async fn settle_before_notify(config: &Config) {
+    let delay = config.renew_signal_delay();
+    if delay.is_zero() {
         return;
+    }
+
+    let cert_dirs: Vec<PathBuf> = config
+        .output_configs()
+        .iter()
+        .filter_map(|output| output.cert_dir.as_ref().map(PathBuf::from))
+        .collect();
+    // fsync blocks, so it runs off the async runtime
+    let synced = tokio::task::spawn_blocking(move || {
+        for cert_dir in cert_dirs {
+            if let Err(e) = file_system::sync_dir(&cert_dir) {
+                eprintln!("{e:#}");
+            }
+        }
+    })
+    .await;
+    if let Err(e) = synced {
+        eprintln!("Failed to sync output directories: {e}");
+    }
+    tokio::time::sleep(delay).await;
+}
+
+fn notify_rotation(
+    config: &Config,
+    children: &[ManagedChild],
+    renew_signal: Option<signal::Signal>,
+    health_status: &health::SharedHealthStatus,
+) {
+    // Capture the confirm file's mtime before signalling so a fast handler is not missed
+    let confirm_file = config.renew_signal_confirm_file.as_ref().map(PathBuf::from);
+    let confirm_baseline = confirm_file.as_deref().and_then(modified_time);
+
+    let signalled_children = signal_managed_processes(children);
+    let signalled_pid_file = signal_pid_file(renew_signal, config.pid_file_name.as_deref());
+    let signalled_targets = signal_targets(config);
+    let wrote_sentinel = config.renew_sentinel_file.as_deref().is_some_and(|path| {
+        signal::write_renew_sentinel(Path::new(path))
+            .map_err(|e| eprintln!("{e}"))
+            .is_ok()
+    });
+
+    if let Some(confirm_file) = confirm_file {
+        if signalled_children || signalled_pid_file || signalled_targets || wrote_sentinel {
+            tokio::spawn(await_renew_confirmation(
+                confirm_file,
+                confirm_baseline,
+                config.renew_signal_confirm_timeout(),
+                health_status.clone(),
+            ));
+        }
+    }
+}
+
+/// Time until the daemon re-reads the source without a push: half of the leaf's remaining
+/// validity, clamped by the configured refresh bounds.
+fn fallback_refresh_interval(config: &Config, svid: &X509Svid) -> Duration {
+    workload_api::refresh_interval_for(
+        svid,
+        config.min_refresh_interval(),
+        config.max_refresh_interval(),
+    )
+}
+
+/// Removes a `ready_file` left behind by an earlier run, logging any failure other than the
+/// file being absent.
+fn remove_stale_ready_file(path: &Path) {
+    if let Err(e) = std::fs::remove_file(path) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            eprintln!("Failed to remove stale ready file {}: {e}", path.display());
+        }
+    }
+}
+
+/// Creates `ready_file`, holding the time every configured credential was first written in
+/// seconds since the Unix epoch. Returns whether it was written; a failure is logged and
+/// retried on the next refresh.
+fn write_ready_file(path: &Path) -> bool {
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    match std::fs::write(path, format!("{now}\n")) {
+        Ok(()) => {
+            info!(
+                "Initial credentials written; created ready file {}",
+                path.display()
+            );
+            true
+        }
+        Err(e) => {
+            eprintln!("Failed to write ready file {}: {e}", path.display());
+            false
+        }
+    }
+}
+
+/// Logs each liveness or readiness change since the last call, degradations as warnings.
+async fn log_health_transitions(
+    transitions: &mut health::HealthTransitions,
+    health_status: &health::SharedHealthStatus,
+) {
+    for transition in transitions.observe(&*health_status.read().await) {
+        if transition.healthy {
+            info!("{transition}");
+        } else {
+            eprintln!("Warning: {transition}");
+        }
+    }
+}
+
+/// Records a successful X.509 write along with the identity of the written SVID, and serves
+/// the SVID from the health check server when it uses TLS.
+async fn record_x509_success(
+    health_status: &health::SharedHealthStatus,
+    svid: &X509Svid,
+    bundle: &X509Bundle,
+) {
+    let mut status = health_status.write().await;
+    status.x509_svid.record_success();
+    status.svid_info = workload_api::svid_info(svid).ok();
+    if let Some(identity) = &status.tls_identity {
+        if let Err(e) = identity.update(svid, bundle) {
+            eprintln!("{e:#}");
+        }
+    }
+}
+
+/// A source of change notifications, abstracted so that debouncing can be exercised without
+/// an agent.
+trait UpdateNotifications {
+    fn changed(&mut self) -> impl Future<Output = Result<()>> + Send;
+}
+
+impl UpdateNotifications for X509SourceUpdates {
+    async fn changed(&mut self) -> Result<()> {
+        X509SourceUpdates::changed(self).await?;
+        Ok(())
+    }
+}
+
+/// How many debounce windows a steady stream of notifications may postpone a write for.
+const MAX_DEBOUNCE_WINDOWS: u32 = 4;
+
+/// Called after a notification arrives; absorbs further notifications until none has arrived
+/// for `window`, or until `MAX_DEBOUNCE_WINDOWS` windows have passed so that continuous updates
+/// cannot hold back the write indefinitely.
+async fn debounce_updates<U: UpdateNotifications>(updates: &mut U, window: Duration) -> Result<()> {
+    if window.is_zero() {
+        return Ok(());
+    }
+
+    let deadline = Instant::now() + window * MAX_DEBOUNCE_WINDOWS;
+    loop {
+        let wait = window.min(deadline.saturating_duration_since(Instant::now()));
+        if wait.is_zero() {
+            return Ok(());
+        }
+        match tokio::time::timeout(wait, updates.changed()).await {
+            Ok(res) => res?,
+            Err(_) => return Ok(()),
+        }
+    }
+}
+
+/// Runs the post-rotation command to completion and records the outcome in the health status.
+/// A failure is logged and reported there rather than stopping the daemon. The command is
+/// killed, and nothing is recorded, if `shutdown` is cancelled while it runs.
+async fn run_post_rotation_cmd(
+    cmd: String,
+    args_str: Option<String>,
+    umask: Option<u32>,
+    health_status: health::SharedHealthStatus,
+    shutdown: CancellationToken,
+) {
+    info!(
+        "Running post-rotation command: {cmd} {:?}",
+        args_str.as_deref().unwrap_or("")
+    );
+
+    let status = tokio::select! {
+        status = process::run_to_completion(&cmd, args_str.as_deref(), umask) => status,
+        () = shutdown.cancelled() => {
+            info!("Stopping post-rotation command {cmd}...");
+            return;
+        }
+    };
+    let error = match status {
+        Ok(status) if status.success() => {
+            info!("Post-rotation command exited: {status}");
+            None
+        }
+        Ok(status) => Some(format!("Post-rotation command exited: {status}")),
+        Err(e) => Some(format!("Post-rotation command failed: {e}")),
+    };
+
+    if let Some(error) = &error {
+        eprintln!("{error}");
+    }
+
+    health_status.write().await.post_rotation_cmd = Some(health::HookStatus {
+        succeeded: error.is_none(),
+        last_run: Some(SystemTime::now()),
+        last_error: error,
+    });
+}
+
+/// A process spawned by the daemon. The task owning the `Child` waits for it to exit and
+/// kills it when the daemon shuts down.
+struct ManagedChild {
+    cmd: String,
+    pid: Option<i32>,
+    renew_signal: Option<signal::Signal>,
+    task: JoinHandle<()>,
+}
+
+/// Proof that the initial certificate, key and bundle writes succeeded. Only the daemon's
+/// startup creates one, after those writes, and a managed process cannot be spawned without it.
+struct InitialWrite(());
+
+/// How the daemon sets up every managed process it spawns.
+struct ChildSettings {
+    /// Relay the process's stdout and stderr instead of letting it inherit the helper's
+    capture_output: bool,
+    /// Umask the process runs with instead of the helper's
+    #[cfg_attr(not(unix), allow(dead_code))]
+    umask: Option<u32>,
+}
+
+/// Spawns `process` and a task that reports its exit on `exits` as `index`, or kills it once
+/// `shutdown` is cancelled. Returns the child along with its output relay tasks.
+///
+/// Taking an [`InitialWrite`] guarantees the process never starts before its credentials exist.
+fn spawn_managed_process(
+    _initial_write: &InitialWrite,
+    process: &ManagedProcess,
+    renew_signal: Option<signal::Signal>,
+    settings: ChildSettings,
+    shutdown: &CancellationToken,
+    exits: mpsc::UnboundedSender<usize>,
+    index: usize,
+) -> Result<(ManagedChild, Vec<JoinHandle<()>>)> {
+    let mut command = Command::new(&process.cmd);
+    // Never leave the process behind if its task is dropped without a graceful shutdown
+    command.kill_on_drop(true);
+    if let Some(args_str) = &process.cmd_args {
+        let args = process::parse_cmd_args(args_str)?;
+        command.args(args);
+    }
+    if settings.capture_output {
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+    #[cfg(unix)]
+    if let Some(mask) = settings.umask {
+        process::set_child_umask(&mut command, mask);
+    }
+    info!(
+        "Spawning managed process: {} {:?}",
+        process.cmd,
+        process.cmd_args.as_deref().unwrap_or("")
+    );
+    let mut child = command
+        .spawn()
+        .with_context(|| format!("Failed to spawn managed process {}", process.cmd))?;
+    let relays = process::spawn_output_relays(&mut child);
+
+    let pid = child.id().and_then(|pid| match i32::try_from(pid) {
+        Ok(pid_i32) => Some(pid_i32),
+        Err(e) => {
+            eprintln!("Failed to convert PID {pid} to i32: {e}");
+            None
+        }
+    });
+
+    let cmd = process.cmd.clone();
+    let shutdown = shutdown.clone();
+    let task = tokio::spawn(async move {
+        tokio::select! {
+            status = child.wait() => {
+                let status_str = match status {
+                    Ok(s) => s.to_string(),
+                    Err(e) => format!("error: {e}"),
+                };
+                info!("Managed process {cmd} exited: {status_str}");
+                // Depending on requirements, we might want to restart it or exit.
+                // For now, we'll just stop managing it and continue running the daemon.
+                let _ = exits.send(index);
+            }
+            () = shutdown.cancelled() => {
+                info!("Stopping managed process {cmd}...");
+                let _ = child.kill().await;
+                let _ = child.wait().await;
+            }
+        }
+    });
+
+    let child = ManagedChild {
+        cmd: process.cmd.clone(),
+        pid,
+        renew_signal,
+        task,
     };
+    Ok((child, relays))
+}
+
+/// Sends each running managed process its own renew signal. Returns whether any was signalled.
+fn signal_managed_processes(children: &[ManagedChild]) -> bool {
+    let mut signalled = false;
+    for child in children {
+        let (Some(sig), Some(pid)) = (child.renew_signal, child.pid) else {
+            continue;
+        };
 
-    if let Some(pid) = child_pid {
-        println!("Sending signal {sig:?} to managed process (PID: {pid})");
-        if let Err(e) = signal::send_signal(pid, sig) {
-            eprintln!("Failed to signal managed process: {e}");
+        info!(
+            "Sending signal {sig:?} to managed process {} (PID: {pid})",
+            child.cmd
+        );
+        match signal::send_signal(pid, sig) {
+            Ok(()) => signalled = true,
+            Err(e) => eprintln!("Failed to signal managed process {}: {e}", child.cmd),
         }
     }
+    signalled
+}
 
-    if let Some(pid_file) = pid_file {
-        match signal::read_pid_from_file(Path::new(pid_file)) {
-            Ok(pid) => {
-                println!("Sending signal {sig:?} to process from PID file {pid_file} (PID: {pid})");
-                if let Err(e) = signal::send_signal(pid, sig) {
+/// Sends the renew signal to the process in `pid_file`. Returns whether it was signalled.
+///
+/// The file is read afresh on every rotation, so a process that restarted and rewrote it since
+/// the last rotation is signalled at its new PID. A PID whose process is no longer running is
+/// skipped with a warning rather than signalled.
+fn signal_pid_file(renew_signal: Option<signal::Signal>, pid_file: Option<&str>) -> bool {
+    let (Some(sig), Some(pid_file)) = (renew_signal, pid_file) else {
+        return false;
+    };
+
+    match signal::read_pid_from_file(Path::new(pid_file)) {
+        Ok(pid) if !signal::is_process_running(pid) => {
+            eprintln!(
+                "Warning: PID file {pid_file} names process {pid}, which is not running; \
+                 skipping renew signal"
+            );
+            false
+        }
+        Ok(pid) => {
+            info!("Sending signal {sig:?} to process from PID file {pid_file} (PID: {pid})");
+            match signal::send_signal(pid, sig) {
+                Ok(()) => true,
+                Err(e) => {
                     eprintln!("Failed to signal process from PID file: {e}");
+                    false
                 }
             }
-            Err(e) => {
-                eprintln!("Failed to read PID from file {pid_file}: {e}");
+        }
+        Err(e) => {
+            eprintln!("Failed to read PID from file {pid_file}: {e}");
+            false
+        }
+    }
+}
+
+/// Sends each `signal_targets` entry its own signal, skipping processes that are not running as
+/// [`signal_pid_file`] does. Returns whether any was signalled.
+fn signal_targets(config: &Config) -> bool {
+    let mut signalled = false;
+    for target in config.signal_targets.iter().flatten() {
+        match signal::parse_signal_name(&target.signal) {
+            Ok(sig) => signalled |= signal_pid_file(Some(sig), Some(&target.pid_file)),
+            Err(e) => eprintln!(
+                "Failed to signal process from PID file {}: {e}",
+                target.pid_file
+            ),
+        }
+    }
+    signalled
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// How often the renew signal confirm file is checked for a newer modification time
+const CONFIRM_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Waits up to `timeout` for `confirm_file` to be modified after `baseline`, the modification
+/// time captured just before signalling, and records the outcome in the health status.
+async fn await_renew_confirmation(
+    confirm_file: PathBuf,
+    baseline: Option<SystemTime>,
+    timeout: Duration,
+    health_status: health::SharedHealthStatus,
+) {
+    let confirmed = tokio::time::timeout(timeout, async {
+        loop {
+            let modified = modified_time(&confirm_file);
+            if modified.is_some() && modified > baseline {
+                return;
             }
+            tokio::time::sleep(CONFIRM_POLL_INTERVAL).await;
         }
+    })
+    .await
+    .is_ok();
+
+    let error = if confirmed {
+        info!(
+            "Renew signal confirmed: {} was updated",
+            confirm_file.display()
+        );
+        None
+    } else {
+        let error = format!(
+            "Renew signal not confirmed: {} was not updated within {}s",
+            confirm_file.display(),
+            timeout.as_secs()
+        );
+        eprintln!("{error}");
+        Some(error)
+    };
+
+    health_status.write().await.renew_signal_confirmation = Some(health::HookStatus {
+        succeeded: error.is_none(),
+        last_run: Some(SystemTime::now()),
+        last_error: error,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::watch;
+
+    #[test]
+    fn test_repeated_errors_are_logged_once_then_summarized() {
+        let mut log = RateLimitedErrorLog::new(Duration::from_secs(60));
+        let start = Instant::now();
+
+        let logged: Vec<String> = (0..10)
+            .flat_map(|i| {
+                log.failure(
+                    "Failed to write: read-only",
+                    start + Duration::from_millis(i * 100),
+                )
+            })
+            .collect();
+        assert_eq!(logged, ["Failed to write: read-only"]);
+
+        assert_eq!(
+            log.success().as_deref(),
+            Some("Error cleared after 9 suppressed repeats: Failed to write: read-only")
+        );
+        assert_eq!(log.success(), None);
+    }
+
+    #[test]
+    fn test_repeated_error_is_logged_again_after_interval() {
+        let mut log = RateLimitedErrorLog::new(Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert_eq!(log.failure("disk full", start).len(), 1);
+        assert!(log
+            .failure("disk full", start + Duration::from_secs(30))
+            .is_empty());
+        assert_eq!(
+            log.failure("disk full", start + Duration::from_secs(61)),
+            ["disk full (repeated 1 more times since last logged)"]
+        );
+        assert_eq!(log.success(), None);
+    }
+
+    #[test]
+    fn test_changed_error_is_logged_with_previous_summary() {
+        let mut log = RateLimitedErrorLog::new(Duration::from_secs(60));
+        let start = Instant::now();
+
+        log.failure("disk full", start);
+        log.failure("disk full", start);
+        assert_eq!(
+            log.failure("permission denied", start),
+            [
+                "Error changed after 1 suppressed repeats: disk full",
+                "permission denied"
+            ]
+        );
+    }
+
+    impl UpdateNotifications for watch::Receiver<u64> {
+        async fn changed(&mut self) -> Result<()> {
+            watch::Receiver::changed(self).await?;
+            Ok(())
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_signal_pid_file_skips_process_that_is_not_running() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let pid_file = temp_dir.path().join("app.pid");
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        child.wait().unwrap();
+        std::fs::write(&pid_file, child.id().to_string()).unwrap();
+
+        let signalled = signal_pid_file(Some(signal::Signal::SIGWINCH), pid_file.to_str());
+        assert!(!signalled);
+
+        // The file is re-read on every call, so a rewritten PID is picked up
+        std::fs::write(&pid_file, std::process::id().to_string()).unwrap();
+        let signalled = signal_pid_file(Some(signal::Signal::SIGWINCH), pid_file.to_str());
+        assert!(signalled);
+    }
+
+    #[tokio::test]
+    async fn test_await_renew_confirmation_observes_touched_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let confirm_file = temp_dir.path().join("reloaded");
+        std::fs::write(&confirm_file, "").unwrap();
+        let baseline = modified_time(&confirm_file);
+        let health_status = health::create_health_status();
+
+        let waiter = tokio::spawn(await_renew_confirmation(
+            confirm_file.clone(),
+            baseline,
+            Duration::from_secs(5),
+            health_status.clone(),
+        ));
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        std::fs::write(&confirm_file, "reloaded").unwrap();
+        waiter.await.unwrap();
+
+        let status = health_status.read().await;
+        let confirmation = status.renew_signal_confirmation.as_ref().unwrap();
+        assert!(confirmation.succeeded);
+        assert!(confirmation.last_error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_await_renew_confirmation_times_out_without_touch() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let confirm_file = temp_dir.path().join("reloaded");
+        let health_status = health::create_health_status();
+
+        await_renew_confirmation(
+            confirm_file,
+            None,
+            Duration::from_millis(300),
+            health_status.clone(),
+        )
+        .await;
+
+        let status = health_status.read().await;
+        let confirmation = status.renew_signal_confirmation.as_ref().unwrap();
+        assert!(!confirmation.succeeded);
+        assert!(confirmation
+            .last_error
+            .as_deref()
+            .unwrap()
+            .contains("not confirmed"));
+    }
+
+    #[tokio::test]
+    async fn test_debounce_zero_window_returns_immediately() {
+        let (_tx, mut rx) = watch::channel(0u64);
+
+        let result = tokio::time::timeout(
+            Duration::from_millis(50),
+            debounce_updates(&mut rx, Duration::ZERO),
+        )
+        .await;
+
+        assert!(matches!(result, Ok(Ok(()))));
+    }
+
+    #[tokio::test]
+    async fn test_debounce_returns_after_max_wait_despite_steady_updates() {
+        let (tx, mut rx) = watch::channel(0u64);
+        let sender = tokio::spawn(async move {
+            for version in 1.. {
+                if tx.send(version).is_err() {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        });
+
+        let window = Duration::from_millis(100);
+        let started = Instant::now();
+        let result =
+            tokio::time::timeout(Duration::from_secs(5), debounce_updates(&mut rx, window)).await;
+        sender.abort();
+
+        assert!(matches!(result, Ok(Ok(()))), "Debounce never returned");
+        let elapsed = started.elapsed();
+        assert!(
+            elapsed >= window * MAX_DEBOUNCE_WINDOWS && elapsed < Duration::from_secs(2),
+            "Debounce returned after {elapsed:?}"
+        );
     }
 }
@@ -0,0 +1,65 @@
+//! In-memory credential fetching for embedding spiffe-helper's agent connection handling in
+//! other Rust services. Nothing is written to disk.
+//!
+//! ```no_run
+//! # async fn example() -> anyhow::Result<()> {
+//! let svid = spiffe_helper::fetch::fetch_x509_svid("unix:///run/spire/sockets/agent.sock").await?;
+//! println!("Fetched {}", svid.spiffe_id());
+//!
+//! let audiences = ["my-service".to_string()];
+//! let jwt = spiffe_helper::fetch::fetch_jwt_svid("unix:///run/spire/sockets/agent.sock", &audiences)
+//!     .await?;
+//! println!("Token expires at {}", jwt.expiry());
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{anyhow, Result};
+use spiffe::svid::jwt::JwtSvid;
+use spiffe::svid::x509::X509Svid;
+
+use crate::cli::Config;
+use crate::{jwt, workload_api};
+
+/// Connects to the SPIRE agent at `agent_address` and returns its current X.509 SVID.
+///
+/// Uses the same connection handling as the helper itself, including abstract socket
+/// addresses, and waits for the agent's first response with the same reconnect backoff.
+pub async fn fetch_x509_svid(agent_address: &str) -> Result<X509Svid> {
+    workload_api::validate_agent_address(agent_address)?;
+
+    let source = workload_api::create_x509_source(agent_address).await?;
+    let svid = source
+        .svid()
+        .map_err(|e| anyhow!("Failed to get SVID: {e}"));
+    source.shutdown().await;
+
+    svid.map(|svid| (*svid).clone())
+}
+
+/// Fetches a JWT SVID for `audiences` (at least one) from the SPIRE agent at `agent_address`.
+///
+/// Failed attempts are retried with exponential backoff like the helper's own `jwt_svids`
+/// fetches, bounded by the default `jwt_fetch_timeout_seconds`.
+pub async fn fetch_jwt_svid(agent_address: &str, audiences: &[String]) -> Result<JwtSvid> {
+    workload_api::validate_agent_address(agent_address)?;
+    if audiences.is_empty() {
+        anyhow::bail!("At least one audience is required to fetch a JWT SVID");
+    }
+
+    let config = Config::default();
+    let timeout = config.jwt_fetch_timeout();
+    let fetch = jwt::fetch_with_retry(
+        agent_address,
+        audiences,
+        config.jwt_retry_max_attempts(),
+        jwt::retry_backoff(&config),
+    );
+    tokio::time::timeout(timeout, fetch).await.map_err(|_| {
+        anyhow!(
+            "Timed out after {}s fetching JWT SVID for audience {}",
+            timeout.as_secs(),
+            audiences[0]
+        )
+    })?
+}
@@ -0,0 +1,121 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use spiffe::bundle::x509::X509Bundle;
+use spiffe::cert::Certificate;
+
+use super::{
+    certificates_pem, crls_pem, jwt_bundle_content, jwt_file_content, key_pem, pem_line_ending,
+    CredentialSink,
+};
+use crate::chain_inspect;
+use crate::cli::Config;
+use crate::info;
+use crate::jwt::JwtBundles;
+use crate::jwt_inspect::format_timestamp;
+
+/// A [`CredentialSink`] for `--dry-run` that logs each file the helper would write, with its
+/// size, instead of writing it. Sizes are those of the content [`super::LocalFileSystem`]
+/// would write with the same config.
+#[derive(Debug)]
+pub struct DryRunSink {
+    output_dir: PathBuf,
+    cert_file_name: String,
+    key_file_name: String,
+    bundle_file_name: String,
+    jwt_bundle_file_name: Option<String>,
+    jwt_bundle_format: String,
+    jwt_bundle_compression: String,
+    crl_file_name: Option<String>,
+    jwt_trailing_newline: bool,
+    line_ending: pem::LineEnding,
+}
+
+impl DryRunSink {
+    #[must_use]
+    pub fn new(config: &Config) -> Self {
+        Self {
+            output_dir: PathBuf::from(config.cert_dir.as_deref().unwrap_or_default()),
+            cert_file_name: config.svid_file_name().to_string(),
+            key_file_name: config.svid_key_file_name().to_string(),
+            bundle_file_name: config.svid_bundle_file_name().to_string(),
+            jwt_bundle_file_name: config.jwt_bundle_file_name.clone(),
+            jwt_bundle_format: config.jwt_bundle_format().to_string(),
+            jwt_bundle_compression: config.jwt_bundle_compression().to_string(),
+            crl_file_name: config.crl_file_name.clone(),
+            jwt_trailing_newline: config.jwt_trailing_newline(),
+            line_ending: pem_line_ending(config),
+        }
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.output_dir.join(name)
+    }
+
+    fn log(path: &Path, size: usize, details: &str) {
+        info!(
+            "Dry run: would write {} ({size} bytes{details})",
+            path.display()
+        );
+    }
+}
+
+impl CredentialSink for DryRunSink {
+    fn write_cert_chain(&self, certificates: &[Certificate]) -> Result<()> {
+        let content = certificates_pem(certificates, self.line_ending);
+        let leaf = certificates
+            .first()
+            .and_then(|leaf| chain_inspect::describe_der(leaf.as_ref()).ok());
+        let details = leaf.map_or_else(String::new, |leaf| {
+            format!(
+                ", SPIFFE ID {}, expires {}",
+                leaf.spiffe_ids.join(", "),
+                format_timestamp(leaf.not_after)
+            )
+        });
+        Self::log(&self.path(&self.cert_file_name), content.len(), &details);
+        Ok(())
+    }
+
+    fn write_key(&self, key: &[u8]) -> Result<()> {
+        let content = key_pem(key, self.line_ending);
+        Self::log(&self.path(&self.key_file_name), content.len(), "");
+        Ok(())
+    }
+
+    fn write_bundle(&self, bundle: &X509Bundle) -> Result<()> {
+        let content = certificates_pem(bundle.authorities(), self.line_ending);
+        let details = format!(", {} authorities", bundle.authorities().len());
+        Self::log(&self.path(&self.bundle_file_name), content.len(), &details);
+        Ok(())
+    }
+
+    fn write_jwt(&self, name: &str, token: &str) -> Result<()> {
+        let content = jwt_file_content(token, self.jwt_trailing_newline);
+        Self::log(&self.path(name), content.len(), "");
+        Ok(())
+    }
+
+    fn write_jwt_bundle(&self, bundles: &JwtBundles) -> Result<()> {
+        let Some(name) = &self.jwt_bundle_file_name else {
+            return Ok(());
+        };
+        let content = jwt_bundle_content(
+            bundles,
+            &self.jwt_bundle_format,
+            &self.jwt_bundle_compression,
+        )?;
+        let details = format!(", {} trust domains", bundles.len());
+        Self::log(&self.path(name), content.len(), &details);
+        Ok(())
+    }
+
+    fn write_crl(&self, crls: &[Vec<u8>]) -> Result<()> {
+        let Some(name) = &self.crl_file_name else {
+            return Ok(());
+        };
+        let content = crls_pem(crls, self.line_ending);
+        Self::log(&self.path(name), content.len(), "");
+        Ok(())
+    }
+}
@@ -0,0 +1,186 @@
+//! Names bundle authorities the way OpenSSL looks them up in a `-CApath` directory: by the hash
+//! of the certificate's subject, as printed by `openssl x509 -hash`.
+
+use anyhow::{anyhow, Result};
+use spiffe::cert::Certificate;
+
+/// ASN.1 string types OpenSSL converts to a canonical UTF8String before hashing a name.
+const TAG_UTF8_STRING: u32 = 12;
+const TAG_PRINTABLE_STRING: u32 = 19;
+const TAG_T61_STRING: u32 = 20;
+const TAG_IA5_STRING: u32 = 22;
+const TAG_VISIBLE_STRING: u32 = 26;
+const TAG_UNIVERSAL_STRING: u32 = 28;
+const TAG_BMP_STRING: u32 = 30;
+
+/// File names for `authorities` in a hashed directory, in order: `<subject hash>.<n>`, where
+/// `n` counts earlier authorities with the same subject hash.
+pub(crate) fn hashed_file_names(authorities: &[Certificate]) -> Result<Vec<String>> {
+    let mut names: Vec<String> = Vec::with_capacity(authorities.len());
+    for authority in authorities {
+        let hash = format!("{:08x}", subject_hash(authority.as_ref())?);
+        let index = names
+            .iter()
+            .filter(|name| name.split('.').next() == Some(hash.as_str()))
+            .count();
+        names.push(format!("{hash}.{index}"));
+    }
+    Ok(names)
+}
+
+/// Whether `name` is a file name [`hashed_file_names`] produces.
+pub(crate) fn is_hashed_file_name(name: &str) -> bool {
+    name.split_once('.').is_some_and(|(hash, index)| {
+        hash.len() == 8
+            && hash.bytes().all(|b| b.is_ascii_hexdigit())
+            && !index.is_empty()
+            && index.bytes().all(|b| b.is_ascii_digit())
+    })
+}
+
+/// OpenSSL's subject name hash (`X509_NAME_hash`): the first four bytes, little-endian, of the
+/// SHA-1 digest of the subject's canonical encoding.
+pub(crate) fn subject_hash(der: &[u8]) -> Result<u32> {
+    let (_, cert) = x509_parser::parse_x509_certificate(der)
+        .map_err(|e| anyhow!("Failed to parse bundle authority: {e}"))?;
+
+    // The canonical encoding is each RDN's SET, concatenated without the outer SEQUENCE
+    let mut canonical = Vec::new();
+    for rdn in cert.subject().iter() {
+        let mut entries: Vec<Vec<u8>> = rdn
+            .iter()
+            .map(|attribute| {
+                let value = attribute.attr_value();
+                let value = match canonical_string(value.header.tag().0, value.data) {
+                    Some(text) => der_tlv(TAG_UTF8_STRING as u8, text.as_bytes()),
+                    None => der_tlv(value.header.tag().0 as u8, value.data),
+                };
+                let mut entry = der_tlv(0x06, attribute.attr_type().as_bytes());
+                entry.extend(value);
+                der_tlv(0x30, &entry)
+            })
+            .collect();
+        // DER orders the members of a SET OF by their encoding
+        entries.sort();
+        canonical.extend(der_tlv(0x31, &entries.concat()));
+    }
+
+    let digest = ring::digest::digest(&ring::digest::SHA1_FOR_LEGACY_USE_ONLY, &canonical);
+    let bytes = digest.as_ref();
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// The canonical form of a string attribute value: converted to UTF-8, with leading and
+/// trailing whitespace removed, inner runs of whitespace collapsed to one space, and ASCII
+/// letters lowercased. `None` for values OpenSSL hashes as they are.
+fn canonical_string(tag: u32, data: &[u8]) -> Option<String> {
+    let text: String = match tag {
+        TAG_UTF8_STRING | TAG_PRINTABLE_STRING | TAG_IA5_STRING | TAG_VISIBLE_STRING => {
+            String::from_utf8_lossy(data).into_owned()
+        }
+        // OpenSSL reads T61String as Latin-1
+        TAG_T61_STRING => data.iter().map(|&b| char::from(b)).collect(),
+        TAG_BMP_STRING => char::decode_utf16(
+            data.chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]])),
+        )
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect(),
+        TAG_UNIVERSAL_STRING => data
+            .chunks_exact(4)
+            .map(|quad| {
+                char::from_u32(u32::from_be_bytes([quad[0], quad[1], quad[2], quad[3]]))
+                    .unwrap_or(char::REPLACEMENT_CHARACTER)
+            })
+            .collect(),
+        _ => return None,
+    };
+
+    // OpenSSL's isspace: space, \t, \n, \v, \f and \r
+    let is_space = |c: char| matches!(c, ' ' | '\t' | '\n' | '\x0b' | '\x0c' | '\r');
+    let mut canonical = String::with_capacity(text.len());
+    let mut pending_space = false;
+    for c in text.trim_matches(is_space).chars() {
+        if is_space(c) {
+            pending_space = true;
+            continue;
+        }
+        if pending_space {
+            canonical.push(' ');
+            pending_space = false;
+        }
+        canonical.push(c.to_ascii_lowercase());
+    }
+    Some(canonical)
+}
+
+/// DER-encodes a tag, length and `content`.
+fn der_tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut encoded = vec![tag];
+    let len = content.len();
+    if len < 0x80 {
+        encoded.push(len as u8);
+    } else {
+        let len_bytes: Vec<u8> = len
+            .to_be_bytes()
+            .into_iter()
+            .skip_while(|&b| b == 0)
+            .collect();
+        encoded.push(0x80 | len_bytes.len() as u8);
+        encoded.extend(len_bytes);
+    }
+    encoded.extend_from_slice(content);
+    encoded
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A CA whose organization needs canonicalizing: `O=  Example   TRUST Org `. OpenSSL
+    /// reports its subject hash as `8b6ac555`.
+    pub(crate) const KNOWN_CA_PEM: &str = "\
+-----BEGIN CERTIFICATE-----
+MIIB5zCCAY2gAwIBAgIUSK4esPASOKZ8JpmhRDdM1a2FkrgwCgYIKoZIzj0EAwIw
+SDELMAkGA1UEBhMCVVMxHzAdBgNVBAoMFiAgRXhhbXBsZSAgIFRSVVNUIE9yZyAx
+GDAWBgNVBAMMD0V4YW1wbGUgUm9vdCBDQTAgFw0yNjEwMTYwNjU3NTBaGA8yMTI2
+MDkyMjA2NTc1MFowSDELMAkGA1UEBhMCVVMxHzAdBgNVBAoMFiAgRXhhbXBsZSAg
+IFRSVVNUIE9yZyAxGDAWBgNVBAMMD0V4YW1wbGUgUm9vdCBDQTBZMBMGByqGSM49
+AgEGCCqGSM49AwEHA0IABCoxmazN4jTWyW55rHxKZUHE2MSKk84xJr4GP/nhWsO6
+B45J8O6EgvIPkNlQTpDVOtNPj4S7YfvtSkVTUxmKLfCjUzBRMB0GA1UdDgQWBBS7
+soL4/Jsn18vnW8bixKsp/P8zHDAfBgNVHSMEGDAWgBS7soL4/Jsn18vnW8bixKsp
+/P8zHDAPBgNVHRMBAf8EBTADAQH/MAoGCCqGSM49BAMCA0gAMEUCIDz1rsm6R7BZ
+CL4tRqfs79J/KlCON++i2yb1ouk/i/U5AiEAvIHrjXo9KRxjXvM4g7DEpqrpneEL
+2uF6Z5dpqsxf8Fk=
+-----END CERTIFICATE-----
+";
+    pub(crate) const KNOWN_CA_HASH: &str = "8b6ac555";
+
+    pub(crate) fn known_ca() -> Certificate {
+        Certificate::try_from(pem::parse(KNOWN_CA_PEM).unwrap().contents).unwrap()
+    }
+
+    #[test]
+    fn test_subject_hash_matches_openssl() {
+        let hash = subject_hash(known_ca().as_ref()).unwrap();
+        assert_eq!(format!("{hash:08x}"), KNOWN_CA_HASH);
+    }
+
+    #[test]
+    fn test_hashed_file_names_number_colliding_subjects() {
+        let names = hashed_file_names(&[known_ca(), known_ca()]).unwrap();
+        assert_eq!(
+            names,
+            [format!("{KNOWN_CA_HASH}.0"), format!("{KNOWN_CA_HASH}.1")]
+        );
+    }
+
+    #[test]
+    fn test_is_hashed_file_name() {
+        assert!(is_hashed_file_name("8b6ac555.0"));
+        assert!(is_hashed_file_name("0000abcd.12"));
+        assert!(!is_hashed_file_name("bundle.pem"));
+        assert!(!is_hashed_file_name("8b6ac555.pem"));
+        assert!(!is_hashed_file_name("8b6ac55.0"));
+    }
+}
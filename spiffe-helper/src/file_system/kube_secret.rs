@@ -0,0 +1,352 @@
+use anyhow::{anyhow, Context, Result};
+use k8s_openapi::api::core::v1::Secret;
+use k8s_openapi::ByteString;
+use kube::api::{Api, Patch, PatchParams};
+use spiffe::bundle::x509::X509Bundle;
+use spiffe::cert::Certificate;
+use tokio::runtime::RuntimeFlavor;
+
+use super::{
+    certificates_pem, crls_pem, jwt_bundle_content, jwt_file_content, key_pem, pem_line_ending,
+    CredentialSink, X509Update,
+};
+use crate::cli::config::{JWT_BUNDLE_COMPRESSION_NONE, JWT_BUNDLE_FORMAT_SPIFFE};
+use crate::cli::Config;
+use crate::jwt::JwtBundles;
+
+/// Data keys of a `kubernetes.io/tls` Secret, plus the conventional key for the CA bundle.
+pub const TLS_CERT_KEY: &str = "tls.crt";
+pub const TLS_KEY_KEY: &str = "tls.key";
+pub const CA_CERT_KEY: &str = "ca.crt";
+
+const FIELD_MANAGER: &str = "spiffe-helper";
+/// Data key for the JWT bundle when `jwt_bundle_file_name` is not set.
+const DEFAULT_JWT_BUNDLE_KEY: &str = "jwt_bundle.json";
+/// Data key for the CRLs when `crl_file_name` is not set.
+const DEFAULT_CRL_KEY: &str = "ca.crl";
+
+/// A [`CredentialSink`] that merge-patches the data of an existing Kubernetes Secret. The
+/// certificate chain, key and bundle go to `tls.crt`, `tls.key` and `ca.crt`; JWT SVIDs, the
+/// JWT bundle and the CRLs go to keys named after their configured file names.
+///
+/// Each write blocks on the API request, so the sink must be used from a multi-threaded Tokio
+/// runtime; on a current-thread runtime every write fails with an error.
+pub struct KubeSecretSink {
+    api: Api<Secret>,
+    secret_name: String,
+    jwt_bundle_key: String,
+    jwt_bundle_format: String,
+    jwt_bundle_compression: String,
+    line_ending: pem::LineEnding,
+    jwt_trailing_newline: bool,
+    crl_key: String,
+}
+
+impl KubeSecretSink {
+    /// Connects with the pod's service account to patch `kube_secret_name`, in
+    /// `kube_secret_namespace` or else the pod's own namespace.
+    pub fn new(config: &Config) -> Result<Self> {
+        let secret_name = config
+            .kube_secret_name
+            .clone()
+            .ok_or_else(|| anyhow!("kube_secret_name must be configured"))?;
+        let kube_config = kube::Config::incluster()
+            .context("Failed to load the in-cluster Kubernetes service account")?;
+        let namespace = config
+            .kube_secret_namespace
+            .clone()
+            .unwrap_or_else(|| kube_config.default_namespace.clone());
+        let client = kube::Client::try_from(kube_config)
+            .context("Failed to create the Kubernetes API client")?;
+
+        let mut sink = Self::with_client(
+            client,
+            &namespace,
+            secret_name,
+            config.jwt_bundle_file_name.clone(),
+        );
+        if let Some(crl_file_name) = &config.crl_file_name {
+            sink.crl_key.clone_from(crl_file_name);
+        }
+        sink.jwt_bundle_format = config.jwt_bundle_format().to_string();
+        sink.jwt_bundle_compression = config.jwt_bundle_compression().to_string();
+        sink.line_ending = pem_line_ending(config);
+        sink.jwt_trailing_newline = config.jwt_trailing_newline();
+        Ok(sink)
+    }
+
+    /// Patches `secret_name` in `namespace` through an already configured client.
+    #[must_use]
+    pub fn with_client(
+        client: kube::Client,
+        namespace: &str,
+        secret_name: String,
+        jwt_bundle_key: Option<String>,
+    ) -> Self {
+        Self {
+            api: Api::namespaced(client, namespace),
+            secret_name,
+            jwt_bundle_key: jwt_bundle_key.unwrap_or_else(|| DEFAULT_JWT_BUNDLE_KEY.to_string()),
+            jwt_bundle_format: JWT_BUNDLE_FORMAT_SPIFFE.to_string(),
+            jwt_bundle_compression: JWT_BUNDLE_COMPRESSION_NONE.to_string(),
+            line_ending: pem::LineEnding::LF,
+            jwt_trailing_newline: false,
+            crl_key: DEFAULT_CRL_KEY.to_string(),
+        }
+    }
+
+    /// Sends one merge patch setting every `(key, value)` pair in `data`, so the keys of one
+    /// update change together.
+    fn patch(&self, data: &[(&str, Vec<u8>)]) -> Result<()> {
+        let keys = data
+            .iter()
+            .map(|(key, _)| *key)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let data: serde_json::Map<String, serde_json::Value> = data
+            .iter()
+            .map(|(key, value)| {
+                let value = serde_json::to_value(ByteString(value.clone()))?;
+                Ok((key.to_string(), value))
+            })
+            .collect::<serde_json::Result<_>>()?;
+        let body = serde_json::json!({ "data": data });
+        let params = PatchParams {
+            field_manager: Some(FIELD_MANAGER.to_string()),
+            ..Default::default()
+        };
+        let handle = tokio::runtime::Handle::try_current()
+            .context("KubeSecretSink must be used within a Tokio runtime")?;
+        // Blocking in place is not possible on a current-thread runtime
+        if handle.runtime_flavor() == RuntimeFlavor::CurrentThread {
+            return Err(anyhow!(
+                "KubeSecretSink must be used within a multi-threaded Tokio runtime"
+            ));
+        }
+
+        tokio::task::block_in_place(|| {
+            handle.block_on(
+                self.api
+                    .patch(&self.secret_name, &params, &Patch::Merge(&body)),
+            )
+        })
+        .with_context(|| {
+            format!(
+                "Failed to update {keys} in Secret {}/{}",
+                self.api.namespace().unwrap_or_default(),
+                self.secret_name
+            )
+        })?;
+        Ok(())
+    }
+
+    fn cert_chain_data(&self, certificates: &[Certificate]) -> (&'static str, Vec<u8>) {
+        let content = certificates_pem(certificates, self.line_ending);
+        (TLS_CERT_KEY, content.into_bytes())
+    }
+
+    fn key_data(&self, key: &[u8]) -> (&'static str, Vec<u8>) {
+        (TLS_KEY_KEY, key_pem(key, self.line_ending).into_bytes())
+    }
+
+    fn bundle_data(&self, bundle: &X509Bundle) -> (&'static str, Vec<u8>) {
+        let content = certificates_pem(bundle.authorities(), self.line_ending);
+        (CA_CERT_KEY, content.into_bytes())
+    }
+}
+
+impl CredentialSink for KubeSecretSink {
+    fn write_cert_chain(&self, certificates: &[Certificate]) -> Result<()> {
+        self.patch(&[self.cert_chain_data(certificates)])
+    }
+
+    fn write_key(&self, key: &[u8]) -> Result<()> {
+        self.patch(&[self.key_data(key)])
+    }
+
+    fn write_bundle(&self, bundle: &X509Bundle) -> Result<()> {
+        self.patch(&[self.bundle_data(bundle)])
+    }
+
+    fn write_jwt(&self, name: &str, token: &str) -> Result<()> {
+        let content = jwt_file_content(token, self.jwt_trailing_newline);
+        self.patch(&[(name, content.into_bytes())])
+    }
+
+    fn write_jwt_bundle(&self, bundles: &JwtBundles) -> Result<()> {
+        let content = jwt_bundle_content(
+            bundles,
+            &self.jwt_bundle_format,
+            &self.jwt_bundle_compression,
+        )?;
+        self.patch(&[(self.jwt_bundle_key.as_str(), content)])
+    }
+
+    fn write_crl(&self, crls: &[Vec<u8>]) -> Result<()> {
+        let content = crls_pem(crls, self.line_ending);
+        self.patch(&[(self.crl_key.as_str(), content.into_bytes())])
+    }
+
+    /// Sends the certificate, key and bundle in a single patch, so a consumer reloading on a
+    /// Secret change never loads a new certificate with the previous key.
+    fn write_x509_update(&self, update: &X509Update<'_>) -> Result<()> {
+        let data: Vec<_> = [
+            update.cert_chain.map(|c| self.cert_chain_data(c)),
+            update.key.map(|k| self.key_data(k)),
+            update.bundle.map(|b| self.bundle_data(b)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if data.is_empty() {
+            return Ok(());
+        }
+        self.patch(&data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::{Path, State};
+    use axum::http::HeaderMap;
+    use axum::routing::patch;
+    use axum::{Json, Router};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug, Clone)]
+    struct RecordedPatch {
+        path: String,
+        content_type: String,
+        body: serde_json::Value,
+    }
+
+    type Recorded = Arc<Mutex<Vec<RecordedPatch>>>;
+
+    async fn record_patch(
+        State(recorded): State<Recorded>,
+        Path((namespace, name)): Path<(String, String)>,
+        headers: HeaderMap,
+        body: String,
+    ) -> Json<serde_json::Value> {
+        recorded.lock().unwrap().push(RecordedPatch {
+            path: format!("/api/v1/namespaces/{namespace}/secrets/{name}"),
+            content_type: headers
+                .get("content-type")
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default()
+                .to_string(),
+            body: serde_json::from_str(&body).unwrap(),
+        });
+        Json(serde_json::json!({
+            "apiVersion": "v1",
+            "kind": "Secret",
+            "metadata": { "name": name, "namespace": namespace },
+        }))
+    }
+
+    /// Serves a fake API server that records Secret patches and returns a sink pointed at it.
+    async fn mock_api_server() -> (KubeSecretSink, Recorded) {
+        let recorded = Recorded::default();
+        let app = Router::new()
+            .route(
+                "/api/v1/namespaces/:namespace/secrets/:name",
+                patch(record_patch),
+            )
+            .with_state(recorded.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let client = kube::Client::try_from(kube::Config::new(url.parse().unwrap())).unwrap();
+        let sink =
+            KubeSecretSink::with_client(client, "workloads", "workload-tls".to_string(), None);
+        (sink, recorded)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_write_cert_chain_merge_patches_tls_crt() {
+        let (sink, recorded) = mock_api_server().await;
+        let der = spire_agent_mock::svid::SvidGenerator::generate_ca_der("example.org");
+        let certificate = Certificate::try_from(der.clone()).unwrap();
+
+        sink.write_cert_chain(&[certificate]).unwrap();
+
+        let recorded = recorded.lock().unwrap().clone();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(
+            recorded[0].path,
+            "/api/v1/namespaces/workloads/secrets/workload-tls"
+        );
+        assert_eq!(recorded[0].content_type, "application/merge-patch+json");
+
+        let expected_pem = pem::encode_config(
+            &pem::Pem {
+                tag: "CERTIFICATE".to_string(),
+                contents: der,
+            },
+            pem::EncodeConfig {
+                line_ending: pem::LineEnding::LF,
+            },
+        );
+        let expected = serde_json::json!({
+            "data": { "tls.crt": ByteString(expected_pem.into_bytes()) }
+        });
+        assert_eq!(recorded[0].body, expected);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_x509_update_sends_one_patch() {
+        let (sink, recorded) = mock_api_server().await;
+        let der = spire_agent_mock::svid::SvidGenerator::generate_ca_der("example.org");
+        let certificate = Certificate::try_from(der.clone()).unwrap();
+        let trust_domain = spiffe::spiffe_id::TrustDomain::new("example.org").unwrap();
+        let mut bundle = X509Bundle::new(trust_domain);
+        bundle.add_authority(&der).unwrap();
+
+        sink.write_x509_update(&X509Update {
+            cert_chain: Some(&[certificate]),
+            key: Some(b"key"),
+            bundle: Some(&bundle),
+        })
+        .unwrap();
+
+        let recorded = recorded.lock().unwrap().clone();
+        assert_eq!(recorded.len(), 1);
+        let mut keys: Vec<&String> = recorded[0].body["data"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .collect();
+        keys.sort();
+        assert_eq!(keys, ["ca.crt", "tls.crt", "tls.key"]);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_current_thread_runtime_is_an_error() {
+        let (sink, recorded) = mock_api_server().await;
+
+        let error = sink.write_key(b"key").unwrap_err();
+        assert!(
+            format!("{error:#}").contains("multi-threaded Tokio runtime"),
+            "{error:#}"
+        );
+        assert!(recorded.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_patch_failure_names_the_secret() {
+        let client =
+            kube::Client::try_from(kube::Config::new("http://127.0.0.1:1".parse().unwrap()))
+                .unwrap();
+        let sink =
+            KubeSecretSink::with_client(client, "workloads", "workload-tls".to_string(), None);
+
+        let error = sink.write_key(b"key").unwrap_err();
+        assert!(
+            format!("{error:#}")
+                .contains("Failed to update tls.key in Secret workloads/workload-tls"),
+            "{error:#}"
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use anyhow::Result;
+use spiffe::bundle::x509::X509Bundle;
+use spiffe::cert::Certificate;
+
+use super::CredentialSink;
+use crate::jwt::JwtBundles;
+
+/// Credentials most recently written to a [`MemorySink`], with a count of writes per output.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryContents {
+    /// DER of each certificate in the chain, leaf first
+    pub cert_chain: Option<Vec<Vec<u8>>>,
+    pub key: Option<Vec<u8>>,
+    /// DER of each bundle authority
+    pub bundle: Option<Vec<Vec<u8>>>,
+    /// JWT SVID tokens keyed by `jwt_svid_file_name`
+    pub jwts: BTreeMap<String, String>,
+    pub jwt_bundles: Option<JwtBundles>,
+    /// DER of each CRL
+    pub crl: Option<Vec<Vec<u8>>>,
+    pub cert_chain_writes: usize,
+    pub key_writes: usize,
+    pub bundle_writes: usize,
+    pub jwt_writes: usize,
+    pub jwt_bundle_writes: usize,
+    pub crl_writes: usize,
+}
+
+/// A [`CredentialSink`] that keeps credentials in memory instead of writing files. Clones share
+/// the same contents, so a test can hand one clone to the daemon and inspect another.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySink {
+    contents: Arc<Mutex<MemoryContents>>,
+}
+
+impl MemorySink {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A snapshot of everything written so far.
+    #[must_use]
+    pub fn contents(&self) -> MemoryContents {
+        self.lock().clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MemoryContents> {
+        // A panic while holding the lock cannot leave the plain data inconsistent
+        self.contents
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+impl CredentialSink for MemorySink {
+    fn write_cert_chain(&self, certificates: &[Certificate]) -> Result<()> {
+        let mut contents = self.lock();
+        contents.cert_chain = Some(certificates.iter().map(|c| c.as_ref().to_vec()).collect());
+        contents.cert_chain_writes += 1;
+        Ok(())
+    }
+
+    fn write_key(&self, key: &[u8]) -> Result<()> {
+        let mut contents = self.lock();
+        contents.key = Some(key.to_vec());
+        contents.key_writes += 1;
+        Ok(())
+    }
+
+    fn write_bundle(&self, bundle: &X509Bundle) -> Result<()> {
+        let mut contents = self.lock();
+        contents.bundle = Some(
+            bundle
+                .authorities()
+                .iter()
+                .map(|c| c.as_ref().to_vec())
+                .collect(),
+        );
+        contents.bundle_writes += 1;
+        Ok(())
+    }
+
+    fn write_jwt(&self, name: &str, token: &str) -> Result<()> {
+        let mut contents = self.lock();
+        contents.jwts.insert(name.to_string(), token.to_string());
+        contents.jwt_writes += 1;
+        Ok(())
+    }
+
+    fn write_jwt_bundle(&self, bundles: &JwtBundles) -> Result<()> {
+        let mut contents = self.lock();
+        contents.jwt_bundles = Some(bundles.clone());
+        contents.jwt_bundle_writes += 1;
+        Ok(())
+    }
+
+    fn write_crl(&self, crls: &[Vec<u8>]) -> Result<()> {
+        let mut contents = self.lock();
+        contents.crl = Some(crls.to_vec());
+        contents.crl_writes += 1;
+        Ok(())
+    }
+}
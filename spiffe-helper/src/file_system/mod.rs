@@ -1,22 +1,87 @@
 /* The file_system module abstract the interaction of this program with the FileSystem */
 
+use std::io::Write;
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
 use std::{fs, path::PathBuf, str::FromStr};
 
 use anyhow::{anyhow, Context, Result};
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use spiffe::bundle::x509::X509Bundle;
 use spiffe::cert::Certificate;
+use x509_parser::extensions::GeneralName;
 
+use crate::cli::config::{
+    JWT_BUNDLE_COMPRESSION_GZIP, JWT_BUNDLE_FORMAT_JWKS_FLAT, PEM_LINE_ENDING_CRLF,
+};
 use crate::cli::Config;
+use crate::health::OutputStatus;
+use crate::jwt::JwtBundles;
 
-pub trait X509CertsWriter {
-    fn write_certs(&self, certificates: &[Certificate]) -> Result<()>;
+mod dry_run;
+mod hashed_dir;
+#[cfg(feature = "kube")]
+mod kube_secret;
+mod memory;
+mod multi;
+
+pub use dry_run::DryRunSink;
+#[cfg(feature = "kube")]
+pub use kube_secret::KubeSecretSink;
+pub use memory::MemorySink;
+pub use multi::MultiSink;
+
+/// Destination for the credentials fetched from the SPIRE agent. The daemon and one-shot mode
+/// write through this trait, so outputs other than [`LocalFileSystem`] plug in without changes
+/// to either.
+pub trait CredentialSink: Send + Sync {
+    /// Writes the leaf certificate followed by any intermediates.
+    fn write_cert_chain(&self, certificates: &[Certificate]) -> Result<()>;
     fn write_key(&self, key: &[u8]) -> Result<()>;
     fn write_bundle(&self, bundle: &X509Bundle) -> Result<()>;
+    /// Writes a JWT SVID token under `name`, the entry's `jwt_svid_file_name`.
+    fn write_jwt(&self, name: &str, token: &str) -> Result<()>;
+    fn write_jwt_bundle(&self, bundles: &JwtBundles) -> Result<()>;
+    /// Writes the DER-encoded certificate revocation lists, in order.
+    fn write_crl(&self, crls: &[Vec<u8>]) -> Result<()>;
+
+    /// Writes the parts of one X.509 SVID update that are set. Sinks that can store them in a
+    /// single operation override this, so a reader never sees a new certificate next to the
+    /// previous key.
+    fn write_x509_update(&self, update: &X509Update<'_>) -> Result<()> {
+        if let Some(certificates) = update.cert_chain {
+            self.write_cert_chain(certificates)?;
+        }
+        if let Some(key) = update.key {
+            self.write_key(key)?;
+        }
+        if let Some(bundle) = update.bundle {
+            self.write_bundle(bundle)?;
+        }
+        Ok(())
+    }
+
+    /// The outcome of each output for sinks that write to several, reported in the health
+    /// status. Empty for sinks with a single destination.
+    fn output_statuses(&self) -> Vec<OutputStatus> {
+        Vec::new()
+    }
+}
+
+/// The credentials of one X.509 SVID update, written together by
+/// [`CredentialSink::write_x509_update`]. A part left `None` is not written.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct X509Update<'a> {
+    pub cert_chain: Option<&'a [Certificate]>,
+    pub key: Option<&'a [u8]>,
+    pub bundle: Option<&'a X509Bundle>,
 }
 
 #[derive(Debug)]
+// Modes and ownership only apply on Unix; elsewhere files get the platform's default ACLs
+#[cfg_attr(not(unix), allow(dead_code))]
 pub struct LocalFileSystem {
     output_dir: PathBuf, // from the cert_dir in the config
     cer_path: PathBuf,
@@ -25,6 +90,22 @@ pub struct LocalFileSystem {
     cert_mode: u32,
     key_mode: u32,
     bundle_mode: u32,
+    bundle_dir: Option<PathBuf>,
+    jwt_bundle_path: Option<PathBuf>,
+    jwt_bundle_mode: u32,
+    jwt_bundle_format: String,
+    jwt_bundle_compression: String,
+    line_ending: pem::LineEnding,
+    crl_path: Option<PathBuf>,
+    jwt_svid_mode: u32,
+    jwt_trailing_newline: bool,
+    owner_uid: Option<u32>,
+    owner_gid: Option<u32>,
+    dir_mode: u32,
+    write_fingerprints: bool,
+    follow_symlinks: bool,
+    verify_after_write: bool,
+    require_spiffe_san: bool,
 }
 
 impl LocalFileSystem {
@@ -49,103 +130,905 @@ impl LocalFileSystem {
             cert_mode: config.cert_file_mode(),
             key_mode: config.key_file_mode(),
             bundle_mode: config.cert_file_mode(),
+            bundle_dir: config.bundle_dir.as_ref().map(PathBuf::from),
+            jwt_bundle_path: config
+                .jwt_bundle_file_name
+                .as_ref()
+                .map(|name| output_dir.join(name)),
+            jwt_bundle_mode: config.jwt_bundle_file_mode(),
+            jwt_bundle_format: config.jwt_bundle_format().to_string(),
+            jwt_bundle_compression: config.jwt_bundle_compression().to_string(),
+            line_ending: pem_line_ending(config),
+            crl_path: config
+                .crl_file_name
+                .as_ref()
+                .map(|name| output_dir.join(name)),
+            jwt_svid_mode: config.jwt_svid_file_mode(),
+            jwt_trailing_newline: config.jwt_trailing_newline(),
+            owner_uid: config.file_owner_uid,
+            owner_gid: config.file_owner_gid,
+            dir_mode: config.cert_dir_mode(),
+            write_fingerprints: config.write_fingerprints(),
+            follow_symlinks: config.follow_symlinks(),
+            verify_after_write: config.verify_after_write(),
+            require_spiffe_san: config.require_spiffe_san(),
         })
     }
 
+    /// Creates the output directory if needed and checks that it is writable. Directories
+    /// created here, including missing parents, get `cert_dir_mode`; existing directories are
+    /// left untouched.
     pub fn ensure(self) -> Result<Self> {
         if !&self.output_dir.exists() {
+            #[cfg(unix)]
+            let created: Vec<&Path> = self
+                .output_dir
+                .ancestors()
+                .take_while(|dir| !dir.as_os_str().is_empty() && !dir.exists())
+                .collect();
+
             fs::create_dir_all(&self.output_dir).with_context(|| {
                 format!(
                     "Failed to create output directory: {}",
                     self.output_dir.display()
                 )
             })?;
+
+            #[cfg(unix)]
+            for dir in created {
+                fs::set_permissions(dir, fs::Permissions::from_mode(self.dir_mode)).with_context(
+                    || format!("Failed to set permissions on directory {}", dir.display()),
+                )?;
+            }
         }
 
+        self.check_writable()?;
+
         Ok(self)
     }
-}
 
-impl X509CertsWriter for LocalFileSystem {
-    fn write_certs(&self, certificates: &[Certificate]) -> Result<()> {
-        let content = certificates
-            .iter()
-            .map(|c| {
-                pem::encode(&pem::Pem {
-                    tag: "CERTIFICATE".to_string(),
-                    contents: c.as_ref().to_vec(),
-                })
+    /// Writes and removes a probe file so that an unwritable `cert_dir` is reported up front
+    /// rather than on the first certificate write.
+    fn check_writable(&self) -> Result<()> {
+        let probe = self
+            .output_dir
+            .join(format!(".spiffe-helper-probe-{}", std::process::id()));
+
+        fs::write(&probe, b"")
+            .and_then(|()| fs::remove_file(&probe))
+            .map_err(|e| {
+                anyhow!(
+                    "cert_dir {} is not writable: {e}. Check the directory's ownership and permissions",
+                    self.output_dir.display()
+                )
             })
-            .collect::<Vec<_>>()
-            .join("\n");
+    }
 
-        fs::write(&self.cer_path, content).with_context(|| {
-            format!("Failed to write certificate to {}", self.cer_path.display())
-        })?;
+    /// Writes `<path>.sha256` containing the hex SHA-256 digest of `content`, the exact bytes
+    /// written to `path`, when `write_fingerprints` is enabled. The sidecar is written to a
+    /// temporary file and renamed into place, and gets the bundle permissions and owner.
+    fn write_fingerprint(&self, path: &Path, content: &[u8]) -> Result<()> {
+        if !self.write_fingerprints {
+            return Ok(());
+        }
+
+        let mut fingerprint_path = path.as_os_str().to_owned();
+        fingerprint_path.push(".sha256");
+        let fingerprint_path = PathBuf::from(fingerprint_path);
+        let mut tmp_path = fingerprint_path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+
+        fs::write(&tmp_path, format!("{}\n", sha256_hex(content)))
+            .with_context(|| format!("Failed to write fingerprint to {}", tmp_path.display()))?;
 
         #[cfg(unix)]
-        fs::set_permissions(&self.cer_path, fs::Permissions::from_mode(self.cert_mode))
-            .with_context(|| {
+        fs::set_permissions(&tmp_path, fs::Permissions::from_mode(self.bundle_mode)).with_context(
+            || {
                 format!(
-                    "Failed to set permissions on certificate file {}",
-                    self.cer_path.display()
+                    "Failed to set permissions on fingerprint file {}",
+                    tmp_path.display()
                 )
+            },
+        )?;
+
+        #[cfg(unix)]
+        self.set_owner(&tmp_path)?;
+
+        fs::rename(&tmp_path, &fingerprint_path).with_context(|| {
+            format!(
+                "Failed to move fingerprint into place at {}",
+                fingerprint_path.display()
+            )
+        })
+    }
+
+    /// Writes each authority to its own file in `bundle_dir`, named by OpenSSL subject hash, and
+    /// removes the hashed files of authorities that left the bundle so they stop being trusted.
+    fn write_bundle_dir(&self, authorities: &[Certificate]) -> Result<()> {
+        let Some(bundle_dir) = &self.bundle_dir else {
+            return Ok(());
+        };
+        fs::create_dir_all(bundle_dir).with_context(|| {
+            format!("Failed to create bundle directory {}", bundle_dir.display())
+        })?;
+
+        let names = hashed_dir::hashed_file_names(authorities)?;
+        for (name, authority) in names.iter().zip(authorities) {
+            let path = bundle_dir.join(name);
+            let content = certificates_pem(std::slice::from_ref(authority), self.line_ending);
+            fs::write(&path, content).with_context(|| {
+                format!("Failed to write bundle authority to {}", path.display())
             })?;
 
+            #[cfg(unix)]
+            fs::set_permissions(&path, fs::Permissions::from_mode(self.bundle_mode))
+                .with_context(|| format!("Failed to set permissions on {}", path.display()))?;
+
+            #[cfg(unix)]
+            self.set_owner(&path)?;
+        }
+
+        let entries = fs::read_dir(bundle_dir)
+            .with_context(|| format!("Failed to list {}", bundle_dir.display()))?;
+        for entry in entries {
+            let entry = entry?;
+            let file_name = entry.file_name();
+            let Some(file_name) = file_name.to_str() else {
+                continue;
+            };
+            if hashed_dir::is_hashed_file_name(file_name) && !names.iter().any(|n| n == file_name) {
+                fs::remove_file(entry.path()).with_context(|| {
+                    format!(
+                        "Failed to remove stale authority {}",
+                        entry.path().display()
+                    )
+                })?;
+            }
+        }
         Ok(())
     }
 
+    /// Where a write to `path` lands. A symlink is resolved to its target, relative to the
+    /// link's directory, so the link itself stays in place; with `follow_symlinks` off, writing
+    /// through one is an error instead.
+    fn destination(&self, path: &Path) -> Result<PathBuf> {
+        let is_symlink = fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink());
+        if !is_symlink {
+            return Ok(path.to_path_buf());
+        }
+        if !self.follow_symlinks {
+            anyhow::bail!(
+                "{} is a symlink; refusing to write through it. \
+                 Set follow_symlinks = true to write to its target",
+                path.display()
+            );
+        }
+
+        let target = fs::read_link(path)
+            .with_context(|| format!("Failed to read symlink {}", path.display()))?;
+        Ok(path.parent().unwrap_or(Path::new("")).join(target))
+    }
+
+    /// Changes the owner of a written file to `file_owner_uid` / `file_owner_gid`. Ids that are
+    /// not configured are left unchanged.
+    #[cfg(unix)]
+    fn set_owner(&self, path: &Path) -> Result<()> {
+        use nix::errno::Errno;
+        use nix::unistd::{chown, Gid, Uid};
+
+        if self.owner_uid.is_none() && self.owner_gid.is_none() {
+            return Ok(());
+        }
+
+        chown(
+            path,
+            self.owner_uid.map(Uid::from_raw),
+            self.owner_gid.map(Gid::from_raw),
+        )
+        .map_err(|e| {
+            let owner = format!(
+                "uid={} gid={}",
+                self.owner_uid
+                    .map_or("unchanged".to_string(), |v| v.to_string()),
+                self.owner_gid
+                    .map_or("unchanged".to_string(), |v| v.to_string())
+            );
+            if e == Errno::EPERM {
+                anyhow!(
+                    "Failed to change ownership of {} to {owner}: permission denied. \
+                     file_owner_uid and file_owner_gid require the helper to run as root \
+                     or with CAP_CHOWN",
+                    path.display()
+                )
+            } else {
+                anyhow!(
+                    "Failed to change ownership of {} to {owner}: {e}",
+                    path.display()
+                )
+            }
+        })
+    }
+}
+
+/// Holds a process umask in place, restoring the one it replaced when dropped.
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct UmaskGuard {
+    previous: nix::sys::stat::Mode,
+}
+
+#[cfg(unix)]
+impl UmaskGuard {
+    /// Sets the process umask to `mask` until the guard is dropped. The umask is process-wide,
+    /// so it also applies to files other threads create meanwhile.
+    #[must_use]
+    pub fn set(mask: u32) -> Self {
+        use nix::sys::stat::{umask, Mode};
+
+        let previous = umask(Mode::from_bits_truncate(mask as nix::libc::mode_t));
+        Self { previous }
+    }
+
+    /// The umask in place before the guard was set.
+    #[must_use]
+    pub fn previous(&self) -> u32 {
+        u32::from(self.previous.bits())
+    }
+}
+
+#[cfg(unix)]
+impl Drop for UmaskGuard {
+    fn drop(&mut self) {
+        nix::sys::stat::umask(self.previous);
+    }
+}
+
+/// Flushes every file in `dir`, then the directory itself so that renames into it are durable.
+/// Windows cannot open a directory as a file, so there only the files are flushed.
+pub fn sync_dir(dir: &Path) -> Result<()> {
+    let entries =
+        fs::read_dir(dir).with_context(|| format!("Failed to list directory {}", dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_file() {
+            fs::File::open(&path)
+                .and_then(|file| file.sync_all())
+                .with_context(|| format!("Failed to sync {}", path.display()))?;
+        }
+    }
+
+    #[cfg(unix)]
+    fs::File::open(dir)
+        .and_then(|dir| dir.sync_all())
+        .with_context(|| format!("Failed to sync directory {}", dir.display()))?;
+    Ok(())
+}
+
+/// Returns the lowercase hex SHA-256 digest of `content`.
+pub fn sha256_hex(content: &[u8]) -> String {
+    ring::digest::digest(&ring::digest::SHA256, content)
+        .as_ref()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// The line ending `pem_line_ending` selects for every line of the PEM output, including
+/// the lines between blocks.
+pub(crate) fn pem_line_ending(config: &Config) -> pem::LineEnding {
+    if config.pem_line_ending() == PEM_LINE_ENDING_CRLF {
+        pem::LineEnding::CRLF
+    } else {
+        pem::LineEnding::LF
+    }
+}
+
+fn encode_pem(
+    tag: &str,
+    blocks: impl Iterator<Item = Vec<u8>>,
+    line_ending: pem::LineEnding,
+) -> String {
+    let pems: Vec<pem::Pem> = blocks
+        .map(|contents| pem::Pem {
+            tag: tag.to_string(),
+            contents,
+        })
+        .collect();
+    pem::encode_many_config(&pems, pem::EncodeConfig { line_ending })
+}
+
+/// PEM-encodes `certificates` in order, as written to the certificate and bundle files.
+pub(crate) fn certificates_pem(
+    certificates: &[Certificate],
+    line_ending: pem::LineEnding,
+) -> String {
+    encode_pem(
+        "CERTIFICATE",
+        certificates.iter().map(|c| c.as_ref().to_vec()),
+        line_ending,
+    )
+}
+
+/// PEM-encodes a PKCS#8 private key.
+pub(crate) fn key_pem(key: &[u8], line_ending: pem::LineEnding) -> String {
+    encode_pem("PRIVATE KEY", std::iter::once(Vec::from(key)), line_ending)
+}
+
+/// PEM-encodes each DER CRL in order; an empty list yields an empty string.
+pub(crate) fn crls_pem(crls: &[Vec<u8>], line_ending: pem::LineEnding) -> String {
+    encode_pem("X509 CRL", crls.iter().cloned(), line_ending)
+}
+
+/// The content of a JWT SVID file: the token, followed by `\n` with `jwt_trailing_newline`.
+pub(crate) fn jwt_file_content(token: &str, trailing_newline: bool) -> String {
+    if trailing_newline {
+        format!("{token}\n")
+    } else {
+        token.to_string()
+    }
+}
+
+/// Serializes `bundles` in `format`: a JWKS per trust domain keyed by its name for
+/// `jwt_bundle_format = "spiffe"`, or a single `{"keys": [...]}` JWKS merging every domain's
+/// keys for `"jwks_flat"`.
+pub(crate) fn jwt_bundle_json(bundles: &JwtBundles, format: &str) -> Result<String> {
+    let mut content = serde_json::Map::new();
+    let mut keys = Vec::new();
+    for (trust_domain, jwks) in bundles {
+        let jwks: serde_json::Value = serde_json::from_slice(jwks)
+            .with_context(|| format!("Invalid JWKS received for {trust_domain}"))?;
+        if format == JWT_BUNDLE_FORMAT_JWKS_FLAT {
+            if let Some(domain_keys) = jwks.get("keys").and_then(serde_json::Value::as_array) {
+                keys.extend(domain_keys.iter().cloned());
+            }
+        } else {
+            content.insert(trust_domain.clone(), jwks);
+        }
+    }
+
+    if format == JWT_BUNDLE_FORMAT_JWKS_FLAT {
+        content.insert("keys".to_string(), serde_json::Value::Array(keys));
+    }
+    Ok(serde_json::Value::Object(content).to_string())
+}
+
+/// The JWT bundle file content: [`jwt_bundle_json`] in `format`, gzip-compressed when
+/// `compression` is `"gzip"`.
+pub(crate) fn jwt_bundle_content(
+    bundles: &JwtBundles,
+    format: &str,
+    compression: &str,
+) -> Result<Vec<u8>> {
+    let json = jwt_bundle_json(bundles, format)?;
+    if compression != JWT_BUNDLE_COMPRESSION_GZIP {
+        return Ok(json.into_bytes());
+    }
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(json.as_bytes())
+        .context("Failed to compress the JWT bundle")?;
+    encoder
+        .finish()
+        .context("Failed to compress the JWT bundle")
+}
+
+/// Reads back the certificate file at `path` and checks that its first certificate has the
+/// serial number of `expected`, catching a corrupted write or another writer racing the helper.
+fn verify_cert_file(path: &Path, expected: &Certificate) -> Result<()> {
+    let serial = |der: &[u8]| {
+        x509_parser::parse_x509_certificate(der).map(|(_, cert)| cert.raw_serial_as_string())
+    };
+    let expected_serial = serial(expected.as_ref())
+        .map_err(|e| anyhow!("Failed to parse the certificate just written: {e}"))?;
+
+    let content = fs::read(path).with_context(|| {
+        format!(
+            "Failed to read back certificate {} for verification",
+            path.display()
+        )
+    })?;
+    let written_serial = pem::parse(&content)
+        .map_err(|e| anyhow!("{e}"))
+        .and_then(|block| serial(&block.contents).map_err(|e| anyhow!("{e}")))
+        .map_err(|e| {
+            anyhow!(
+                "Verification of {} failed: the file does not hold a certificate: {e}",
+                path.display()
+            )
+        })?;
+    if written_serial != expected_serial {
+        anyhow::bail!(
+            "Verification of {} failed: read back serial {written_serial}, expected {expected_serial}",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Fails unless `leaf` has a URI SAN with the `spiffe` scheme, so that a certificate that is
+/// not an SVID is never written in place of one.
+fn check_spiffe_san(leaf: &Certificate) -> Result<()> {
+    let (_, cert) = x509_parser::parse_x509_certificate(leaf.as_ref())
+        .map_err(|e| anyhow!("Failed to parse leaf certificate: {e}"))?;
+    let has_spiffe_id =
+        cert.subject_alternative_name()
+            .map_err(|e| anyhow!("Failed to parse leaf certificate SANs: {e}"))?
+            .is_some_and(|san| {
+                san.value.general_names.iter().any(
+                    |name| matches!(name, GeneralName::URI(uri) if uri.starts_with("spiffe://")),
+                )
+            });
+    if !has_spiffe_id {
+        anyhow::bail!(
+            "Leaf certificate {} has no spiffe:// URI SAN and require_spiffe_san is set; \
+             refusing to write it",
+            cert.subject()
+        );
+    }
+    Ok(())
+}
+
+impl CredentialSink for LocalFileSystem {
+    fn write_cert_chain(&self, certificates: &[Certificate]) -> Result<()> {
+        if self.require_spiffe_san {
+            let leaf = certificates
+                .first()
+                .ok_or_else(|| anyhow!("Refusing to write an empty certificate chain"))?;
+            check_spiffe_san(leaf)?;
+        }
+
+        let content = certificates_pem(certificates, self.line_ending);
+        let cer_path = self.destination(&self.cer_path)?;
+
+        fs::write(&cer_path, &content)
+            .with_context(|| format!("Failed to write certificate to {}", cer_path.display()))?;
+
+        #[cfg(unix)]
+        fs::set_permissions(&cer_path, fs::Permissions::from_mode(self.cert_mode)).with_context(
+            || {
+                format!(
+                    "Failed to set permissions on certificate file {}",
+                    cer_path.display()
+                )
+            },
+        )?;
+
+        #[cfg(unix)]
+        self.set_owner(&cer_path)?;
+
+        if self.verify_after_write {
+            if let Some(leaf) = certificates.first() {
+                verify_cert_file(&cer_path, leaf)?;
+            }
+        }
+
+        self.write_fingerprint(&self.cer_path, content.as_bytes())
+    }
+
     fn write_key(&self, key: &[u8]) -> Result<()> {
-        let key_pem = pem::Pem {
-            tag: "PRIVATE KEY".to_string(),
-            contents: Vec::from(key),
-        };
+        let content = key_pem(key, self.line_ending);
+        let key_path = self.destination(&self.key_path)?;
 
-        let content = pem::encode(&key_pem);
+        fs::write(&key_path, content)
+            .with_context(|| format!("Failed to write key to {}", key_path.display()))?;
 
-        fs::write(&self.key_path, content)
-            .with_context(|| format!("Failed to write key to {}", self.key_path.display()))?;
+        #[cfg(unix)]
+        fs::set_permissions(&key_path, fs::Permissions::from_mode(self.key_mode)).with_context(
+            || {
+                format!(
+                    "Failed to set permissions on private key file {}",
+                    key_path.display()
+                )
+            },
+        )?;
 
         #[cfg(unix)]
-        fs::set_permissions(&self.key_path, fs::Permissions::from_mode(self.key_mode))
+        self.set_owner(&key_path)?;
+
+        Ok(())
+    }
+
+    fn write_bundle(&self, bundle: &X509Bundle) -> Result<()> {
+        let bundle_pem = certificates_pem(bundle.authorities(), self.line_ending);
+        let bundle_path = self.destination(&self.bundle_path)?;
+
+        fs::write(&bundle_path, &bundle_pem)
+            .with_context(|| format!("Failed to write bundle to {}", bundle_path.display()))?;
+
+        #[cfg(unix)]
+        fs::set_permissions(&bundle_path, fs::Permissions::from_mode(self.bundle_mode))
             .with_context(|| {
                 format!(
-                    "Failed to set permissions on private key file {}",
-                    self.key_path.display()
+                    "Failed to set permissions on bundle file {}",
+                    bundle_path.display()
                 )
             })?;
 
+        #[cfg(unix)]
+        self.set_owner(&bundle_path)?;
+
+        self.write_fingerprint(&self.bundle_path, bundle_pem.as_bytes())?;
+        self.write_bundle_dir(bundle.authorities())
+    }
+
+    /// Writes the JWT SVID token to `name` in the output directory.
+    fn write_jwt(&self, name: &str, token: &str) -> Result<()> {
+        let path = self.destination(&self.output_dir.join(name))?;
+
+        fs::write(&path, jwt_file_content(token, self.jwt_trailing_newline))
+            .with_context(|| format!("Failed to write JWT SVID to {}", path.display()))?;
+
+        #[cfg(unix)]
+        fs::set_permissions(&path, fs::Permissions::from_mode(self.jwt_svid_mode)).with_context(
+            || {
+                format!(
+                    "Failed to set permissions on JWT SVID file {}",
+                    path.display()
+                )
+            },
+        )?;
+
+        #[cfg(unix)]
+        self.set_owner(&path)?;
+
         Ok(())
     }
 
-    fn write_bundle(&self, bundle: &X509Bundle) -> Result<()> {
-        let bundle_pem = bundle
-            .authorities()
-            .iter()
-            .map(|cert: &spiffe::cert::Certificate| {
-                pem::encode(&pem::Pem {
-                    tag: "CERTIFICATE".to_string(),
-                    contents: cert.as_ref().to_vec(),
-                })
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
+    /// Writes the JWT bundles as rendered by [`jwt_bundle_content`].
+    fn write_jwt_bundle(&self, bundles: &JwtBundles) -> Result<()> {
+        let jwt_bundle_path = self
+            .jwt_bundle_path
+            .as_ref()
+            .ok_or_else(|| anyhow!("jwt_bundle_file_name must be configured"))?;
+        let jwt_bundle_path = &self.destination(jwt_bundle_path)?;
 
-        fs::write(&self.bundle_path, bundle_pem)
-            .with_context(|| format!("Failed to write bundle to {}", self.bundle_path.display()))?;
+        fs::write(
+            jwt_bundle_path,
+            jwt_bundle_content(
+                bundles,
+                &self.jwt_bundle_format,
+                &self.jwt_bundle_compression,
+            )?,
+        )
+        .with_context(|| {
+            format!(
+                "Failed to write JWT bundle to {}",
+                jwt_bundle_path.display()
+            )
+        })?;
 
         #[cfg(unix)]
         fs::set_permissions(
-            &self.bundle_path,
-            fs::Permissions::from_mode(self.bundle_mode),
+            jwt_bundle_path,
+            fs::Permissions::from_mode(self.jwt_bundle_mode),
         )
         .with_context(|| {
             format!(
-                "Failed to set permissions on bundle file {}",
-                self.bundle_path.display()
+                "Failed to set permissions on JWT bundle file {}",
+                jwt_bundle_path.display()
             )
         })?;
 
+        #[cfg(unix)]
+        self.set_owner(jwt_bundle_path)?;
+
         Ok(())
     }
+
+    /// Writes the CRLs as concatenated PEM with the certificate file mode, since they are public.
+    fn write_crl(&self, crls: &[Vec<u8>]) -> Result<()> {
+        let crl_path = self
+            .crl_path
+            .as_ref()
+            .ok_or_else(|| anyhow!("crl_file_name must be configured"))?;
+        let crl_path = &self.destination(crl_path)?;
+
+        fs::write(crl_path, crls_pem(crls, self.line_ending))
+            .with_context(|| format!("Failed to write CRL to {}", crl_path.display()))?;
+
+        #[cfg(unix)]
+        fs::set_permissions(crl_path, fs::Permissions::from_mode(self.cert_mode)).with_context(
+            || {
+                format!(
+                    "Failed to set permissions on CRL file {}",
+                    crl_path.display()
+                )
+            },
+        )?;
+
+        #[cfg(unix)]
+        self.set_owner(crl_path)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::config::JWT_BUNDLE_FORMAT_SPIFFE;
+
+    #[test]
+    fn test_sha256_hex_matches_known_digest() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    fn two_certificates() -> Vec<Certificate> {
+        ["example.org", "federated.org"]
+            .iter()
+            .map(|domain| {
+                let der = spire_agent_mock::svid::SvidGenerator::generate_ca_der(domain);
+                Certificate::try_from(der).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_certificates_pem_lf_round_trips() {
+        let certificates = two_certificates();
+        let content = certificates_pem(&certificates, pem::LineEnding::LF);
+
+        assert!(!content.contains('\r'), "{content:?}");
+        let parsed = pem::parse_many(&content).unwrap();
+        assert_eq!(parsed.len(), 2);
+        for (block, certificate) in parsed.iter().zip(&certificates) {
+            assert_eq!(block.contents, certificate.as_ref());
+        }
+    }
+
+    #[test]
+    fn test_certificates_pem_crlf_round_trips() {
+        let certificates = two_certificates();
+        let content = certificates_pem(&certificates, pem::LineEnding::CRLF);
+
+        assert_eq!(
+            content.matches('\n').count(),
+            content.matches("\r\n").count(),
+            "{content:?}"
+        );
+        let parsed = pem::parse_many(&content).unwrap();
+        assert_eq!(parsed.len(), 2);
+        for (block, certificate) in parsed.iter().zip(&certificates) {
+            assert_eq!(block.contents, certificate.as_ref());
+        }
+
+        let key = key_pem(b"key", pem::LineEnding::CRLF);
+        assert!(key.ends_with("-----END PRIVATE KEY-----\r\n"), "{key:?}");
+        assert_eq!(pem::parse(&key).unwrap().contents, b"key");
+    }
+
+    fn two_domain_bundles() -> JwtBundles {
+        JwtBundles::from([
+            (
+                "example.org".to_string(),
+                br#"{"keys":[{"kid":"a","kty":"EC"}],"spiffe_refresh_hint":300}"#.to_vec(),
+            ),
+            (
+                "federated.org".to_string(),
+                br#"{"keys":[{"kid":"b","kty":"EC"},{"kid":"c","kty":"RSA"}]}"#.to_vec(),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_jwt_bundle_json_spiffe_format_keys_by_trust_domain() {
+        let json: serde_json::Value = serde_json::from_str(
+            &jwt_bundle_json(&two_domain_bundles(), JWT_BUNDLE_FORMAT_SPIFFE).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "example.org": {"keys": [{"kid": "a", "kty": "EC"}], "spiffe_refresh_hint": 300},
+                "federated.org": {"keys": [{"kid": "b", "kty": "EC"}, {"kid": "c", "kty": "RSA"}]},
+            })
+        );
+    }
+
+    #[test]
+    fn test_jwt_bundle_json_flat_format_merges_keys() {
+        let json: serde_json::Value = serde_json::from_str(
+            &jwt_bundle_json(&two_domain_bundles(), JWT_BUNDLE_FORMAT_JWKS_FLAT).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "keys": [
+                    {"kid": "a", "kty": "EC"},
+                    {"kid": "b", "kty": "EC"},
+                    {"kid": "c", "kty": "RSA"},
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn test_local_file_system_writes_configured_jwt_bundle_format() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = Config {
+            cert_dir: Some(temp_dir.path().to_str().unwrap().to_string()),
+            jwt_bundle_file_name: Some("jwks.json".to_string()),
+            jwt_bundle_format: Some(JWT_BUNDLE_FORMAT_JWKS_FLAT.to_string()),
+            ..Default::default()
+        };
+
+        let fs = LocalFileSystem::new(&config).unwrap();
+        fs.write_jwt_bundle(&two_domain_bundles()).unwrap();
+
+        let written: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(temp_dir.path().join("jwks.json")).unwrap())
+                .unwrap();
+        assert_eq!(written["keys"].as_array().unwrap().len(), 3);
+        assert!(written.get("example.org").is_none());
+    }
+
+    #[test]
+    fn test_local_file_system_gzips_jwt_bundle() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = Config {
+            cert_dir: Some(temp_dir.path().to_str().unwrap().to_string()),
+            jwt_bundle_file_name: Some("jwt_bundle.json.gz".to_string()),
+            jwt_bundle_compression: Some(JWT_BUNDLE_COMPRESSION_GZIP.to_string()),
+            ..Default::default()
+        };
+
+        let fs = LocalFileSystem::new(&config).unwrap();
+        fs.write_jwt_bundle(&two_domain_bundles()).unwrap();
+
+        let written = std::fs::read(temp_dir.path().join("jwt_bundle.json.gz")).unwrap();
+        let mut json = String::new();
+        std::io::Read::read_to_string(&mut flate2::read::GzDecoder::new(&written[..]), &mut json)
+            .unwrap();
+        assert_eq!(
+            json,
+            jwt_bundle_json(&two_domain_bundles(), JWT_BUNDLE_FORMAT_SPIFFE).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_local_file_system_jwt_trailing_newline() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = Config {
+            cert_dir: Some(temp_dir.path().to_str().unwrap().to_string()),
+            ..Default::default()
+        };
+        let token = "header.payload.signature";
+
+        LocalFileSystem::new(&config)
+            .unwrap()
+            .write_jwt("raw.jwt", token)
+            .unwrap();
+        config.jwt_trailing_newline = Some(true);
+        LocalFileSystem::new(&config)
+            .unwrap()
+            .write_jwt("newline.jwt", token)
+            .unwrap();
+
+        let raw = std::fs::read(temp_dir.path().join("raw.jwt")).unwrap();
+        let newline = std::fs::read(temp_dir.path().join("newline.jwt")).unwrap();
+        assert_eq!(raw.len(), token.len());
+        assert_eq!(newline.len(), token.len() + 1);
+        assert_eq!(newline.last(), Some(&b'\n'));
+    }
+
+    #[test]
+    fn test_local_file_system_verifies_written_certificate() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = Config {
+            cert_dir: Some(temp_dir.path().to_str().unwrap().to_string()),
+            verify_after_write: Some(true),
+            ..Default::default()
+        };
+
+        let certificates = two_certificates();
+        LocalFileSystem::new(&config)
+            .unwrap()
+            .write_cert_chain(&certificates)
+            .unwrap();
+        verify_cert_file(&temp_dir.path().join("svid.pem"), &certificates[0]).unwrap();
+    }
+
+    #[test]
+    fn test_require_spiffe_san_refuses_non_spiffe_certificate() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = Config {
+            cert_dir: Some(temp_dir.path().to_str().unwrap().to_string()),
+            require_spiffe_san: Some(true),
+            ..Default::default()
+        };
+        let local_fs = LocalFileSystem::new(&config).unwrap();
+
+        let error = local_fs
+            .write_cert_chain(&[hashed_dir::tests::known_ca()])
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("has no spiffe:// URI SAN"),
+            "{error}"
+        );
+        assert!(!temp_dir.path().join("svid.pem").exists());
+
+        local_fs.write_cert_chain(&two_certificates()).unwrap();
+    }
+
+    #[test]
+    fn test_verify_cert_file_rejects_corrupt_or_replaced_certificate() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("svid.pem");
+        let expected = &two_certificates()[0];
+
+        std::fs::write(&path, "-----BEGIN CERTIFICATE-----\ngarbage\n").unwrap();
+        let error = verify_cert_file(&path, expected).unwrap_err();
+        assert!(
+            error.to_string().contains("does not hold a certificate"),
+            "{error}"
+        );
+
+        let other = certificates_pem(&[hashed_dir::tests::known_ca()], pem::LineEnding::LF);
+        std::fs::write(&path, other).unwrap();
+        let error = verify_cert_file(&path, expected).unwrap_err();
+        assert!(error.to_string().contains("read back serial"), "{error}");
+    }
+
+    #[test]
+    fn test_local_file_system_writes_hashed_bundle_dir() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let bundle_dir = temp_dir.path().join("ca");
+        std::fs::create_dir_all(&bundle_dir).unwrap();
+        std::fs::write(bundle_dir.join("00000000.0"), "stale").unwrap();
+        std::fs::write(bundle_dir.join("README"), "kept").unwrap();
+        let config = Config {
+            cert_dir: Some(temp_dir.path().to_str().unwrap().to_string()),
+            bundle_dir: Some(bundle_dir.to_str().unwrap().to_string()),
+            ..Default::default()
+        };
+
+        let trust_domain = spiffe::spiffe_id::TrustDomain::new("example.org").unwrap();
+        let mut bundle = X509Bundle::new(trust_domain);
+        bundle
+            .add_authority(hashed_dir::tests::known_ca().as_ref())
+            .unwrap();
+        LocalFileSystem::new(&config)
+            .unwrap()
+            .write_bundle(&bundle)
+            .unwrap();
+
+        let mut names: Vec<String> = std::fs::read_dir(&bundle_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        let hashed = format!("{}.0", hashed_dir::tests::KNOWN_CA_HASH);
+        assert_eq!(names, [hashed.clone(), "README".to_string()]);
+        let written = std::fs::read_to_string(bundle_dir.join(hashed)).unwrap();
+        assert_eq!(
+            pem::parse(written).unwrap().contents,
+            hashed_dir::tests::known_ca().as_ref()
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_file_modes_are_ignored_on_windows() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = Config {
+            cert_dir: Some(temp_dir.path().join("certs").to_str().unwrap().to_string()),
+            key_file_mode: Some("0400".to_string()),
+            cert_dir_mode: Some("0700".to_string()),
+            file_owner_uid: Some(1000),
+            ..Default::default()
+        };
+
+        let fs = LocalFileSystem::new(&config).unwrap().ensure().unwrap();
+        fs.write_key(b"key").unwrap();
+
+        assert!(temp_dir.path().join("certs").join("svid_key.pem").exists());
+    }
 }
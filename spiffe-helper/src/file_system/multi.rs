@@ -0,0 +1,292 @@
+use std::collections::BTreeMap;
+use std::sync::{Mutex, MutexGuard};
+use std::time::SystemTime;
+
+use anyhow::{anyhow, Result};
+use spiffe::bundle::x509::X509Bundle;
+use spiffe::cert::Certificate;
+
+use super::{CredentialSink, LocalFileSystem, X509Update};
+use crate::cli::Config;
+use crate::health::{CredentialStatus, OutputStatus};
+use crate::jwt::JwtBundles;
+
+/// The failing writes of one output, keyed by what was written, how many writes in a row
+/// failed, and when it last succeeded.
+#[derive(Debug, Default)]
+struct OutputState {
+    failures: BTreeMap<String, String>,
+    consecutive_failures: u32,
+    last_success: Option<SystemTime>,
+}
+
+/// A [`CredentialSink`] that writes every credential to each of several outputs, for a helper
+/// serving consumers that need the same SVID in different places. A failing output does not
+/// stop the others; the write fails if any output failed, and each output's outcome is
+/// reported through [`CredentialSink::output_statuses`].
+pub struct MultiSink<S> {
+    outputs: Vec<(String, S)>,
+    states: Mutex<Vec<OutputState>>,
+}
+
+impl<S: CredentialSink> MultiSink<S> {
+    /// Writes to each `(name, sink)` pair in order; `name` identifies the output in errors and
+    /// the health status.
+    #[must_use]
+    pub fn new(outputs: Vec<(String, S)>) -> Self {
+        let states = outputs.iter().map(|_| OutputState::default()).collect();
+        Self {
+            outputs,
+            states: Mutex::new(states),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<OutputState>> {
+        self.states
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    fn write_all(&self, what: &str, write: impl Fn(&S) -> Result<()>) -> Result<()> {
+        let mut states = self.lock();
+        let mut failed = Vec::new();
+        for ((name, sink), state) in self.outputs.iter().zip(states.iter_mut()) {
+            match write(sink) {
+                Ok(()) => {
+                    state.failures.remove(what);
+                    state.last_success = Some(SystemTime::now());
+                    // The output recovers once nothing written to it is failing any more
+                    if state.failures.is_empty() {
+                        state.consecutive_failures = 0;
+                    }
+                }
+                Err(e) => {
+                    let error = format!("{e:#}");
+                    failed.push(format!("{name}: {error}"));
+                    state.failures.insert(what.to_string(), error);
+                    state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+                }
+            }
+        }
+
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "Failed to write {what} to {} of {} outputs: {}",
+                failed.len(),
+                self.outputs.len(),
+                failed.join("; ")
+            ))
+        }
+    }
+}
+
+impl MultiSink<LocalFileSystem> {
+    /// One [`LocalFileSystem`] per entry of [`Config::output_configs`], each with its
+    /// directory created and checked for writability.
+    pub fn local(config: &Config) -> Result<Self> {
+        let outputs = config
+            .output_configs()
+            .iter()
+            .map(|output| {
+                let name = output.cert_dir.clone().unwrap_or_default();
+                Ok((name, LocalFileSystem::new(output)?.ensure()?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self::new(outputs))
+    }
+}
+
+impl<S: CredentialSink> CredentialSink for MultiSink<S> {
+    fn write_cert_chain(&self, certificates: &[Certificate]) -> Result<()> {
+        self.write_all("certificate chain", |sink| {
+            sink.write_cert_chain(certificates)
+        })
+    }
+
+    fn write_key(&self, key: &[u8]) -> Result<()> {
+        self.write_all("private key", |sink| sink.write_key(key))
+    }
+
+    fn write_bundle(&self, bundle: &X509Bundle) -> Result<()> {
+        self.write_all("bundle", |sink| sink.write_bundle(bundle))
+    }
+
+    fn write_jwt(&self, name: &str, token: &str) -> Result<()> {
+        self.write_all(&format!("JWT SVID {name}"), |sink| {
+            sink.write_jwt(name, token)
+        })
+    }
+
+    fn write_jwt_bundle(&self, bundles: &JwtBundles) -> Result<()> {
+        self.write_all("JWT bundle", |sink| sink.write_jwt_bundle(bundles))
+    }
+
+    fn write_crl(&self, crls: &[Vec<u8>]) -> Result<()> {
+        self.write_all("CRL", |sink| sink.write_crl(crls))
+    }
+
+    fn write_x509_update(&self, update: &X509Update<'_>) -> Result<()> {
+        self.write_all("X.509 SVID", |sink| sink.write_x509_update(update))
+    }
+
+    fn output_statuses(&self) -> Vec<OutputStatus> {
+        self.outputs
+            .iter()
+            .zip(self.lock().iter())
+            .map(|((name, _), state)| OutputStatus {
+                name: name.clone(),
+                status: CredentialStatus {
+                    write_succeeded: state.failures.is_empty(),
+                    consecutive_failures: state.consecutive_failures,
+                    last_success: state.last_success,
+                    last_error: state
+                        .failures
+                        .iter()
+                        .next()
+                        .map(|(what, error)| format!("{what}: {error}")),
+                },
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_system::MemorySink;
+    use crate::health::HealthStatus;
+    use spiffe::spiffe_id::TrustDomain;
+
+    /// A [`MemorySink`] whose writes fail while `fail` is set.
+    #[derive(Default)]
+    struct FlakySink {
+        fail: bool,
+        inner: MemorySink,
+    }
+
+    impl FlakySink {
+        fn check(&self) -> Result<()> {
+            if self.fail {
+                Err(anyhow!("read-only file system"))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    impl CredentialSink for FlakySink {
+        fn write_cert_chain(&self, certificates: &[Certificate]) -> Result<()> {
+            self.check()?;
+            self.inner.write_cert_chain(certificates)
+        }
+        fn write_key(&self, key: &[u8]) -> Result<()> {
+            self.check()?;
+            self.inner.write_key(key)
+        }
+        fn write_bundle(&self, bundle: &X509Bundle) -> Result<()> {
+            self.check()?;
+            self.inner.write_bundle(bundle)
+        }
+        fn write_jwt(&self, name: &str, token: &str) -> Result<()> {
+            self.check()?;
+            self.inner.write_jwt(name, token)
+        }
+        fn write_jwt_bundle(&self, bundles: &JwtBundles) -> Result<()> {
+            self.check()?;
+            self.inner.write_jwt_bundle(bundles)
+        }
+        fn write_crl(&self, crls: &[Vec<u8>]) -> Result<()> {
+            self.check()?;
+            self.inner.write_crl(crls)
+        }
+    }
+
+    #[test]
+    fn test_writes_every_output() {
+        let sink = MultiSink::new(vec![
+            ("a".to_string(), MemorySink::new()),
+            ("b".to_string(), MemorySink::new()),
+        ]);
+
+        sink.write_key(b"key").unwrap();
+
+        for (_, output) in &sink.outputs {
+            assert_eq!(output.contents().key.as_deref(), Some(&b"key"[..]));
+        }
+        let statuses = sink.output_statuses();
+        assert_eq!(statuses.len(), 2);
+        assert!(statuses.iter().all(|s| s.status.write_succeeded));
+    }
+
+    #[test]
+    fn test_failing_output_is_reported_without_stopping_others() {
+        let sink = MultiSink::new(vec![
+            ("good".to_string(), FlakySink::default()),
+            (
+                "bad".to_string(),
+                FlakySink {
+                    fail: true,
+                    ..Default::default()
+                },
+            ),
+        ]);
+
+        let error = sink.write_key(b"key").unwrap_err().to_string();
+        assert!(error.contains("Failed to write private key to 1 of 2 outputs"));
+        assert!(error.contains("bad: read-only file system"));
+
+        assert_eq!(
+            sink.outputs[0].1.inner.contents().key.as_deref(),
+            Some(&b"key"[..])
+        );
+
+        let statuses = sink.output_statuses();
+        assert!(statuses[0].status.write_succeeded);
+        assert!(!statuses[1].status.write_succeeded);
+        assert_eq!(
+            statuses[1].status.last_error.as_deref(),
+            Some("private key: read-only file system")
+        );
+    }
+
+    #[test]
+    fn test_counts_consecutive_failures_per_output() {
+        let mut sink = MultiSink::new(vec![
+            ("good".to_string(), FlakySink::default()),
+            ("bad".to_string(), FlakySink::default()),
+        ]);
+        let bundle = X509Bundle::new(TrustDomain::new("example.org").unwrap());
+        sink.write_key(b"key").unwrap();
+
+        let mut health = HealthStatus {
+            liveness_failure_threshold: 2,
+            ..Default::default()
+        };
+        health.x509_svid.record_success();
+
+        sink.outputs[1].1.fail = true;
+        sink.write_key(b"key").unwrap_err();
+        health.outputs = sink.output_statuses();
+        assert!(health.is_live());
+
+        sink.write_bundle(&bundle).unwrap_err();
+        health.outputs = sink.output_statuses();
+        assert!(!health.is_live());
+
+        let statuses = sink.output_statuses();
+        assert_eq!(statuses[0].status.consecutive_failures, 0);
+        assert_eq!(statuses[1].status.consecutive_failures, 2);
+
+        // One write succeeding is not enough while another still fails
+        sink.outputs[1].1.fail = false;
+        sink.write_key(b"key").unwrap();
+        assert_eq!(sink.output_statuses()[1].status.consecutive_failures, 2);
+
+        sink.write_bundle(&bundle).unwrap();
+        let statuses = sink.output_statuses();
+        assert_eq!(statuses[1].status.consecutive_failures, 0);
+        assert!(statuses[1].status.write_succeeded);
+    }
+}
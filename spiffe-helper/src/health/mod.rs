@@ -1,5 +1,10 @@
 pub mod server;
 pub mod status;
+pub mod tls;
 
 pub use server::HealthCheckServer;
-pub use status::{create_health_status, CredentialStatus, HealthStatus, SharedHealthStatus};
+pub use status::{
+    create_health_status, CredentialStatus, HealthStatus, HealthTransition, HealthTransitions,
+    HookStatus, ObservedSvid, OutputStatus, SharedHealthStatus, SvidInfo,
+};
+pub use tls::TlsIdentity;
@@ -1,28 +1,53 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
 use anyhow::{Context, Result};
-use axum::{http::StatusCode, response::IntoResponse, routing::get, Router};
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::{self, Next},
+    response::IntoResponse,
+    response::Response,
+    routing::get,
+    Extension, Json, Router,
+};
+use hyper_util::rt::TokioIo;
+use hyper_util::service::TowerToHyperService;
+use tokio::net::TcpListener;
 use tokio::sync::oneshot;
 use tokio::task::JoinHandle;
 use tokio::time::{interval, Duration, MissedTickBehavior};
+use tokio_rustls::TlsAcceptor;
 
 use crate::cli::HealthChecksConfig;
+use crate::health::{tls, HealthStatus, SharedHealthStatus};
+use crate::info;
+
+const HEARTBEAT_PERIOD: Duration = Duration::from_secs(30);
 
 /// A handle to the health check server.
 pub enum HealthCheckServer {
     Disabled,
     Enabled {
         server_handle: JoinHandle<Result<()>>,
-        heartbeat_handle: JoinHandle<()>,
+        /// The heartbeat task, absent when `heartbeat_enabled = false`
+        heartbeat_handle: Option<JoinHandle<()>>,
         receiver: oneshot::Receiver<Result<()>>,
+        /// The address actually bound, with the port the OS chose for `bind_port = 0`
+        local_addr: SocketAddr,
     },
 }
 
 impl HealthCheckServer {
-    pub async fn new(health_checks: Option<&HealthChecksConfig>) -> Result<Self> {
+    pub async fn new(
+        health_checks: Option<&HealthChecksConfig>,
+        status: SharedHealthStatus,
+    ) -> Result<Self> {
         match health_checks {
             None => Ok(Self::Disabled),
             Some(hc) => {
                 if hc.listener_enabled {
-                    start(hc).await
+                    start(hc, status).await
                 } else {
                     Ok(Self::Disabled)
                 }
@@ -37,14 +62,12 @@ impl HealthCheckServer {
         match self {
             HealthCheckServer::Disabled => std::future::pending().await,
             HealthCheckServer::Enabled {
-                server_handle: _,
                 heartbeat_handle,
                 receiver,
+                ..
             } => match receiver.await {
                 Ok(res) => {
-                    if !heartbeat_handle.is_finished() {
-                        heartbeat_handle.abort();
-                    }
+                    stop_heartbeat(heartbeat_handle.as_ref());
                     res
                 }
                 Err(_) => Err(anyhow::anyhow!("Health check server task disappeared")),
@@ -59,15 +82,13 @@ impl HealthCheckServer {
             HealthCheckServer::Enabled {
                 server_handle,
                 heartbeat_handle,
-                receiver: _,
+                ..
             } => {
                 if !server_handle.is_finished() {
                     server_handle.abort();
-                    println!("Health check server stopped");
-                }
-                if !heartbeat_handle.is_finished() {
-                    heartbeat_handle.abort();
+                    info!("Health check server stopped");
                 }
+                stop_heartbeat(heartbeat_handle.as_ref());
             }
         }
     }
@@ -77,48 +98,212 @@ impl HealthCheckServer {
     pub fn is_enabled(&self) -> bool {
         matches!(self, HealthCheckServer::Enabled { .. })
     }
+
+    /// The address the server listens on, if enabled. With `bind_port = 0` this reports the
+    /// port the OS chose.
+    #[must_use]
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        match self {
+            HealthCheckServer::Disabled => None,
+            HealthCheckServer::Enabled { local_addr, .. } => Some(*local_addr),
+        }
+    }
+}
+
+/// HTTP 200 when `healthy`, otherwise HTTP 503 with [`HealthStatus::failure_reason`] as the
+/// body.
+fn probe_response(healthy: bool, status: &HealthStatus) -> Response {
+    if healthy {
+        StatusCode::OK.into_response()
+    } else {
+        let reason = status.failure_reason().unwrap_or_default();
+        (StatusCode::SERVICE_UNAVAILABLE, reason).into_response()
+    }
+}
+
+async fn liveness_handler(State(status): State<SharedHealthStatus>) -> Response {
+    let status = status.read().await;
+    probe_response(status.is_live(), &status)
+}
+
+/// Readiness also fails once the leaf is within `expiry_threshold` of expiring, so traffic
+/// drains from a certificate that is not rotating. A zero threshold disables the check. With
+/// `requires_child`, it also fails once a managed process has exited.
+async fn readiness_handler(
+    State(status): State<SharedHealthStatus>,
+    expiry_threshold: Duration,
+    requires_child: bool,
+) -> Response {
+    let status = status.read().await;
+    let expiring = !expiry_threshold.is_zero() && status.expires_within(expiry_threshold);
+    let child_exited = requires_child && !status.exited_processes.is_empty();
+    probe_response(status.is_ready() && !expiring && !child_exited, &status)
+}
+
+async fn info_handler(State(status): State<SharedHealthStatus>) -> Response {
+    match &status.read().await.svid_info {
+        Some(info) => Json(info.clone()).into_response(),
+        None => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+    }
+}
+
+/// The SPIFFE ID from the client certificate of a TLS connection, if the client presented one.
+#[derive(Debug, Clone)]
+struct PeerSpiffeId(Option<String>);
+
+/// Rejects requests with 403 unless the client certificate's SPIFFE ID is in `allowed`.
+async fn authorize_client(
+    State(allowed): State<Arc<[String]>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let peer = request
+        .extensions()
+        .get::<PeerSpiffeId>()
+        .and_then(|peer| peer.0.clone());
+    match peer {
+        Some(id) if allowed.contains(&id) => next.run(request).await,
+        peer => {
+            eprintln!(
+                "Health check request from {} rejected: not in allowed_client_ids",
+                peer.as_deref().unwrap_or("a client without a SPIFFE ID")
+            );
+            StatusCode::FORBIDDEN.into_response()
+        }
+    }
 }
 
-async fn liveness_handler() -> impl IntoResponse {
-    StatusCode::OK
+/// Starts the periodic liveness log, the only one the helper prints, passing each line to
+/// `emit`. Returns `None` without starting a task when `heartbeat_enabled = false`.
+fn spawn_heartbeat(
+    hc: &HealthChecksConfig,
+    period: Duration,
+    emit: impl Fn(&str) + Send + 'static,
+) -> Option<JoinHandle<()>> {
+    let message = hc.heartbeat_message()?;
+    Some(tokio::spawn(async move {
+        let mut liveness_interval = interval(period);
+        liveness_interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        loop {
+            liveness_interval.tick().await;
+            emit(&message);
+        }
+    }))
 }
 
-async fn readiness_handler() -> impl IntoResponse {
-    StatusCode::OK
+fn stop_heartbeat(heartbeat_handle: Option<&JoinHandle<()>>) {
+    if let Some(handle) = heartbeat_handle {
+        if !handle.is_finished() {
+            handle.abort();
+        }
+    }
 }
 
-async fn heartbeat_reporter() {
-    let mut liveness_interval = interval(Duration::from_secs(30));
-    liveness_interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+/// Serves `app` over TLS, completing each handshake on its own task so a stalled client cannot
+/// hold up the others. Handshake failures, such as a client without a trusted certificate, are
+/// logged and the connection dropped. Requests carry the client's SPIFFE ID as a
+/// [`PeerSpiffeId`] extension.
+async fn serve_tls(listener: TcpListener, acceptor: TlsAcceptor, app: Router) -> Result<()> {
     loop {
-        liveness_interval.tick().await;
-        println!("spiffe-helper daemon is alive");
+        let (stream, peer) = listener
+            .accept()
+            .await
+            .context("Health check server stopped")?;
+        let acceptor = acceptor.clone();
+        let app = app.clone();
+        tokio::spawn(async move {
+            let stream = match acceptor.accept(stream).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    eprintln!("Health check TLS handshake with {peer} failed: {e}");
+                    return;
+                }
+            };
+            let peer_id = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|chain| chain.first())
+                .and_then(|leaf| tls::spiffe_id_of(leaf));
+            let service = TowerToHyperService::new(app.layer(Extension(PeerSpiffeId(peer_id))));
+            if let Err(e) = hyper::server::conn::http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                eprintln!("Health check connection with {peer} failed: {e}");
+            }
+        });
     }
 }
 
 /// Starts the health check HTTP server if enabled in configuration.
-async fn start(hc: &HealthChecksConfig) -> Result<HealthCheckServer> {
+async fn start(hc: &HealthChecksConfig, status: SharedHealthStatus) -> Result<HealthCheckServer> {
     let (tx, rx) = oneshot::channel();
     let addr = hc.bind_addr();
     let liveness = hc.liveness_path();
     let readiness = hc.readiness_path();
+    let info = hc.info_path();
+    let expiry_threshold = hc.readiness_expiry_threshold();
+    let requires_child = hc.readiness_requires_child();
 
-    println!("Starting health check server on {addr}");
-    println!("  Liveness path: {liveness}");
-    println!("  Readiness path: {readiness}");
+    info!("Starting health check server on {addr}");
+    info!("  Liveness path: {liveness}");
+    info!("  Readiness path: {readiness}");
+    info!("  Info path: {info}");
 
-    let app = Router::new()
+    let mut app = Router::new()
         .route(&liveness, get(liveness_handler))
-        .route(&readiness, get(readiness_handler));
+        .route(
+            &readiness,
+            get(move |state| readiness_handler(state, expiry_threshold, requires_child)),
+        )
+        .route(&info, get(info_handler));
+    let allowed_client_ids = hc.allowed_client_ids();
+    if !allowed_client_ids.is_empty() {
+        info!("  Allowed clients: {}", allowed_client_ids.join(", "));
+        app = app.layer(middleware::from_fn_with_state(
+            Arc::<[String]>::from(allowed_client_ids),
+            authorize_client,
+        ));
+    }
+    let app = app.with_state(status.clone());
 
-    let listener = tokio::net::TcpListener::bind(&addr)
+    let listener = TcpListener::bind(&addr)
         .await
         .with_context(|| format!("Failed to bind to {addr}"))?;
+    let local_addr = listener
+        .local_addr()
+        .context("Failed to read the health check server address")?;
+    info!("Health check server listening on {local_addr}");
 
-    let server_handle = tokio::spawn(async move {
-        let res = axum::serve(listener, app)
+    let tls_acceptor = if hc.is_tls_enabled() {
+        let identity = status
+            .write()
             .await
-            .context("Health check server stopped");
+            .tls_identity
+            .get_or_insert_with(Default::default)
+            .clone();
+        let server_config = identity.server_config(hc.requires_client_cert())?;
+        info!(
+            "  TLS: enabled, client certificates {}",
+            if hc.requires_client_cert() {
+                "required"
+            } else {
+                "not requested"
+            }
+        );
+        Some(TlsAcceptor::from(Arc::new(server_config)))
+    } else {
+        None
+    };
+
+    let server_handle = tokio::spawn(async move {
+        let res = match tls_acceptor {
+            Some(acceptor) => serve_tls(listener, acceptor, app).await,
+            None => axum::serve(listener, app)
+                .await
+                .context("Health check server stopped"),
+        };
 
         let signal = res.as_ref().cloned().map_err(|e| anyhow::anyhow!("{e}"));
         let _ = tx.send(signal);
@@ -126,13 +311,56 @@ async fn start(hc: &HealthChecksConfig) -> Result<HealthCheckServer> {
         res
     });
 
-    let heartbeat_handle = tokio::spawn(heartbeat_reporter());
+    let heartbeat_handle = spawn_heartbeat(hc, HEARTBEAT_PERIOD, |message| info!("{message}"));
 
     let server = HealthCheckServer::Enabled {
         server_handle,
         heartbeat_handle,
         receiver: rx,
+        local_addr,
     };
 
     Ok(server)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn health_checks(heartbeat_enabled: Option<bool>) -> HealthChecksConfig {
+        HealthChecksConfig {
+            listener_enabled: true,
+            bind_port: 0,
+            heartbeat_enabled,
+            heartbeat_message: Some("sidecar up".to_string()),
+            ..Default::default()
+        }
+    }
+
+    async fn heartbeat_lines(hc: &HealthChecksConfig) -> Vec<String> {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let sink = lines.clone();
+        let handle = spawn_heartbeat(hc, Duration::from_millis(10), move |message| {
+            sink.lock().unwrap().push(message.to_string());
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        stop_heartbeat(handle.as_ref());
+        let lines = lines.lock().unwrap().clone();
+        lines
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_logs_custom_message() {
+        let lines = heartbeat_lines(&health_checks(None)).await;
+        assert!(!lines.is_empty());
+        assert!(lines.iter().all(|line| line == "sidecar up"), "{lines:?}");
+    }
+
+    #[tokio::test]
+    async fn test_disabled_heartbeat_logs_nothing() {
+        let hc = health_checks(Some(false));
+        assert!(spawn_heartbeat(&hc, Duration::from_millis(10), |_| {}).is_none());
+        assert!(heartbeat_lines(&hc).await.is_empty());
+    }
+}
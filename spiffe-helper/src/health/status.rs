@@ -1,18 +1,93 @@
+use serde::Serialize;
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::sync::RwLock;
 
+use super::TlsIdentity;
+
 /// Status of a single credential type
 #[derive(Debug, Clone, Default)]
 pub struct CredentialStatus {
     /// Whether the last write operation succeeded
     pub write_succeeded: bool,
+    /// Failed writes since the last successful one
+    pub consecutive_failures: u32,
     /// When the credential was last successfully written
     pub last_success: Option<SystemTime>,
     /// Error message if last write failed
     pub last_error: Option<String>,
 }
 
+impl CredentialStatus {
+    /// Record a successful write
+    pub fn record_success(&mut self) {
+        self.write_succeeded = true;
+        self.consecutive_failures = 0;
+        self.last_success = Some(SystemTime::now());
+        self.last_error = None;
+    }
+
+    /// Record a failed write
+    pub fn record_failure(&mut self, error: String) {
+        self.write_succeeded = false;
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        self.last_error = Some(error);
+    }
+
+    /// Whether the credential counts towards liveness: its last write succeeded, or it was
+    /// written before and has since failed fewer than `threshold` times in a row.
+    #[must_use]
+    pub fn is_live(&self, threshold: u32) -> bool {
+        self.write_succeeded
+            || (self.last_success.is_some() && self.consecutive_failures < threshold)
+    }
+}
+
+/// Status of one destination of a sink that writes to several, such as an `output` block
+#[derive(Debug, Clone, Default)]
+pub struct OutputStatus {
+    /// Identifies the output, e.g. its `cert_dir`
+    pub name: String,
+    pub status: CredentialStatus,
+}
+
+/// Outcome of the most recent run of a post-rotation step, such as the post-rotation command
+/// or the renew signal confirmation
+#[derive(Debug, Clone, Default)]
+pub struct HookStatus {
+    /// Whether the last run succeeded
+    pub succeeded: bool,
+    /// When the command last ran
+    pub last_run: Option<SystemTime>,
+    /// Error message if the last run failed
+    pub last_error: Option<String>,
+}
+
+/// Identity of the leaf certificate the helper last wrote, served on the info endpoint
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SvidInfo {
+    pub spiffe_id: String,
+    /// Serial number as colon-separated hex bytes
+    pub serial: String,
+    /// Start of the validity period, in seconds since the Unix epoch
+    pub not_before: i64,
+    /// End of the validity period, in seconds since the Unix epoch
+    pub not_after: i64,
+    /// Every SVID the agent offered in the same update, of which this one was selected. Only
+    /// collected while the health check listener is enabled.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub observed_svids: Vec<ObservedSvid>,
+}
+
+/// An SVID the agent offered to the workload, listed on the info endpoint to debug `hint`
+/// selection
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ObservedSvid {
+    pub spiffe_id: String,
+    /// Hint the agent set on the SVID, if any
+    pub hint: Option<String>,
+}
+
 /// Aggregated health status for all credential types
 #[derive(Debug, Clone, Default)]
 pub struct HealthStatus {
@@ -20,17 +95,38 @@ pub struct HealthStatus {
     pub x509_bundle: Option<CredentialStatus>, // Only if bundle configured
     pub jwt_bundle: Option<CredentialStatus>,  // Only if JWT bundle configured
     pub jwt_svids: Vec<CredentialStatus>,      // One per configured JWT SVID
+    pub outputs: Vec<OutputStatus>,            // One per output, if writing to several
+    pub post_rotation_cmd: Option<HookStatus>, // Only once the post-rotation command has run
+    pub renew_signal_confirmation: Option<HookStatus>, // Only once a renew signal was confirmed or timed out
+    pub svid_info: Option<SvidInfo>,                   // Only once an SVID has been processed
+    pub tls_identity: Option<Arc<TlsIdentity>>,        // Only if the server uses TLS
+    pub exited_processes: Vec<String>,                 // Managed processes that have exited
+    pub liveness_failure_threshold: u32, // Consecutive failed writes tolerated; 0 counts as 1
 }
 
 impl HealthStatus {
-    /// Check if the helper is live (no recent failures)
+    /// Check if the helper is live (no credential failing more than
+    /// `liveness_failure_threshold` times in a row)
     #[must_use]
     pub fn is_live(&self) -> bool {
+        let threshold = self.liveness_failure_threshold.max(1);
         // Live if X.509 SVID write succeeded (at minimum)
-        self.x509_svid.write_succeeded
-            && self.x509_bundle.as_ref().is_none_or(|s| s.write_succeeded)
-            && self.jwt_bundle.as_ref().is_none_or(|s| s.write_succeeded)
-            && self.jwt_svids.iter().all(|s| s.write_succeeded)
+        self.x509_svid.is_live(threshold)
+            && self
+                .x509_bundle
+                .as_ref()
+                .is_none_or(|s| s.is_live(threshold))
+            && self
+                .jwt_bundle
+                .as_ref()
+                .is_none_or(|s| s.is_live(threshold))
+            && self.jwt_svids.iter().all(|s| s.is_live(threshold))
+            && self.outputs.iter().all(|o| o.status.is_live(threshold))
+            && self.post_rotation_cmd.as_ref().is_none_or(|s| s.succeeded)
+            && self
+                .renew_signal_confirmation
+                .as_ref()
+                .is_none_or(|s| s.succeeded)
     }
 
     /// Check if the helper is ready (all initial writes complete)
@@ -47,6 +143,130 @@ impl HealthStatus {
                 .as_ref()
                 .is_none_or(|s| s.last_success.is_some())
             && self.jwt_svids.iter().all(|s| s.last_success.is_some())
+            && self.outputs.iter().all(|o| o.status.last_success.is_some())
+    }
+
+    /// Names the first credential or post-rotation step that is failing liveness, with its
+    /// error, or else the first configured credential that was never written.
+    #[must_use]
+    pub fn failure_reason(&self) -> Option<String> {
+        let credentials = std::iter::once(("X.509 SVID".to_string(), Some(&self.x509_svid)))
+            .chain(std::iter::once((
+                "X.509 bundle".to_string(),
+                self.x509_bundle.as_ref(),
+            )))
+            .chain(std::iter::once((
+                "JWT bundle".to_string(),
+                self.jwt_bundle.as_ref(),
+            )))
+            .chain(
+                self.jwt_svids
+                    .iter()
+                    .enumerate()
+                    .map(|(index, s)| (format!("JWT SVID {}", index + 1), Some(s))),
+            )
+            .chain(
+                self.outputs
+                    .iter()
+                    .map(|o| (format!("output {}", o.name), Some(&o.status))),
+            )
+            .filter_map(|(name, status)| status.map(|s| (name, s)))
+            .collect::<Vec<_>>();
+        let hooks = [
+            ("post-rotation command", self.post_rotation_cmd.as_ref()),
+            (
+                "renew signal confirmation",
+                self.renew_signal_confirmation.as_ref(),
+            ),
+        ];
+
+        let describe = |name: &str, error: Option<&String>| match error {
+            Some(error) => format!("{name}: {error}"),
+            None => format!("{name} failed"),
+        };
+
+        credentials
+            .iter()
+            .find(|(_, s)| !s.write_succeeded && s.last_error.is_some())
+            .map(|(name, s)| describe(name, s.last_error.as_ref()))
+            .or_else(|| {
+                hooks.iter().find_map(|(name, hook)| {
+                    hook.filter(|h| !h.succeeded)
+                        .map(|h| describe(name, h.last_error.as_ref()))
+                })
+            })
+            .or_else(|| {
+                credentials
+                    .iter()
+                    .find(|(_, s)| s.last_success.is_none())
+                    .map(|(name, _)| format!("{name} not yet written"))
+            })
+    }
+
+    /// Check if the last written leaf certificate expires within `threshold` from now.
+    /// False until an SVID has been processed.
+    #[must_use]
+    pub fn expires_within(&self, threshold: Duration) -> bool {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let deadline = i64::try_from((now + threshold).as_secs()).unwrap_or(i64::MAX);
+        self.svid_info
+            .as_ref()
+            .is_some_and(|info| deadline > info.not_after)
+    }
+}
+
+/// A change in liveness or readiness between two observations of the health status.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthTransition {
+    /// `"live"` or `"ready"`
+    pub probe: &'static str,
+    /// The new value of the probe
+    pub healthy: bool,
+    /// Why the probe is failing, when it became unhealthy
+    pub reason: Option<String>,
+}
+
+impl std::fmt::Display for HealthTransition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.healthy, &self.reason) {
+            (true, _) => write!(f, "Health: now {}", self.probe),
+            (false, Some(reason)) => write!(f, "Health: no longer {} ({reason})", self.probe),
+            (false, None) => write!(f, "Health: no longer {}", self.probe),
+        }
+    }
+}
+
+/// Remembers the last observed liveness and readiness so that only their edges are reported.
+#[derive(Debug, Clone, Default)]
+pub struct HealthTransitions {
+    live: Option<bool>,
+    ready: Option<bool>,
+}
+
+impl HealthTransitions {
+    /// Compares `status` with the previous observation and returns the probes that changed.
+    /// The first observation only records the baseline.
+    pub fn observe(&mut self, status: &HealthStatus) -> Vec<HealthTransition> {
+        let mut transitions = Vec::new();
+        for (probe, previous, current) in [
+            ("live", &mut self.live, status.is_live()),
+            ("ready", &mut self.ready, status.is_ready()),
+        ] {
+            if previous.replace(current).is_some_and(|p| p != current) {
+                transitions.push(HealthTransition {
+                    probe,
+                    healthy: current,
+                    reason: if current {
+                        None
+                    } else {
+                        status.failure_reason()
+                    },
+                });
+            }
+        }
+        transitions
     }
 }
 
@@ -72,6 +292,34 @@ mod tests {
         assert!(status.last_error.is_none());
     }
 
+    #[test]
+    fn test_credential_status_record_success_and_failure() {
+        let mut status = CredentialStatus::default();
+
+        status.record_failure("disk full".to_string());
+        assert!(!status.write_succeeded);
+        assert!(status.last_success.is_none());
+        assert_eq!(status.last_error.as_deref(), Some("disk full"));
+
+        status.record_success();
+        assert!(status.write_succeeded);
+        assert!(status.last_success.is_some());
+        assert!(status.last_error.is_none());
+    }
+
+    #[test]
+    fn test_credential_status_counts_consecutive_failures() {
+        let mut status = CredentialStatus::default();
+        status.record_success();
+
+        status.record_failure("disk full".to_string());
+        status.record_failure("disk full".to_string());
+        assert_eq!(status.consecutive_failures, 2);
+
+        status.record_success();
+        assert_eq!(status.consecutive_failures, 0);
+    }
+
     #[test]
     fn test_health_status_default() {
         let status = HealthStatus::default();
@@ -95,12 +343,71 @@ mod tests {
         assert!(!status.is_live());
     }
 
+    #[test]
+    fn test_is_live_tolerates_failures_below_threshold() {
+        let mut status = HealthStatus {
+            liveness_failure_threshold: 2,
+            ..Default::default()
+        };
+        status.x509_svid.record_success();
+
+        status.x509_svid.record_failure("disk full".to_string());
+        assert!(status.is_live());
+
+        status.x509_svid.record_failure("disk full".to_string());
+        assert!(!status.is_live());
+    }
+
+    #[test]
+    fn test_is_live_tolerates_output_failures_below_threshold() {
+        let mut status = HealthStatus {
+            liveness_failure_threshold: 2,
+            ..Default::default()
+        };
+        status.x509_svid.record_success();
+        let mut output = CredentialStatus::default();
+        output.record_success();
+        status.outputs = vec![
+            OutputStatus {
+                name: "/certs/a".to_string(),
+                status: output.clone(),
+            },
+            OutputStatus {
+                name: "/certs/b".to_string(),
+                status: output,
+            },
+        ];
+
+        status.outputs[1]
+            .status
+            .record_failure("disk full".to_string());
+        assert!(status.is_live());
+
+        status.outputs[1]
+            .status
+            .record_failure("disk full".to_string());
+        assert!(!status.is_live());
+    }
+
+    #[test]
+    fn test_is_live_threshold_needs_prior_success() {
+        let mut status = HealthStatus {
+            liveness_failure_threshold: 2,
+            ..Default::default()
+        };
+        status
+            .x509_svid
+            .record_failure("agent unavailable".to_string());
+        assert!(!status.is_live());
+    }
+
     #[test]
     fn test_is_live_with_bundle_succeeded() {
         let mut status = HealthStatus::default();
         status.x509_svid.write_succeeded = true;
         status.x509_bundle = Some(CredentialStatus {
             write_succeeded: true,
+            consecutive_failures: 0,
             last_success: None,
             last_error: None,
         });
@@ -113,6 +420,7 @@ mod tests {
         status.x509_svid.write_succeeded = true;
         status.x509_bundle = Some(CredentialStatus {
             write_succeeded: false,
+            consecutive_failures: 0,
             last_success: None,
             last_error: None,
         });
@@ -125,6 +433,7 @@ mod tests {
         status.x509_svid.write_succeeded = true;
         status.jwt_bundle = Some(CredentialStatus {
             write_succeeded: true,
+            consecutive_failures: 0,
             last_success: None,
             last_error: None,
         });
@@ -137,6 +446,7 @@ mod tests {
         status.x509_svid.write_succeeded = true;
         status.jwt_bundle = Some(CredentialStatus {
             write_succeeded: false,
+            consecutive_failures: 0,
             last_success: None,
             last_error: None,
         });
@@ -150,11 +460,13 @@ mod tests {
         status.jwt_svids = vec![
             CredentialStatus {
                 write_succeeded: true,
+                consecutive_failures: 0,
                 last_success: None,
                 last_error: None,
             },
             CredentialStatus {
                 write_succeeded: true,
+                consecutive_failures: 0,
                 last_success: None,
                 last_error: None,
             },
@@ -169,11 +481,13 @@ mod tests {
         status.jwt_svids = vec![
             CredentialStatus {
                 write_succeeded: true,
+                consecutive_failures: 0,
                 last_success: None,
                 last_error: None,
             },
             CredentialStatus {
                 write_succeeded: false,
+                consecutive_failures: 0,
                 last_success: None,
                 last_error: None,
             },
@@ -181,6 +495,36 @@ mod tests {
         assert!(!status.is_live());
     }
 
+    #[test]
+    fn test_is_live_with_post_rotation_cmd_failed() {
+        let mut status = HealthStatus::default();
+        status.x509_svid.write_succeeded = true;
+        status.post_rotation_cmd = Some(HookStatus {
+            succeeded: false,
+            last_run: Some(SystemTime::now()),
+            last_error: Some("exit status: 1".to_string()),
+        });
+        assert!(!status.is_live());
+
+        status.post_rotation_cmd.as_mut().unwrap().succeeded = true;
+        assert!(status.is_live());
+    }
+
+    #[test]
+    fn test_is_live_with_renew_signal_unconfirmed() {
+        let mut status = HealthStatus::default();
+        status.x509_svid.write_succeeded = true;
+        status.renew_signal_confirmation = Some(HookStatus {
+            succeeded: false,
+            last_run: Some(SystemTime::now()),
+            last_error: Some("not confirmed".to_string()),
+        });
+        assert!(!status.is_live());
+
+        status.renew_signal_confirmation.as_mut().unwrap().succeeded = true;
+        assert!(status.is_live());
+    }
+
     #[test]
     fn test_is_ready_not_ready() {
         let status = HealthStatus::default();
@@ -200,6 +544,7 @@ mod tests {
         status.x509_svid.last_success = Some(SystemTime::now());
         status.x509_bundle = Some(CredentialStatus {
             write_succeeded: true,
+            consecutive_failures: 0,
             last_success: Some(SystemTime::now()),
             last_error: None,
         });
@@ -212,6 +557,7 @@ mod tests {
         status.x509_svid.last_success = Some(SystemTime::now());
         status.x509_bundle = Some(CredentialStatus {
             write_succeeded: true,
+            consecutive_failures: 0,
             last_success: None,
             last_error: None,
         });
@@ -224,6 +570,7 @@ mod tests {
         status.x509_svid.last_success = Some(SystemTime::now());
         status.jwt_bundle = Some(CredentialStatus {
             write_succeeded: true,
+            consecutive_failures: 0,
             last_success: Some(SystemTime::now()),
             last_error: None,
         });
@@ -237,11 +584,13 @@ mod tests {
         status.jwt_svids = vec![
             CredentialStatus {
                 write_succeeded: true,
+                consecutive_failures: 0,
                 last_success: Some(SystemTime::now()),
                 last_error: None,
             },
             CredentialStatus {
                 write_succeeded: true,
+                consecutive_failures: 0,
                 last_success: Some(SystemTime::now()),
                 last_error: None,
             },
@@ -256,11 +605,13 @@ mod tests {
         status.jwt_svids = vec![
             CredentialStatus {
                 write_succeeded: true,
+                consecutive_failures: 0,
                 last_success: Some(SystemTime::now()),
                 last_error: None,
             },
             CredentialStatus {
                 write_succeeded: true,
+                consecutive_failures: 0,
                 last_success: None,
                 last_error: None,
             },
@@ -268,6 +619,92 @@ mod tests {
         assert!(!status.is_ready());
     }
 
+    fn svid_info_expiring_in(seconds: i64) -> SvidInfo {
+        let now = i64::try_from(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+        )
+        .unwrap();
+        SvidInfo {
+            spiffe_id: "spiffe://example.org/workload".to_string(),
+            serial: "01".to_string(),
+            not_before: now - 60,
+            not_after: now + seconds,
+            observed_svids: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_expires_within() {
+        let mut status = HealthStatus::default();
+        assert!(!status.expires_within(Duration::from_secs(60)));
+
+        status.svid_info = Some(svid_info_expiring_in(30));
+        assert!(status.expires_within(Duration::from_secs(60)));
+        assert!(!status.expires_within(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_failure_reason_names_failed_credential() {
+        let mut status = HealthStatus::default();
+        assert_eq!(
+            status.failure_reason().as_deref(),
+            Some("X.509 SVID not yet written")
+        );
+
+        status.x509_svid.record_success();
+        status.jwt_svids = vec![CredentialStatus::default(), CredentialStatus::default()];
+        status.jwt_svids[0].record_success();
+        status.jwt_svids[1].record_failure("agent unavailable".to_string());
+        assert_eq!(
+            status.failure_reason().as_deref(),
+            Some("JWT SVID 2: agent unavailable")
+        );
+
+        status.jwt_svids[1].record_success();
+        assert_eq!(status.failure_reason(), None);
+    }
+
+    #[test]
+    fn test_transitions_reported_only_on_edges() {
+        let mut status = HealthStatus::default();
+        status.x509_svid.record_success();
+        let mut transitions = HealthTransitions::default();
+        let mut logged = Vec::new();
+
+        // Baseline, then a repeated failure and a repeated recovery
+        logged.extend(transitions.observe(&status));
+        status.x509_svid.record_failure("disk full".to_string());
+        logged.extend(transitions.observe(&status));
+        logged.extend(transitions.observe(&status));
+        status.x509_svid.record_success();
+        logged.extend(transitions.observe(&status));
+        logged.extend(transitions.observe(&status));
+
+        assert_eq!(
+            logged,
+            [
+                HealthTransition {
+                    probe: "live",
+                    healthy: false,
+                    reason: Some("X.509 SVID: disk full".to_string()),
+                },
+                HealthTransition {
+                    probe: "live",
+                    healthy: true,
+                    reason: None,
+                },
+            ]
+        );
+        assert_eq!(
+            logged[0].to_string(),
+            "Health: no longer live (X.509 SVID: disk full)"
+        );
+        assert_eq!(logged[1].to_string(), "Health: now live");
+    }
+
     #[tokio::test]
     async fn test_create_health_status() {
         let status = create_health_status();
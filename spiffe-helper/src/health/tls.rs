@@ -0,0 +1,166 @@
+//! TLS for the health check server, using the X.509 SVID the helper itself manages.
+
+use std::sync::{Arc, RwLock};
+
+use anyhow::{anyhow, Context, Result};
+use rustls::client::danger::HandshakeSignatureValid;
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, UnixTime};
+use rustls::server::danger::{ClientCertVerified, ClientCertVerifier};
+use rustls::server::{ClientHello, ResolvesServerCert, WebPkiClientVerifier};
+use rustls::sign::CertifiedKey;
+use rustls::{DigitallySignedStruct, DistinguishedName, RootCertStore, ServerConfig};
+use spiffe::bundle::x509::X509Bundle;
+use spiffe::svid::x509::X509Svid;
+use x509_parser::extensions::GeneralName;
+
+/// The certificate, key and client verifier built from the most recent SVID and bundle.
+#[derive(Debug)]
+struct TlsMaterial {
+    certified_key: Arc<CertifiedKey>,
+    client_verifier: Arc<dyn ClientCertVerifier>,
+}
+
+/// The identity the health check server presents over TLS. The daemon updates it after every
+/// X.509 write, so new connections use the rotated SVID and trust bundle without a restart.
+#[derive(Debug, Default)]
+pub struct TlsIdentity {
+    material: RwLock<Option<TlsMaterial>>,
+}
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+impl TlsIdentity {
+    /// Serves `svid` from now on, and accepts client certificates issued by `bundle`.
+    pub fn update(&self, svid: &X509Svid, bundle: &X509Bundle) -> Result<()> {
+        let provider = provider();
+        let chain = svid
+            .cert_chain()
+            .iter()
+            .map(|cert| CertificateDer::from(cert.as_ref().to_vec()))
+            .collect();
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(
+            svid.private_key().as_ref().to_vec(),
+        ));
+        let certified_key = CertifiedKey::from_der(chain, key, &provider)
+            .context("Failed to load the SVID for the health check server")?;
+
+        let mut roots = RootCertStore::empty();
+        for authority in bundle.authorities() {
+            roots
+                .add(CertificateDer::from(authority.as_ref().to_vec()))
+                .context("Failed to load the trust bundle for the health check server")?;
+        }
+        let client_verifier =
+            WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                .build()
+                .context("Failed to build the health check client verifier")?;
+
+        *self
+            .material
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner) = Some(TlsMaterial {
+            certified_key: Arc::new(certified_key),
+            client_verifier,
+        });
+        Ok(())
+    }
+
+    fn material<T>(&self, f: impl FnOnce(&TlsMaterial) -> T) -> Option<T> {
+        self.material
+            .read()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .as_ref()
+            .map(f)
+    }
+
+    /// A server config presenting the current SVID, requiring clients to present a
+    /// certificate from the trust bundle when `require_client_cert` is set.
+    pub fn server_config(self: &Arc<Self>, require_client_cert: bool) -> Result<ServerConfig> {
+        let builder = ServerConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()
+            .map_err(|e| anyhow!("Failed to configure TLS for the health check server: {e}"))?;
+        let builder = if require_client_cert {
+            builder.with_client_cert_verifier(Arc::new(BundleClientVerifier(self.clone())))
+        } else {
+            builder.with_no_client_auth()
+        };
+        Ok(builder.with_cert_resolver(Arc::new(SvidCertResolver(self.clone()))))
+    }
+}
+
+/// The SPIFFE ID in the URI SAN of a DER certificate, if it has one.
+#[must_use]
+pub fn spiffe_id_of(der: &[u8]) -> Option<String> {
+    let (_, cert) = x509_parser::parse_x509_certificate(der).ok()?;
+    let san = cert.subject_alternative_name().ok()??;
+    san.value.general_names.iter().find_map(|name| match name {
+        GeneralName::URI(uri) if uri.starts_with("spiffe://") => Some(uri.to_string()),
+        _ => None,
+    })
+}
+
+/// Resolves the server certificate to the current SVID; handshakes fail until one is written.
+#[derive(Debug)]
+struct SvidCertResolver(Arc<TlsIdentity>);
+
+impl ResolvesServerCert for SvidCertResolver {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        self.0.material(|m| m.certified_key.clone())
+    }
+}
+
+/// Verifies client certificates against the current trust bundle.
+#[derive(Debug)]
+struct BundleClientVerifier(Arc<TlsIdentity>);
+
+impl BundleClientVerifier {
+    fn verifier(&self) -> Result<Arc<dyn ClientCertVerifier>, rustls::Error> {
+        self.0
+            .material(|m| m.client_verifier.clone())
+            .ok_or_else(|| rustls::Error::General("no trust bundle written yet".to_string()))
+    }
+}
+
+impl ClientCertVerifier for BundleClientVerifier {
+    fn root_hint_subjects(&self) -> &[DistinguishedName] {
+        // The hints would borrow from a bundle that rotates; clients need none to pick the SVID
+        &[]
+    }
+
+    fn verify_client_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        now: UnixTime,
+    ) -> Result<ClientCertVerified, rustls::Error> {
+        self.verifier()?
+            .verify_client_cert(end_entity, intermediates, now)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.verifier()?.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.verifier()?.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        provider()
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
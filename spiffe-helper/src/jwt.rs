@@ -0,0 +1,296 @@
+use anyhow::{anyhow, Context, Result};
+use spiffe::svid::jwt::JwtSvid as FetchedJwtSvid;
+use spiffe::transport::Endpoint;
+use spiffe::WorkloadApiError;
+use std::collections::BTreeMap;
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::transport::Channel;
+#[cfg(unix)]
+use tonic::transport::Uri;
+
+use crate::cli::{Config, JwtSvid};
+use crate::workload_api::{self, Backoff};
+
+const FETCH_JWT_BUNDLES_PATH: &str = "/SpiffeWorkloadAPI/FetchJWTBundles";
+pub(crate) const WORKLOAD_API_HEADER: &str = "workload.spiffe.io";
+
+/// Raw JWT bundles keyed by trust domain ID, each value a JWKS document.
+pub type JwtBundles = BTreeMap<String, Vec<u8>>;
+
+/// Wire types for the `FetchJWTBundles` RPC.
+///
+/// The spiffe crate only hands out parsed `JwtBundle`s, which cannot be turned back into a JWKS
+/// document, so the bundles are fetched directly to preserve the agent's JWKS verbatim.
+mod pb {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct JwtBundlesRequest {}
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct JwtBundlesResponse {
+        #[prost(map = "string, bytes", tag = "1")]
+        pub bundles: std::collections::HashMap<String, Vec<u8>>,
+    }
+}
+
+/// Returns every audience requested by a `jwt_svids` entry, primary audience first.
+fn audiences(jwt_svid: &JwtSvid) -> Vec<String> {
+    std::iter::once(jwt_svid.jwt_audience.clone())
+        .chain(jwt_svid.jwt_extra_audiences.iter().flatten().cloned())
+        .collect()
+}
+
+/// Fetches the JWT SVID described by a `jwt_svids` entry from the SPIRE agent.
+///
+/// Failed attempts (including `PermissionDenied` while the workload is still being attested)
+/// are retried with exponential backoff following the `jwt_retry_*` settings, jittered unless
+/// `connect_backoff_jitter = false`. The whole retry loop is bounded by
+/// `jwt_fetch_timeout_seconds` so a persistently failing workload cannot block the caller
+/// indefinitely.
+pub async fn fetch_jwt_svid_for_config(
+    config: &Config,
+    jwt_svid: &JwtSvid,
+) -> Result<FetchedJwtSvid> {
+    let agent_address = config.agent_address()?;
+    let audiences = audiences(jwt_svid);
+    let timeout = config.jwt_fetch_timeout();
+
+    let attempts = config.jwt_retry_max_attempts();
+    let fetch = fetch_with_retry(agent_address, &audiences, attempts, retry_backoff(config));
+    tokio::time::timeout(timeout, fetch).await.map_err(|_| {
+        anyhow!(
+            "Timed out after {}s fetching JWT SVID for audience {}",
+            timeout.as_secs(),
+            jwt_svid.jwt_audience
+        )
+    })?
+}
+
+/// Retry delays for JWT SVID fetches under `config`.
+pub(crate) fn retry_backoff(config: &Config) -> Backoff {
+    Backoff::with_limits(
+        config.jwt_retry_base(),
+        config.jwt_retry_max_backoff(),
+        config.connect_backoff_jitter(),
+    )
+}
+
+/// Fetches a JWT SVID for `audiences`, trying up to `max_attempts` times in total with delays
+/// from `backoff` between attempts.
+pub(crate) async fn fetch_with_retry(
+    agent_address: &str,
+    audiences: &[String],
+    max_attempts: u32,
+    mut backoff: Backoff,
+) -> Result<FetchedJwtSvid> {
+    let mut attempt = 1;
+
+    loop {
+        let result = match workload_api::connect_client(agent_address).await {
+            Ok(client) => client.fetch_jwt_svid(audiences, None).await,
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(svid) => return Ok(svid),
+            Err(e) if attempt >= max_attempts => {
+                return Err(anyhow!(
+                    "Failed to fetch JWT SVID for audience {} after {attempt} attempts: {e}",
+                    audiences[0]
+                ));
+            }
+            Err(e) => {
+                let delay = backoff.delay(attempt);
+                if matches!(e, WorkloadApiError::PermissionDenied(_)) {
+                    eprintln!(
+                        "JWT SVID fetch for audience {} denied (workload may not be attested yet), retrying in {:.1}s",
+                        audiences[0],
+                        delay.as_secs_f64()
+                    );
+                } else {
+                    eprintln!(
+                        "Failed to fetch JWT SVID for audience {}: {e}, retrying in {:.1}s",
+                        audiences[0],
+                        delay.as_secs_f64()
+                    );
+                }
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Fetches the current JWT bundles from the SPIRE agent.
+///
+/// Only the first message of the `FetchJWTBundles` stream is read. The call is bounded by
+/// `jwt_fetch_timeout_seconds`.
+pub async fn fetch_jwt_bundles_for_config(config: &Config) -> Result<JwtBundles> {
+    let agent_address = config.agent_address()?;
+    let timeout = config.jwt_fetch_timeout();
+
+    tokio::time::timeout(timeout, fetch_jwt_bundles(agent_address))
+        .await
+        .map_err(|_| {
+            anyhow!(
+                "Timed out after {}s fetching JWT bundles",
+                timeout.as_secs()
+            )
+        })?
+}
+
+async fn fetch_jwt_bundles(agent_address: &str) -> Result<JwtBundles> {
+    let channel = connect(agent_address).await?;
+    let mut grpc = tonic::client::Grpc::new(channel);
+    grpc.ready()
+        .await
+        .map_err(|e| anyhow!("SPIRE agent is not ready: {e}"))?;
+
+    let mut request = tonic::Request::new(pb::JwtBundlesRequest {});
+    request
+        .metadata_mut()
+        .insert(WORKLOAD_API_HEADER, "true".parse()?);
+
+    let mut stream = grpc
+        .server_streaming::<_, pb::JwtBundlesResponse, _>(
+            request,
+            PathAndQuery::from_static(FETCH_JWT_BUNDLES_PATH),
+            tonic::codec::ProstCodec::default(),
+        )
+        .await
+        .map_err(|e| anyhow!("Failed to fetch JWT bundles: {}", e.message()))?
+        .into_inner();
+
+    let response = stream
+        .message()
+        .await
+        .map_err(|e| anyhow!("Failed to fetch JWT bundles: {}", e.message()))?
+        .ok_or_else(|| anyhow!("SPIRE agent closed the JWT bundle stream without a response"))?;
+
+    Ok(response.bundles.into_iter().collect())
+}
+
+pub(crate) async fn connect(agent_address: &str) -> Result<Channel> {
+    workload_api::validate_agent_address(agent_address)?;
+
+    let channel = match Endpoint::parse(&workload_api::normalize_endpoint(agent_address)) {
+        Ok(Endpoint::Tcp { host, port }) => {
+            tonic::transport::Endpoint::from_shared(format!("http://{host}:{port}"))?
+                .connect()
+                .await
+        }
+        // Unix socket paths and abstract sockets
+        #[cfg(unix)]
+        _ => {
+            let address = agent_address.to_string();
+            // The URI is ignored by the connector; tonic only requires a syntactically valid one.
+            tonic::transport::Endpoint::from_static("http://localhost")
+                .connect_with_connector(tower::service_fn(move |_: Uri| {
+                    let address = address.clone();
+                    async move { workload_api::connect_unix_stream(&address).await }
+                }))
+                .await
+        }
+        #[cfg(not(unix))]
+        _ => anyhow::bail!(
+            "Unix socket agent_address {agent_address} is not supported on this platform; use tcp://host:port"
+        ),
+    };
+
+    channel.with_context(|| format!("Failed to connect to SPIRE agent at {agent_address}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_audiences_includes_extra_audiences() {
+        let jwt_svid = JwtSvid {
+            jwt_audience: "primary".to_string(),
+            jwt_extra_audiences: Some(vec!["extra1".to_string(), "extra2".to_string()]),
+            jwt_svid_file_name: "svid.jwt".to_string(),
+        };
+
+        assert_eq!(audiences(&jwt_svid), vec!["primary", "extra1", "extra2"]);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_jwt_svid_times_out_on_missing_socket() {
+        let temp_dir = TempDir::new().unwrap();
+        let socket_path = temp_dir.path().join("missing.sock");
+        let config = Config {
+            agent_address: Some(format!("unix://{}", socket_path.display())),
+            jwt_fetch_timeout_seconds: Some(1),
+            ..Default::default()
+        };
+        let jwt_svid = JwtSvid {
+            jwt_audience: "my-audience".to_string(),
+            jwt_extra_audiences: None,
+            jwt_svid_file_name: "svid.jwt".to_string(),
+        };
+
+        let start = Instant::now();
+        let result = fetch_jwt_svid_for_config(&config, &jwt_svid).await;
+
+        assert!(start.elapsed() < Duration::from_secs(3));
+        let error_msg = result.unwrap_err().to_string();
+        assert!(error_msg.contains("Timed out"));
+        assert!(error_msg.contains("my-audience"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_jwt_svid_stops_after_configured_attempts() {
+        let temp_dir = TempDir::new().unwrap();
+        let socket_path = temp_dir.path().join("missing.sock");
+        let config = Config {
+            agent_address: Some(format!("unix://{}", socket_path.display())),
+            jwt_retry_base_seconds: Some(0),
+            jwt_retry_max_attempts: Some(3),
+            ..Default::default()
+        };
+        let jwt_svid = JwtSvid {
+            jwt_audience: "my-audience".to_string(),
+            jwt_extra_audiences: None,
+            jwt_svid_file_name: "svid.jwt".to_string(),
+        };
+
+        let start = Instant::now();
+        let result = fetch_jwt_svid_for_config(&config, &jwt_svid).await;
+
+        assert!(start.elapsed() < Duration::from_secs(5));
+        let error_msg = result.unwrap_err().to_string();
+        assert!(error_msg.contains("after 3 attempts"), "{error_msg}");
+    }
+
+    #[test]
+    fn test_retry_backoff_respects_configured_cap() {
+        let config = Config {
+            jwt_retry_base_seconds: Some(2),
+            jwt_retry_max_backoff_seconds: Some(5),
+            connect_backoff_jitter: Some(false),
+            ..Default::default()
+        };
+        let mut backoff = retry_backoff(&config);
+
+        let delays: Vec<u64> = (1..=5).map(|n| backoff.delay(n).as_secs()).collect();
+        assert_eq!(delays, [2, 4, 5, 5, 5]);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_jwt_bundles_fails_on_missing_socket() {
+        let temp_dir = TempDir::new().unwrap();
+        let socket_path = temp_dir.path().join("missing.sock");
+        let config = Config {
+            agent_address: Some(format!("unix://{}", socket_path.display())),
+            jwt_fetch_timeout_seconds: Some(1),
+            ..Default::default()
+        };
+
+        let result = fetch_jwt_bundles_for_config(&config).await;
+
+        let error_msg = result.unwrap_err().to_string();
+        assert!(error_msg.contains("Failed to connect to SPIRE agent"));
+    }
+}
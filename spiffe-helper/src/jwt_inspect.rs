@@ -0,0 +1,239 @@
+//! Decodes a JWT SVID written by the helper so operators can check its claims and remaining
+//! lifetime, with optional signature verification against a JWKS bundle.
+
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use spiffe::bundle::jwt::{JwtBundle, JwtBundleSet};
+use spiffe::svid::jwt::JwtSvid;
+use spiffe::TrustDomain;
+
+/// The claims of a JWT SVID that `validate-jwt` reports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JwtClaims {
+    /// The `sub` claim
+    pub spiffe_id: String,
+    /// The `aud` claim, which may be a single string in the token
+    pub audiences: Vec<String>,
+    /// The `iat` claim, in seconds since the Unix epoch
+    pub issued_at: Option<i64>,
+    /// The `exp` claim, in seconds since the Unix epoch
+    pub expiry: i64,
+}
+
+/// Decodes the header and payload of `token` without verifying its signature.
+pub fn decode_claims(token: &str) -> Result<JwtClaims> {
+    let parts: Vec<&str> = token.split('.').collect();
+    let [header, payload, _signature] = parts[..] else {
+        anyhow::bail!(
+            "Invalid JWT: expected 3 dot-separated parts, found {}",
+            parts.len()
+        );
+    };
+
+    let header = decode_json_part(header).context("Invalid JWT header")?;
+    if header.get("alg").and_then(|v| v.as_str()).is_none() {
+        anyhow::bail!("Invalid JWT header: missing alg");
+    }
+
+    let payload = decode_json_part(payload).context("Invalid JWT payload")?;
+    let spiffe_id = payload
+        .get("sub")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("Invalid JWT payload: missing sub"))?
+        .to_string();
+    let audiences = match payload.get("aud") {
+        Some(serde_json::Value::String(aud)) => vec![aud.clone()],
+        Some(serde_json::Value::Array(auds)) => auds
+            .iter()
+            .map(|v| {
+                v.as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| anyhow!("Invalid JWT payload: aud must contain strings"))
+            })
+            .collect::<Result<_>>()?,
+        _ => anyhow::bail!("Invalid JWT payload: missing aud"),
+    };
+    let expiry = payload
+        .get("exp")
+        .and_then(serde_json::Value::as_i64)
+        .ok_or_else(|| anyhow!("Invalid JWT payload: missing exp"))?;
+    let issued_at = payload.get("iat").and_then(serde_json::Value::as_i64);
+
+    Ok(JwtClaims {
+        spiffe_id,
+        audiences,
+        issued_at,
+        expiry,
+    })
+}
+
+fn decode_json_part(part: &str) -> Result<serde_json::Value> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(part.trim_end_matches('='))
+        .context("not base64url")?;
+    serde_json::from_slice(&bytes).context("not JSON")
+}
+
+/// Verifies the signature and expiry of `token` against `jwks`, which is either a single JWKS
+/// document (`{"keys": [...]}`) for the token's trust domain or the helper's JWT bundle file
+/// mapping each trust domain to its JWKS.
+pub fn verify_signature(token: &str, jwks: &[u8]) -> Result<()> {
+    let svid = JwtSvid::parse_insecure(token).context("Invalid JWT SVID")?;
+    let document: serde_json::Value =
+        serde_json::from_slice(jwks).context("JWKS bundle is not valid JSON")?;
+
+    let mut bundles = JwtBundleSet::new();
+    if document.get("keys").is_some() {
+        let trust_domain = svid.spiffe_id().trust_domain().clone();
+        bundles.add_bundle(
+            JwtBundle::from_jwt_authorities(trust_domain, jwks).context("Invalid JWKS bundle")?,
+        );
+    } else {
+        let domains = document
+            .as_object()
+            .ok_or_else(|| anyhow!("JWKS bundle must be a JSON object"))?;
+        for (trust_domain, jwks) in domains {
+            let trust_domain = TrustDomain::new(trust_domain)
+                .with_context(|| format!("Invalid trust domain {trust_domain:?} in JWKS bundle"))?;
+            let bundle =
+                JwtBundle::from_jwt_authorities(trust_domain.clone(), jwks.to_string().as_bytes())
+                    .with_context(|| format!("Invalid JWKS for {trust_domain}"))?;
+            bundles.add_bundle(bundle);
+        }
+    }
+
+    // The audience is reported rather than checked, so accept the token's own
+    JwtSvid::parse_and_validate(token, &bundles, svid.audience())
+        .context("Signature verification failed")?;
+    Ok(())
+}
+
+/// Formats seconds since the Unix epoch the way certificate expiry is logged.
+pub(crate) fn format_timestamp(timestamp: i64) -> String {
+    x509_parser::time::ASN1Time::from_timestamp(timestamp)
+        .ok()
+        .and_then(|t| t.to_rfc2822().ok())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Describes how long until `expiry`, or how long ago it passed, relative to `now`.
+pub(crate) fn describe_time_to_expiry(expiry: i64, now: i64) -> String {
+    if expiry > now {
+        format!("{}s", expiry - now)
+    } else {
+        format!("expired {}s ago", now - expiry)
+    }
+}
+
+/// Runs the `validate-jwt` operation: prints the claims of the JWT SVID in `path` and, when
+/// `jwks_path` is given, verifies its signature.
+pub fn run(path: &Path, jwks_path: Option<&Path>) -> Result<()> {
+    let token = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read JWT file {}", path.display()))?;
+    let token = token.trim();
+    let claims = decode_claims(token)?;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+        .try_into()
+        .unwrap_or(i64::MAX);
+
+    println!("SPIFFE ID: {}", claims.spiffe_id);
+    println!("Audiences: {}", claims.audiences.join(", "));
+    match claims.issued_at {
+        Some(iat) => println!("Issued at: {} ({iat})", format_timestamp(iat)),
+        None => println!("Issued at: not set"),
+    }
+    println!(
+        "Expires at: {} ({})",
+        format_timestamp(claims.expiry),
+        claims.expiry
+    );
+    println!(
+        "Time to expiry: {}",
+        describe_time_to_expiry(claims.expiry, now)
+    );
+
+    if let Some(jwks_path) = jwks_path {
+        let jwks = std::fs::read(jwks_path)
+            .with_context(|| format!("Failed to read JWKS bundle {}", jwks_path.display()))?;
+        verify_signature(token, &jwks)?;
+        println!("Signature: valid ({})", jwks_path.display());
+    } else {
+        println!("Signature: not verified");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(value: &serde_json::Value) -> String {
+        URL_SAFE_NO_PAD.encode(value.to_string())
+    }
+
+    fn token(payload: &serde_json::Value) -> String {
+        let header = serde_json::json!({"alg": "ES256", "kid": "key-1", "typ": "JWT"});
+        format!("{}.{}.c2lnbmF0dXJl", encode(&header), encode(payload))
+    }
+
+    #[test]
+    fn test_decode_claims() {
+        let token = token(&serde_json::json!({
+            "sub": "spiffe://example.org/workload",
+            "aud": ["service-a", "service-b"],
+            "iat": 1_700_000_000,
+            "exp": 1_700_003_600,
+        }));
+
+        let claims = decode_claims(&token).unwrap();
+        assert_eq!(
+            claims,
+            JwtClaims {
+                spiffe_id: "spiffe://example.org/workload".to_string(),
+                audiences: vec!["service-a".to_string(), "service-b".to_string()],
+                issued_at: Some(1_700_000_000),
+                expiry: 1_700_003_600,
+            }
+        );
+    }
+
+    #[test]
+    fn test_decode_claims_accepts_single_audience_without_iat() {
+        let token = token(&serde_json::json!({
+            "sub": "spiffe://example.org/workload",
+            "aud": "service-a",
+            "exp": 1_700_003_600,
+        }));
+
+        let claims = decode_claims(&token).unwrap();
+        assert_eq!(claims.audiences, ["service-a".to_string()]);
+        assert_eq!(claims.issued_at, None);
+    }
+
+    #[test]
+    fn test_decode_claims_rejects_malformed_tokens() {
+        let error = decode_claims("not-a-jwt").unwrap_err();
+        assert!(error.to_string().contains("expected 3 dot-separated parts"));
+
+        let error = decode_claims("e30.!!!.sig").unwrap_err();
+        assert!(error.to_string().contains("Invalid JWT header"));
+
+        let missing_exp = token(&serde_json::json!({"sub": "spiffe://example.org/w", "aud": "a"}));
+        let error = decode_claims(&missing_exp).unwrap_err();
+        assert!(error.to_string().contains("missing exp"));
+    }
+
+    #[test]
+    fn test_describe_time_to_expiry() {
+        assert_eq!(describe_time_to_expiry(1_000, 400), "600s");
+        assert_eq!(describe_time_to_expiry(1_000, 1_030), "expired 30s ago");
+    }
+}
@@ -1,8 +1,19 @@
+#[cfg(unix)]
+pub mod background;
+pub mod bundle_only;
+pub mod chain_inspect;
+pub mod check_agent;
 pub mod cli;
+pub mod crl;
 pub mod daemon;
+pub mod fetch;
 pub mod file_system;
 pub mod health;
+pub mod jwt;
+pub mod jwt_inspect;
 pub mod oneshot;
+pub mod output;
 pub mod process;
+pub mod schema;
 pub mod signal;
 pub mod workload_api;
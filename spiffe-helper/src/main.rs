@@ -1,31 +1,243 @@
-use anyhow::{anyhow, Result};
+use anyhow::{Context, Result};
 use clap::Parser;
+use spiffe::X509Source;
+#[cfg(unix)]
+use std::path::Path;
 
-use spiffe_helper::{cli, daemon, oneshot, workload_api};
+#[cfg(unix)]
+use spiffe_helper::background;
+#[cfg(feature = "kube")]
+use spiffe_helper::file_system::KubeSecretSink;
+use spiffe_helper::file_system::{CredentialSink, DryRunSink, LocalFileSystem, MultiSink};
+use spiffe_helper::health::SharedHealthStatus;
+use spiffe_helper::oneshot::OneshotError;
+use spiffe_helper::{
+    bundle_only, chain_inspect, check_agent, cli, daemon, info, jwt_inspect, oneshot, output,
+    schema, workload_api,
+};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-#[tokio::main]
-async fn main() -> Result<()> {
+/// Prints the version number, followed with `verbose` by the build details recorded by the
+/// build script.
+fn print_version(verbose: bool) {
+    println!("{VERSION}");
+    if verbose {
+        println!("git commit: {}", env!("SPIFFE_HELPER_GIT_HASH"));
+        println!("built: {}", env!("SPIFFE_HELPER_BUILD_TIMESTAMP"));
+        println!("target: {}", env!("SPIFFE_HELPER_TARGET"));
+        println!("spiffe crate: {}", env!("SPIFFE_HELPER_SPIFFE_VERSION"));
+    }
+}
+
+fn main() -> Result<()> {
     let args = cli::Args::parse();
 
     if args.version {
-        println!("{VERSION}");
+        print_version(false);
         return Ok(());
     }
 
-    let config = args.get_operation_config()?;
-    let x509_source = workload_api::create_x509_source(
-        config
-            .agent_address
-            .as_ref()
-            .ok_or_else(|| anyhow!("missing agent address"))?,
-    )
-    .await?;
+    match &args.command {
+        Some(cli::Command::Version { verbose }) => {
+            print_version(*verbose);
+            return Ok(());
+        }
+        Some(cli::Command::ValidateJwt { file, jwks }) => {
+            return jwt_inspect::run(file, jwks.as_deref());
+        }
+        Some(cli::Command::DumpChain {
+            cert_dir,
+            svid_file_name,
+            svid_bundle_file_name,
+        }) => {
+            return chain_inspect::run(cert_dir, &[svid_file_name, svid_bundle_file_name]);
+        }
+        Some(cli::Command::Schema) => {
+            return schema::run();
+        }
+        Some(cli::Command::CheckAgent) | None => {}
+    }
+
+    let config = args
+        .get_operation_config()
+        .unwrap_or_else(|e| exit_with(&OneshotError::Config(e)));
+    output::set_quiet(config.is_quiet());
+
+    if args.config_check {
+        info!("Configuration OK: {}", args.config_name());
+        return Ok(());
+    }
+
+    if let Some(cli::Command::CheckAgent) = args.command {
+        return tokio::runtime::Runtime::new()
+            .context("Failed to start the Tokio runtime")?
+            .block_on(check_agent::run(&config))
+            .or_else(|e| exit_with(&OneshotError::Connect(e)));
+    }
+
+    // Forking is only safe while the process is single-threaded, so detach before the runtime
+    // starts its workers
+    #[cfg(unix)]
+    let background_pid_file = config
+        .is_background()
+        .then(|| config.background_pid_file.clone())
+        .flatten();
+    #[cfg(unix)]
+    if config.is_background() {
+        background::daemonize(
+            config.background_pid_file.as_deref().map(Path::new),
+            config.background_log_file.as_deref().map(Path::new),
+        )
+        .unwrap_or_else(|e| exit_with(&OneshotError::Config(e)));
+    }
+
+    let result = tokio::runtime::Runtime::new()
+        .context("Failed to start the Tokio runtime")?
+        .block_on(run(config, args.stdout_key));
+
+    #[cfg(unix)]
+    if let Some(pid_file) = background_pid_file {
+        let _ = std::fs::remove_file(pid_file);
+    }
+    result
+}
+
+/// Runs the mode selected by `config` once the process is in its final foreground or
+/// background state.
+async fn run(config: cli::Config, stdout_key: bool) -> Result<()> {
+    // No SVID is fetched, so there is no X509Source to wait for
+    if config.is_bundle_only() {
+        return bundle_only::run(config).await;
+    }
+
+    if config.is_stdout() {
+        let x509_source = connect_to_agent(&config, None).await;
+        return oneshot::run_stdout(x509_source, config, stdout_key)
+            .await
+            .or_else(|e| exit_with(&e));
+    }
+
+    // The daemon answers health checks while it waits for the agent to issue an SVID
+    let startup_health = if config.is_daemon_mode() && !config.is_watch_once() {
+        Some(daemon::StartupHealth::start(&config).await?)
+    } else {
+        None
+    };
+    let health_status = startup_health.as_ref().map(|health| &health.status);
+
+    // Templated file names are filled in from the SVID, so the agent is reached first
+    let (config, x509_source) = if config.has_file_name_templates() {
+        let x509_source = connect_to_agent(&config, health_status).await;
+        let config = workload_api::config_for_source(config, &x509_source)
+            .unwrap_or_else(|e| exit_with(&OneshotError::Connect(e)));
+        (config, Some(x509_source))
+    } else {
+        (config, None)
+    };
+
+    if config.is_dry_run() {
+        let sink = DryRunSink::new(&config);
+        return run_with_sink(config, sink, x509_source, startup_health).await;
+    }
+
+    #[cfg(feature = "kube")]
+    if config.kube_secret_name.is_some() {
+        let sink =
+            KubeSecretSink::new(&config).unwrap_or_else(|e| exit_with(&OneshotError::Config(e)));
+        return run_with_sink(config, sink, x509_source, startup_health).await;
+    }
+
+    if config.outputs.is_some() {
+        let outputs =
+            MultiSink::local(&config).unwrap_or_else(|e| exit_with(&OneshotError::Write(e)));
+        return run_with_sink(config, outputs, x509_source, startup_health).await;
+    }
+
+    // Surface an unwritable cert_dir before waiting on the agent
+    let local_fs = LocalFileSystem::new(&config)
+        .unwrap_or_else(|e| exit_with(&OneshotError::Config(e)))
+        .ensure()
+        .unwrap_or_else(|e| exit_with(&OneshotError::Write(e)));
+    run_with_sink(config, local_fs, x509_source, startup_health).await
+}
+
+/// Runs the configured mode, writing credentials to `sink`, and connects to the agent unless
+/// `x509_source` is already connected. The daemon carries on with `startup_health`.
+async fn run_with_sink<S: CredentialSink + 'static>(
+    config: cli::Config,
+    sink: S,
+    x509_source: Option<X509Source>,
+    startup_health: Option<daemon::StartupHealth>,
+) -> Result<()> {
+    let x509_source = match x509_source {
+        Some(x509_source) => x509_source,
+        None => {
+            let health_status = startup_health.as_ref().map(|health| &health.status);
+            connect_to_agent(&config, health_status).await
+        }
+    };
+
+    if config.is_watch_once() {
+        return oneshot::run_watch_once_with_sink(x509_source, config, sink)
+            .await
+            .or_else(|e| exit_with(&e));
+    }
 
     if !config.is_daemon_mode() {
-        return oneshot::run(x509_source, config).await;
+        return oneshot::run_with_sink(x509_source, config, sink)
+            .await
+            .or_else(|e| exit_with(&e));
+    }
+
+    daemon::run_with_sink_and_health(x509_source, config, sink, startup_health).await
+}
+
+/// Creates the X.509 source, exiting with the connect error code if the agent is unreachable.
+/// Attempts that find no SVID are recorded in `health_status`, if given.
+async fn connect_to_agent(
+    config: &cli::Config,
+    health_status: Option<&SharedHealthStatus>,
+) -> X509Source {
+    let agent_address = config
+        .agent_address()
+        .unwrap_or_else(|e| exit_with(&OneshotError::Config(e)));
+    workload_api::check_agent_socket(config)
+        .unwrap_or_else(|e| exit_with(&OneshotError::Config(e)));
+    workload_api::wait_for_x509_svid(
+        agent_address,
+        config.no_svid_max_attempts(),
+        config.connect_backoff_jitter(),
+        config.connect_timeout(),
+        health_status,
+    )
+    .await
+    .unwrap_or_else(|e| exit_with(&OneshotError::Connect(e)));
+    // Only one-shot mode retries the connection; every mode bounds the wait for the first
+    // response by connect_timeout_seconds
+    let max_attempts = config
+        .oneshot_max_attempts()
+        .filter(|_| !config.is_daemon_mode() && !config.is_watch_once());
+    match max_attempts {
+        Some(max_attempts) => {
+            workload_api::create_x509_source_with_retry(
+                agent_address,
+                max_attempts,
+                config.connect_backoff_jitter(),
+                config.connect_timeout(),
+            )
+            .await
+        }
+        None => {
+            workload_api::create_x509_source_with_timeout(agent_address, config.connect_timeout())
+                .await
+        }
     }
+    .unwrap_or_else(|e| exit_with(&OneshotError::Connect(e)))
+}
 
-    daemon::run(x509_source, config).await
+/// Reports `error` and exits with the code for its failure cause.
+fn exit_with(error: &OneshotError) -> ! {
+    eprintln!("Error: {error}");
+    std::process::exit(error.exit_code())
 }
@@ -1,20 +1,228 @@
-use crate::{cli::Config, file_system::LocalFileSystem, workload_api};
-use anyhow::Result;
+use crate::file_system::{
+    certificates_pem, key_pem, pem_line_ending, CredentialSink, LocalFileSystem, MultiSink,
+};
+use crate::{cli::Config, crl, info, jwt, workload_api};
 use spiffe::X509Source;
+use std::fmt;
+use std::io::Write;
+
+/// Failure causes of one-shot mode, each mapped to its own process exit code so scripts can
+/// tell them apart.
+#[derive(Debug)]
+pub enum OneshotError {
+    /// The configuration is missing or invalid (exit code 2)
+    Config(anyhow::Error),
+    /// The SPIRE agent could not be reached or did not provide credentials (exit code 3)
+    Connect(anyhow::Error),
+    /// The credentials could not be written to disk (exit code 4)
+    Write(anyhow::Error),
+}
+
+impl OneshotError {
+    #[must_use]
+    pub fn exit_code(&self) -> i32 {
+        i32::from(self)
+    }
+}
+
+impl From<&OneshotError> for i32 {
+    fn from(error: &OneshotError) -> Self {
+        match error {
+            OneshotError::Config(_) => 2,
+            OneshotError::Connect(_) => 3,
+            OneshotError::Write(_) => 4,
+        }
+    }
+}
+
+impl fmt::Display for OneshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OneshotError::Config(e) | OneshotError::Connect(e) | OneshotError::Write(e) => {
+                write!(f, "{e:#}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for OneshotError {}
 
 /// Runs the one-shot mode: fetches certificate and exits.
-pub async fn run(source: X509Source, config: Config) -> Result<()> {
-    println!("Running spiffe-helper in one-shot mode...");
-    let cert_dir = config
-        .cert_dir
-        .as_ref()
-        .ok_or_else(|| anyhow::anyhow!("cert_dir must be configured"))?;
-
-    let local_fs = LocalFileSystem::new(&config)?.ensure()?;
-    workload_api::fetch_and_write_x509_svid(&source, &local_fs)?;
-
-    println!("Successfully fetched and wrote X.509 certificate to {cert_dir}");
-    println!("One-shot mode complete");
+pub async fn run(source: X509Source, config: Config) -> Result<(), OneshotError> {
+    let config = workload_api::config_for_source(config, &source).map_err(OneshotError::Connect)?;
+    if config.outputs.is_some() {
+        let outputs = MultiSink::local(&config).map_err(OneshotError::Write)?;
+        return run_with_sink(source, config, outputs).await;
+    }
+    let local_fs = local_file_system(&config)?;
+    run_with_sink(source, config, local_fs).await
+}
+
+/// Runs the one-shot mode, writing credentials to `sink` instead of the local filesystem.
+pub async fn run_with_sink<S: CredentialSink>(
+    source: X509Source,
+    config: Config,
+    sink: S,
+) -> Result<(), OneshotError> {
+    info!("Running spiffe-helper in one-shot mode...");
+    write_credentials(&source, &config, &sink).await?;
+    info!("One-shot mode complete");
+    Ok(())
+}
+
+/// Runs the watch-once mode: waits until the agent serves a certificate that remains valid for
+/// at least `watch_once_min_validity_seconds`, writes it, and exits.
+///
+/// A certificate too close to expiry is not written; the SVID is fetched again whenever the
+/// agent pushes an update, or otherwise once half of its remaining validity has passed, within
+/// `min_refresh_interval_seconds` and `max_refresh_interval_seconds`.
+pub async fn run_watch_once(source: X509Source, config: Config) -> Result<(), OneshotError> {
+    let config = workload_api::config_for_source(config, &source).map_err(OneshotError::Connect)?;
+    if config.outputs.is_some() {
+        let outputs = MultiSink::local(&config).map_err(OneshotError::Write)?;
+        return run_watch_once_with_sink(source, config, outputs).await;
+    }
+    let local_fs = local_file_system(&config)?;
+    run_watch_once_with_sink(source, config, local_fs).await
+}
+
+/// Runs the watch-once mode, writing credentials to `sink` instead of the local filesystem.
+pub async fn run_watch_once_with_sink<S: CredentialSink>(
+    source: X509Source,
+    config: Config,
+    sink: S,
+) -> Result<(), OneshotError> {
+    info!("Running spiffe-helper in watch-once mode...");
+    let min_validity = config.watch_once_min_validity();
+    let mut update_channel = source.updated();
+
+    loop {
+        let svid = source
+            .svid()
+            .map_err(|e| OneshotError::Connect(anyhow::anyhow!("Failed to get SVID: {e}")))?;
+        let remaining =
+            workload_api::svid_remaining_validity(&svid).map_err(OneshotError::Connect)?;
+        if remaining >= min_validity {
+            info!(
+                "Certificate valid for {}s, meets the required {}s",
+                remaining.as_secs(),
+                min_validity.as_secs()
+            );
+            break;
+        }
+
+        let recheck = workload_api::refresh_interval_for(
+            &svid,
+            config.min_refresh_interval(),
+            config.max_refresh_interval(),
+        );
+        info!(
+            "Certificate valid for only {}s, waiting for one valid for at least {}s (rechecking in {}s)",
+            remaining.as_secs(),
+            min_validity.as_secs(),
+            recheck.as_secs()
+        );
+        tokio::select! {
+            res = update_channel.changed() => {
+                res.map_err(|e| {
+                    OneshotError::Connect(anyhow::anyhow!("X.509 update channel closed: {e}"))
+                })?;
+            }
+            () = tokio::time::sleep(recheck) => {}
+        }
+    }
+
+    write_credentials(&source, &config, &sink).await?;
+    info!("Watch-once mode complete");
+    Ok(())
+}
+
+/// Runs the one-shot mode with `--stdout`: prints the certificate chain PEM, followed by the
+/// private key PEM when `include_key` is set, and writes no files. Nothing else is printed to
+/// stdout so the output can be piped into other tools.
+pub async fn run_stdout(
+    source: X509Source,
+    config: Config,
+    include_key: bool,
+) -> Result<(), OneshotError> {
+    let (svid, _bundle) = workload_api::fetch_x509_svid_for_config(&source, &config)
+        .map_err(OneshotError::Connect)?;
+
+    let line_ending = pem_line_ending(&config);
+    let mut output = certificates_pem(svid.cert_chain(), line_ending);
+    if include_key {
+        output.push_str(&key_pem(svid.private_key().as_ref(), line_ending));
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    stdout
+        .write_all(output.as_bytes())
+        .and_then(|()| stdout.flush())
+        .map_err(|e| OneshotError::Write(anyhow::anyhow!("Failed to write to stdout: {e}")))
+}
+
+fn local_file_system(config: &Config) -> Result<LocalFileSystem, OneshotError> {
+    LocalFileSystem::new(config)
+        .map_err(OneshotError::Config)?
+        .ensure()
+        .map_err(OneshotError::Write)
+}
+
+async fn write_credentials<S: CredentialSink>(
+    source: &X509Source,
+    config: &Config,
+    sink: &S,
+) -> Result<(), OneshotError> {
+    // Only used to describe where the credentials went; sinks other than the local filesystem
+    // need no cert_dir
+    let cert_dir = config.cert_dir.as_deref().unwrap_or("credential sink");
+
+    let (svid, bundle) =
+        workload_api::fetch_x509_svid_for_config(source, config).map_err(OneshotError::Connect)?;
+    workload_api::write_x509_svid_on_update(&svid, &bundle, sink).map_err(OneshotError::Write)?;
+
+    info!("Successfully fetched and wrote X.509 certificate to {cert_dir}");
+
+    if config.write_all_svids() {
+        let names = workload_api::fetch_and_write_all_x509_svids(config)
+            .await
+            .map_err(OneshotError::Write)?;
+        info!(
+            "Successfully fetched and wrote {} X.509 SVIDs to {cert_dir}: {}",
+            names.len(),
+            names.join(", ")
+        );
+    }
+
+    if let Some(crl_file_name) = &config.crl_file_name {
+        let crls = crl::fetch_crls_for_config(config)
+            .await
+            .map_err(OneshotError::Connect)?;
+        if crl::write_crls(config, sink, &crls).map_err(OneshotError::Write)? {
+            info!("Successfully fetched and wrote CRLs to {cert_dir}/{crl_file_name}");
+        }
+    }
+
+    for jwt_svid in config.jwt_svids.iter().flatten() {
+        let fetched = jwt::fetch_jwt_svid_for_config(config, jwt_svid)
+            .await
+            .map_err(OneshotError::Connect)?;
+        sink.write_jwt(&jwt_svid.jwt_svid_file_name, fetched.token())
+            .map_err(OneshotError::Write)?;
+        info!(
+            "Successfully fetched and wrote JWT SVID to {cert_dir}/{}",
+            jwt_svid.jwt_svid_file_name
+        );
+    }
+
+    if let Some(jwt_bundle_file_name) = &config.jwt_bundle_file_name {
+        let bundles = jwt::fetch_jwt_bundles_for_config(config)
+            .await
+            .map_err(OneshotError::Connect)?;
+        sink.write_jwt_bundle(&bundles)
+            .map_err(OneshotError::Write)?;
+        info!("Successfully fetched and wrote JWT bundle to {cert_dir}/{jwt_bundle_file_name}");
+    }
     Ok(())
 }
 
@@ -40,7 +248,30 @@ mod tests {
 
         let result = run(source, config).await;
         assert!(result.is_err());
-        let error_msg = result.unwrap_err().to_string();
-        assert!(error_msg.contains("cert_dir must be configured"));
+        let error = result.unwrap_err();
+        assert_eq!(error.exit_code(), 2);
+        assert!(error.to_string().contains("cert_dir must be configured"));
+    }
+
+    #[test]
+    fn test_exit_codes_distinguish_failure_causes() {
+        let config = OneshotError::Config(anyhow::anyhow!("bad config"));
+        let connect = OneshotError::Connect(anyhow::anyhow!("no agent"));
+        let write = OneshotError::Write(anyhow::anyhow!("read-only"));
+
+        assert_eq!(config.exit_code(), 2);
+        assert_eq!(connect.exit_code(), 3);
+        assert_eq!(i32::from(&write), 4);
+    }
+
+    #[test]
+    fn test_display_includes_context_chain() {
+        let error = OneshotError::Write(
+            anyhow::anyhow!("permission denied").context("Failed to write svid.pem"),
+        );
+        assert_eq!(
+            error.to_string(),
+            "Failed to write svid.pem: permission denied"
+        );
     }
 }
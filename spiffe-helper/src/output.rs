@@ -0,0 +1,45 @@
+//! Informational output on stdout, which `quiet` suppresses. Errors and warnings are written
+//! to stderr with `eprintln!` and are never suppressed.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::io::AsyncWrite;
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Suppresses, or restores, the lines printed with [`info!`](crate::info).
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+#[must_use]
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Stdout for writing from an async task without blocking the runtime, or a writer that
+/// discards everything when quiet output is enabled.
+#[must_use]
+pub fn async_stdout() -> Box<dyn AsyncWrite + Send + Unpin> {
+    if is_quiet() {
+        Box::new(tokio::io::sink())
+    } else {
+        Box::new(tokio::io::stdout())
+    }
+}
+
+/// Stderr for writing from an async task without blocking the runtime. Like `eprintln!`, it
+/// is never suppressed.
+#[must_use]
+pub fn async_stderr() -> Box<dyn AsyncWrite + Send + Unpin> {
+    Box::new(tokio::io::stderr())
+}
+
+/// Prints an informational line to stdout like `println!`, unless quiet output is enabled.
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if !$crate::output::is_quiet() {
+            println!($($arg)*);
+        }
+    };
+}
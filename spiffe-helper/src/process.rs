@@ -1,4 +1,28 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::task::JoinHandle;
+
+use crate::output;
+
+/// Identifies which standard stream of the managed process a line was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChildStream {
+    Stdout,
+    Stderr,
+}
+
+impl fmt::Display for ChildStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChildStream::Stdout => write!(f, "stdout"),
+            ChildStream::Stderr => write!(f, "stderr"),
+        }
+    }
+}
 
 /// Parse command arguments string into individual arguments
 /// Handles quoted strings and escapes using POSIX shell-style parsing
@@ -6,6 +30,139 @@ pub fn parse_cmd_args(args_str: &str) -> Result<Vec<String>> {
     shell_words::split(args_str).map_err(|e| anyhow!("Failed to parse cmd_args: {e}"))
 }
 
+/// Resolves `cmd` the way spawning it would and checks that it is an executable file.
+///
+/// A `cmd` containing a `/` is checked as a path; a bare name is looked up on `PATH`. Returns
+/// the resolved path.
+pub fn check_executable(cmd: &str) -> Result<PathBuf> {
+    if cmd.contains('/') {
+        let path = Path::new(cmd);
+        if !path.exists() {
+            return Err(anyhow!("cmd {cmd} does not exist"));
+        }
+        if !is_executable(path) {
+            return Err(anyhow!(
+                "cmd {cmd} is not an executable file. Check that it is a file and has the executable bit set"
+            ));
+        }
+        return Ok(path.to_path_buf());
+    }
+
+    std::env::var_os("PATH")
+        .iter()
+        .flat_map(std::env::split_paths)
+        .map(|dir| dir.join(cmd))
+        .find(|candidate| is_executable(candidate))
+        .ok_or_else(|| anyhow!("cmd {cmd} was not found as an executable on PATH"))
+}
+
+fn is_executable(path: &Path) -> bool {
+    let Ok(metadata) = path.metadata() else {
+        return false;
+    };
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+    }
+
+    #[cfg(not(unix))]
+    {
+        metadata.is_file()
+    }
+}
+
+/// Run a short-lived command to completion and return its exit status.
+///
+/// Unlike the managed process, the command inherits the helper's stdout and stderr and is
+/// not signalled or tracked once it exits. Dropping the returned future kills the command.
+/// With `umask` set, the command runs with that umask instead of the helper's.
+pub async fn run_to_completion(
+    cmd: &str,
+    args_str: Option<&str>,
+    umask: Option<u32>,
+) -> Result<ExitStatus> {
+    let mut command = Command::new(cmd);
+    command.kill_on_drop(true);
+    if let Some(args_str) = args_str {
+        command.args(parse_cmd_args(args_str)?);
+    }
+    #[cfg(unix)]
+    if let Some(mask) = umask {
+        set_child_umask(&mut command, mask);
+    }
+    #[cfg(not(unix))]
+    let _ = umask;
+    command
+        .status()
+        .await
+        .with_context(|| format!("Failed to run {cmd}"))
+}
+
+/// Sets the umask of the process `command` spawns to `mask` before it executes, so a umask the
+/// helper tightened for its own writes does not change how the process creates its files.
+#[cfg(unix)]
+pub fn set_child_umask(command: &mut Command, mask: u32) {
+    use nix::sys::stat::{umask, Mode};
+
+    let mode = Mode::from_bits_truncate(mask as nix::libc::mode_t);
+    // SAFETY: umask is async-signal-safe and touches no memory shared with the parent
+    unsafe {
+        command.pre_exec(move || {
+            umask(mode);
+            Ok(())
+        });
+    }
+}
+
+/// Relay every line read from a managed process stream to `sink`, tagged with
+/// a `child_stream` field so the output can be told apart from the helper's own.
+///
+/// Returns once the stream reaches EOF, which happens when the child exits or closes it.
+pub async fn relay_child_output<R, W>(reader: R, stream: ChildStream, mut sink: W) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        let tagged = format!("[child_stream={stream}] {line}\n");
+        sink.write_all(tagged.as_bytes()).await?;
+        sink.flush().await?;
+    }
+    Ok(())
+}
+
+/// Spawn relay tasks for the piped stdout and stderr of `child`.
+///
+/// Child stdout is relayed to the helper's stdout, and dropped under `quiet` like the helper's
+/// own informational output; child stderr is relayed to the helper's stderr and never
+/// suppressed. Streams that were not configured as piped are skipped.
+pub fn spawn_output_relays(child: &mut Child) -> Vec<JoinHandle<()>> {
+    let mut relays = Vec::new();
+
+    if let Some(stdout) = child.stdout.take() {
+        relays.push(tokio::spawn(async move {
+            let sink = output::async_stdout();
+            if let Err(e) = relay_child_output(stdout, ChildStream::Stdout, sink).await {
+                eprintln!("Failed to relay managed process stdout: {e}");
+            }
+        }));
+    }
+
+    if let Some(stderr) = child.stderr.take() {
+        relays.push(tokio::spawn(async move {
+            let sink = output::async_stderr();
+            if let Err(e) = relay_child_output(stderr, ChildStream::Stderr, sink).await {
+                eprintln!("Failed to relay managed process stderr: {e}");
+            }
+        }));
+    }
+
+    relays
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -77,6 +234,93 @@ mod tests {
             .contains("Failed to parse cmd_args"));
     }
 
+    #[tokio::test]
+    async fn test_relay_child_output_tags_lines() {
+        let input: &[u8] = b"first\nsecond\n";
+        let mut sink = Vec::new();
+
+        relay_child_output(input, ChildStream::Stderr, &mut sink)
+            .await
+            .unwrap();
+
+        let output = String::from_utf8(sink).unwrap();
+        assert_eq!(
+            output,
+            "[child_stream=stderr] first\n[child_stream=stderr] second\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_to_completion_reports_exit_status() {
+        let status = run_to_completion("sh", Some("-c 'exit 0'"), None)
+            .await
+            .unwrap();
+        assert!(status.success());
+
+        let status = run_to_completion("sh", Some("-c 'exit 3'"), None)
+            .await
+            .unwrap();
+        assert_eq!(status.code(), Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_run_to_completion_missing_command() {
+        let result = run_to_completion("/nonexistent/hook", None, None).await;
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("/nonexistent/hook"));
+    }
+
+    #[test]
+    fn test_check_executable_absolute_path() {
+        assert_eq!(
+            check_executable("/bin/sh").unwrap(),
+            PathBuf::from("/bin/sh")
+        );
+    }
+
+    #[test]
+    fn test_check_executable_on_path() {
+        let resolved = check_executable("sh").unwrap();
+        assert!(resolved.is_absolute());
+        assert!(resolved.ends_with("sh"));
+    }
+
+    #[test]
+    fn test_check_executable_missing() {
+        let error = check_executable("/nonexistent/app")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("/nonexistent/app does not exist"), "{error}");
+
+        let error = check_executable("spiffe-helper-no-such-command")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("not found as an executable on PATH"),
+            "{error}"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_executable_without_exec_bit() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let script = temp_dir.path().join("app.sh");
+        std::fs::write(&script, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        let error = check_executable(script.to_str().unwrap())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("is not an executable file"), "{error}");
+
+        assert!(check_executable(temp_dir.path().to_str().unwrap()).is_err());
+    }
+
     #[test]
     fn test_parse_cmd_args_complex_example() {
         let args = parse_cmd_args(r"-c /etc/nginx/nginx.conf -g 'daemon off;'").unwrap();
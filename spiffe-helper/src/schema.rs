@@ -0,0 +1,68 @@
+//! Describes the config file format as a JSON Schema, for editor autocompletion and for
+//! validating configs in CI. The schema is derived from [`Config`], so new settings appear in it
+//! without further changes.
+
+use anyhow::{Context, Result};
+use schemars::generate::SchemaSettings;
+use schemars::Schema;
+
+use crate::cli::Config;
+
+/// The JSON Schema of [`Config`], with every nested block inlined so each setting is described
+/// where it appears.
+#[must_use]
+pub fn config_schema() -> Schema {
+    SchemaSettings::draft2020_12()
+        .with(|settings| settings.inline_subschemas = true)
+        .into_generator()
+        .into_root_schema_for::<Config>()
+}
+
+/// Prints [`config_schema`] to stdout as pretty-printed JSON.
+pub fn run() -> Result<()> {
+    let schema =
+        serde_json::to_string_pretty(&config_schema()).context("Failed to serialize schema")?;
+    println!("{schema}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    /// The JSON types a property of the config schema accepts.
+    fn property_types(schema: &Value, name: &str) -> Vec<String> {
+        match &schema["properties"][name]["type"] {
+            Value::String(single) => vec![single.clone()],
+            Value::Array(types) => types
+                .iter()
+                .filter_map(|t| t.as_str().map(String::from))
+                .collect(),
+            other => panic!("{name} has no type: {other}"),
+        }
+    }
+
+    #[test]
+    fn test_config_schema_describes_settings() {
+        let schema = serde_json::to_value(config_schema()).unwrap();
+
+        assert!(property_types(&schema, "agent_address").contains(&"string".to_string()));
+        assert!(property_types(&schema, "health_checks").contains(&"object".to_string()));
+        assert!(schema["properties"]["health_checks"]["properties"]["bind_port"].is_object());
+        assert!(schema["properties"]["agent_address"]["description"].is_string());
+    }
+
+    #[test]
+    fn test_config_schema_uses_config_file_keys() {
+        let schema = serde_json::to_value(config_schema()).unwrap();
+        let properties = schema["properties"].as_object().unwrap();
+
+        // Blocks are named as they are written in the config file
+        assert!(properties.contains_key("process"));
+        assert!(properties.contains_key("output"));
+        assert!(!properties.contains_key("processes"));
+        // Set only by --stdout
+        assert!(!properties.contains_key("stdout"));
+    }
+}
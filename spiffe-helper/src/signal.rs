@@ -1,9 +1,26 @@
 use anyhow::{anyhow, Context, Result};
+#[cfg(unix)]
 pub use nix::sys::signal::Signal;
+#[cfg(unix)]
 use nix::unistd::Pid;
 use std::fs;
 use std::path::Path;
 
+/// The signals `renew_signal` accepts. Windows has no POSIX signals, so names still parse but
+/// [`send_signal`] fails; use `renew_sentinel_file` to notify processes there instead.
+#[cfg(not(unix))]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    SIGHUP,
+    SIGINT,
+    SIGQUIT,
+    SIGTERM,
+    SIGUSR1,
+    SIGUSR2,
+    SIGWINCH,
+}
+
 /// Parse a signal name string to a Signal enum
 /// Accepts both "SIGHUP" and "HUP" formats (case-insensitive)
 pub fn parse_signal_name(name: &str) -> Result<Signal> {
@@ -23,11 +40,53 @@ pub fn parse_signal_name(name: &str) -> Result<Signal> {
 }
 
 /// Send a signal to a process identified by PID
+#[cfg(unix)]
 pub fn send_signal(pid: i32, signal: Signal) -> Result<()> {
     nix::sys::signal::kill(Pid::from_raw(pid), signal)
         .with_context(|| format!("Failed to send signal {signal:?} to process {pid}"))
 }
 
+/// Send a signal to a process identified by PID
+#[cfg(not(unix))]
+pub fn send_signal(pid: i32, signal: Signal) -> Result<()> {
+    Err(anyhow!(
+        "Failed to send signal {signal:?} to process {pid}: signals are not supported on this platform"
+    ))
+}
+
+/// Whether a process with `pid` exists, checked by sending it no signal. A process the helper
+/// is not permitted to signal still counts as running.
+#[cfg(unix)]
+pub fn is_process_running(pid: i32) -> bool {
+    !matches!(
+        nix::sys::signal::kill(Pid::from_raw(pid), None),
+        Err(nix::errno::Errno::ESRCH)
+    )
+}
+
+/// Whether a process with `pid` exists. Always true where it cannot be checked.
+#[cfg(not(unix))]
+pub fn is_process_running(_pid: i32) -> bool {
+    true
+}
+
+/// Records a certificate rotation in `path` for processes that cannot be signalled, such as
+/// those on Windows. The file holds the rotation time in seconds since the Unix epoch and is
+/// replaced atomically, so watchers can reload whenever its contents change.
+pub fn write_renew_sentinel(path: &Path) -> Result<()> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = std::path::PathBuf::from(tmp_path);
+
+    fs::write(&tmp_path, format!("{now}\n"))
+        .and_then(|()| fs::rename(&tmp_path, path))
+        .map_err(|e| anyhow!("Failed to write renew sentinel {}: {e}", path.display()))
+}
+
 /// Read a PID from a file
 pub fn read_pid_from_file(path: &Path) -> Result<i32> {
     let content = fs::read_to_string(path)
@@ -154,6 +213,31 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_write_renew_sentinel_records_rotation_time() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let sentinel = temp_dir.path().join("rotated");
+
+        write_renew_sentinel(&sentinel).unwrap();
+
+        let content = fs::read_to_string(&sentinel).unwrap();
+        assert!(content.trim().parse::<u64>().unwrap() > 0, "{content}");
+        assert!(!temp_dir.path().join("rotated.tmp").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_is_process_running() {
+        assert!(is_process_running(nix::unistd::getpid().as_raw()));
+
+        // A child that has exited and been reaped no longer exists
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let pid = i32::try_from(child.id()).unwrap();
+        child.wait().unwrap();
+        assert!(!is_process_running(pid));
+    }
+
+    #[cfg(unix)]
     #[test]
     fn test_send_signal_to_self() {
         // We can't easily test if signal was received without complex setup,
@@ -1,11 +1,28 @@
 use anyhow::{Context, Result};
-use spiffe::bundle::x509::X509Bundle;
-use spiffe::bundle::BundleSource;
+use hyper_util::rt::TokioIo;
+use spiffe::bundle::x509::{X509Bundle, X509BundleSet};
+use spiffe::spiffe_id::{SpiffeId, TrustDomain};
 use spiffe::svid::x509::X509Svid;
-use spiffe::{X509Source, X509SourceBuilder};
+use spiffe::transport::Endpoint;
+use spiffe::{WorkloadApiClient, WorkloadApiError};
+use spiffe::{X509Context, X509Source, X509SourceBuilder};
+use std::collections::hash_map::DefaultHasher;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
 use std::time::Duration;
 
-use crate::file_system::X509CertsWriter;
+use crate::cli::Config;
+use crate::file_system::{CredentialSink, LocalFileSystem, X509Update};
+use crate::health::{ObservedSvid, SharedHealthStatus, SvidInfo};
+use crate::info;
+
+const MAX_BACKOFF_SECONDS: u64 = 16;
+
+type ClientFuture =
+    Pin<Box<dyn Future<Output = Result<WorkloadApiClient, WorkloadApiError>> + Send>>;
 
 fn svid_expiry(svid: &X509Svid) -> String {
     match x509_parser::parse_x509_certificate(svid.leaf().as_ref()) {
@@ -18,22 +35,231 @@ fn svid_expiry(svid: &X509Svid) -> String {
     }
 }
 
-pub fn fetch_and_write_x509_svid<S: X509CertsWriter>(
+/// Time left until the leaf certificate's `not_after`, or zero if it has already expired.
+pub fn svid_remaining_validity(svid: &X509Svid) -> Result<Duration> {
+    let (_, cert) = x509_parser::parse_x509_certificate(svid.leaf().as_ref())
+        .map_err(|e| anyhow::anyhow!("Failed to parse leaf certificate: {e}"))?;
+    let remaining =
+        cert.validity().not_after.timestamp() - x509_parser::time::ASN1Time::now().timestamp();
+    Ok(Duration::from_secs(u64::try_from(remaining).unwrap_or(0)))
+}
+
+/// Time until the SVID should be refreshed: half of its remaining validity, clamped to at least
+/// `floor` and at most `ceil`. The floor wins if the two conflict, and an expired or unparsable
+/// leaf certificate yields the floor so that callers retry promptly.
+#[must_use]
+pub fn refresh_interval_for(svid: &X509Svid, floor: Duration, ceil: Duration) -> Duration {
+    match svid_remaining_validity(svid) {
+        Ok(remaining) => (remaining / 2).min(ceil).max(floor),
+        Err(e) => {
+            eprintln!("Failed to compute refresh interval: {e}");
+            floor
+        }
+    }
+}
+
+/// Summarizes the leaf certificate's identity and validity for the health server's info endpoint.
+pub fn svid_info(svid: &X509Svid) -> Result<SvidInfo> {
+    let (_, cert) = x509_parser::parse_x509_certificate(svid.leaf().as_ref())
+        .map_err(|e| anyhow::anyhow!("Failed to parse leaf certificate: {e}"))?;
+    Ok(SvidInfo {
+        spiffe_id: svid.spiffe_id().to_string(),
+        serial: cert.raw_serial_as_string(),
+        not_before: cert.validity().not_before.timestamp(),
+        not_after: cert.validity().not_after.timestamp(),
+        observed_svids: Vec::new(),
+    })
+}
+
+/// Lists the SPIFFE ID and hint of every SVID in `context`.
+#[must_use]
+pub fn observed_svids(context: &X509Context) -> Vec<ObservedSvid> {
+    context
+        .svids()
+        .iter()
+        .map(|svid| ObservedSvid {
+            spiffe_id: svid.spiffe_id().to_string(),
+            hint: svid
+                .hint()
+                .filter(|hint| !hint.is_empty())
+                .map(String::from),
+        })
+        .collect()
+}
+
+/// Fetches the current SVID and the bundle to write alongside it; see [`bundle_for_writing`].
+/// The bundle is that of `primary_trust_domain`, or of the SVID's own trust domain when `None`.
+pub(crate) fn fetch_x509_svid(
     source: &X509Source,
-    cert_writer: &S,
-) -> Result<()> {
+    primary_trust_domain: Option<&TrustDomain>,
+    include_federated: bool,
+) -> Result<(Arc<X509Svid>, Arc<X509Bundle>)> {
     let svid = source
         .svid()
         .map_err(|e| anyhow::anyhow!("Failed to get SVID: {e}"))?;
 
-    let bundle = source
-        .bundle_for_trust_domain(svid.spiffe_id().trust_domain())
-        .map_err(|e| anyhow::anyhow!("Failed to get bundle: {e}"))?
-        .ok_or_else(|| anyhow::anyhow!("No bundle received"))?;
+    let bundles = source
+        .bundle_set()
+        .map_err(|e| anyhow::anyhow!("Failed to get bundle: {e}"))?;
+    if let Some(primary) = primary_trust_domain {
+        if bundles.get(primary).is_none() {
+            anyhow::bail!(
+                "primary_bundle_trust_domain {primary} is not among the bundles received from \
+                 the SPIRE agent"
+            );
+        }
+    }
+    let primary = primary_trust_domain.unwrap_or_else(|| svid.spiffe_id().trust_domain());
+    let bundle = bundle_for_writing(primary, &bundles, include_federated)?;
+
+    Ok((svid, bundle))
+}
+
+/// Like [`fetch_x509_svid`], with the bundle options of `config`, refusing an SVID outside
+/// `expected_trust_domain` when that is set.
+pub(crate) fn fetch_x509_svid_for_config(
+    source: &X509Source,
+    config: &Config,
+) -> Result<(Arc<X509Svid>, Arc<X509Bundle>)> {
+    let primary = config
+        .primary_bundle_trust_domain
+        .as_deref()
+        .map(TrustDomain::new)
+        .transpose()
+        .context("Invalid primary_bundle_trust_domain")?;
+    let (svid, bundle) =
+        fetch_x509_svid(source, primary.as_ref(), config.include_federated_domains())?;
+    if let Some(expected) = &config.expected_trust_domain {
+        check_trust_domain(svid.spiffe_id().trust_domain(), expected)?;
+    }
+    let bundle = with_extra_ca(bundle, config)?;
+    Ok((svid, bundle))
+}
+
+/// Fails unless `actual` is the trust domain named by `expected`, which may be given as a bare
+/// name or a `spiffe://` URI.
+pub fn check_trust_domain(actual: &TrustDomain, expected: &str) -> Result<()> {
+    let expected = TrustDomain::new(expected)
+        .with_context(|| format!("Invalid expected_trust_domain {expected:?}"))?;
+    if *actual != expected {
+        anyhow::bail!(
+            "SPIRE agent returned an SVID in trust domain {actual}, but expected_trust_domain is \
+             {expected}; refusing to write it"
+        );
+    }
+    Ok(())
+}
+
+/// Returns the bundle for the workload's own `trust_domain`, which must be present.
+///
+/// With `include_federated`, the authorities of every federated bundle are appended in trust
+/// domain order. A federated domain that has no authorities yet is skipped with a warning so
+/// the bundles that are available are still written.
+pub fn bundle_for_writing(
+    trust_domain: &TrustDomain,
+    bundles: &X509BundleSet,
+    include_federated: bool,
+) -> Result<Arc<X509Bundle>> {
+    let own = bundles
+        .get(trust_domain)
+        .ok_or_else(|| anyhow::anyhow!("No bundle received for trust domain {trust_domain}"))?;
+
+    if !include_federated {
+        return Ok(own);
+    }
+
+    let mut combined = X509Bundle::new(trust_domain.clone());
+    for authority in own.authorities() {
+        combined.add_authority(authority.as_ref())?;
+    }
+    for (federated_domain, bundle) in bundles.iter() {
+        if federated_domain == trust_domain {
+            continue;
+        }
+        if bundle.authorities().is_empty() {
+            eprintln!(
+                "Warning: no bundle authorities for federated trust domain {federated_domain} yet; writing the available bundles without it"
+            );
+            continue;
+        }
+        for authority in bundle.authorities() {
+            combined.add_authority(authority.as_ref())?;
+        }
+    }
+
+    Ok(Arc::new(combined))
+}
+
+/// Reads the certificates of a PEM file, failing unless it holds at least one `CERTIFICATE`
+/// block and every block is a certificate that parses.
+pub(crate) fn read_ca_file(path: &str) -> Result<Vec<Vec<u8>>> {
+    let content = std::fs::read(path).with_context(|| format!("Failed to read CA file {path}"))?;
+    let blocks =
+        pem::parse_many(&content).with_context(|| format!("Invalid PEM in CA file {path}"))?;
+    if blocks.is_empty() {
+        anyhow::bail!("CA file {path} holds no certificates");
+    }
+    blocks
+        .into_iter()
+        .enumerate()
+        .map(|(index, block)| {
+            if block.tag != "CERTIFICATE" {
+                anyhow::bail!(
+                    "CA file {path} holds a {} block; only certificates are allowed",
+                    block.tag
+                );
+            }
+            x509_parser::parse_x509_certificate(&block.contents).map_err(|e| {
+                anyhow::anyhow!("Invalid certificate {} in CA file {path}: {e}", index + 1)
+            })?;
+            Ok(block.contents)
+        })
+        .collect()
+}
+
+/// Appends the certificates of `extra_ca_file`, when configured, to `bundle`. The file is read
+/// on every call so each rotation picks up its current content, and a certificate already in
+/// the bundle, compared by DER, is not added again.
+pub(crate) fn with_extra_ca(bundle: Arc<X509Bundle>, config: &Config) -> Result<Arc<X509Bundle>> {
+    let Some(path) = &config.extra_ca_file else {
+        return Ok(bundle);
+    };
+
+    let mut merged = X509Bundle::new(bundle.trust_domain().clone());
+    for authority in bundle.authorities() {
+        merged.add_authority(authority.as_ref())?;
+    }
+    for der in read_ca_file(path)? {
+        if !merged
+            .authorities()
+            .iter()
+            .any(|authority| authority.as_ref() == der.as_slice())
+        {
+            merged.add_authority(&der)?;
+        }
+    }
+    Ok(Arc::new(merged))
+}
 
+pub fn fetch_and_write_x509_svid<S: CredentialSink>(
+    source: &X509Source,
+    cert_writer: &S,
+) -> Result<()> {
+    let (svid, bundle) = fetch_x509_svid(source, None, false)?;
     write_x509_svid_on_update(&svid, &bundle, cert_writer)
 }
 
+/// Like [`fetch_and_write_x509_svid`], but only rewrites files whose content changed since the
+/// last write recorded in `written`. Returns whether any file was written.
+pub fn fetch_and_write_changed_x509_svid<S: CredentialSink>(
+    source: &X509Source,
+    cert_writer: &S,
+    written: &mut WrittenDigests,
+) -> Result<bool> {
+    let (svid, bundle) = fetch_x509_svid(source, None, false)?;
+    write_changed_x509_svid(&svid, &bundle, cert_writer, written)
+}
+
 /// Writes X509 SVID and trust bundle to disk when an update is received from the SPIRE agent.
 ///
 /// This function is called when the `X509Source` receives an update notification.
@@ -44,60 +270,657 @@ pub fn fetch_and_write_x509_svid<S: X509CertsWriter>(
 /// * `svid` - The updated X509 SVID containing the certificate chain and private key
 /// * `bundle` - The trust bundle containing CA certificates
 /// * `config` - Configuration containing output paths
-pub fn write_x509_svid_on_update<S: X509CertsWriter>(
+pub fn write_x509_svid_on_update<S: CredentialSink>(
     svid: &X509Svid,
     bundle: &X509Bundle,
     cert_writer: &S,
 ) -> Result<()> {
     // The chain includes intermediates; writing all certs into one PEM file
     // preserves the full path needed for TLS validation.
-    cert_writer.write_certs(svid.cert_chain())?;
-    cert_writer.write_key(svid.private_key().as_ref())?;
-    cert_writer.write_bundle(bundle)?;
+    cert_writer.write_x509_update(&X509Update {
+        cert_chain: Some(svid.cert_chain()),
+        key: Some(svid.private_key().as_ref()),
+        bundle: Some(bundle),
+    })?;
+
+    log_update(svid);
+
+    Ok(())
+}
+
+/// Name of the `cert_dir` subdirectory [`write_all_x509_svids`] writes `svid` to:
+/// its hint when the agent set one, otherwise its SPIFFE ID without the `spiffe://` scheme.
+/// Characters other than ASCII letters, digits, `.`, `-` and `_` are replaced with `_`, so
+/// `spiffe://example.org/ns/web` becomes `example.org_ns_web`.
+#[must_use]
+pub fn svid_dir_name(svid: &X509Svid) -> String {
+    svid.hint()
+        .map(sanitize_dir_name)
+        .filter(|name| !matches!(name.as_str(), "" | "." | ".."))
+        .unwrap_or_else(|| {
+            let spiffe_id = svid.spiffe_id().to_string();
+            sanitize_dir_name(spiffe_id.trim_start_matches("spiffe://"))
+        })
+}
+
+fn sanitize_dir_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Fills the placeholders of a file name template from an SVID: `{trust_domain}` with its trust
+/// domain name, `{path}` with its SPIFFE ID path without the leading `/`, and `{hint}` with its
+/// hint, empty when the agent set none. Substituted values are sanitized like
+/// [`svid_dir_name`], so `spiffe://example.org/ns/web` fills `{path}` with `ns_web`.
+#[must_use]
+pub fn render_file_name(template: &str, spiffe_id: &SpiffeId, hint: Option<&str>) -> String {
+    template
+        .replace(
+            "{trust_domain}",
+            &sanitize_dir_name(&spiffe_id.trust_domain().to_string()),
+        )
+        .replace(
+            "{path}",
+            &sanitize_dir_name(spiffe_id.path().trim_start_matches('/')),
+        )
+        .replace("{hint}", &sanitize_dir_name(hint.unwrap_or_default()))
+}
+
+/// `config` with its X.509 file name templates rendered for `svid`; see [`render_file_name`].
+#[must_use]
+pub fn config_for_svid(config: &Config, svid: &X509Svid) -> Config {
+    let mut config = config.clone();
+    config.map_x509_file_names(|name| render_file_name(name, svid.spiffe_id(), svid.hint()));
+    config
+}
+
+/// Like [`config_for_svid`], for the default SVID of `source`. Returns `config` unchanged when
+/// it has no file name templates.
+pub fn config_for_source(config: Config, source: &X509Source) -> Result<Config> {
+    if !config.has_file_name_templates() {
+        return Ok(config);
+    }
+    let svid = source
+        .svid()
+        .map_err(|e| anyhow::anyhow!("Failed to get SVID: {e}"))?;
+    Ok(config_for_svid(&config, &svid))
+}
+
+/// Fetches every SVID the agent issues to the workload, failing with [`ConnectTimeout`] when
+/// the agent does not answer within `timeout`. Without a timeout, waits indefinitely.
+pub async fn fetch_x509_context(
+    agent_address: &str,
+    timeout: Option<Duration>,
+) -> Result<X509Context> {
+    let fetch = async {
+        let client = connect_client(agent_address)
+            .await
+            .with_context(|| format!("Failed to connect to SPIRE agent at {agent_address}"))?;
+        client
+            .fetch_x509_context()
+            .await
+            .context("Failed to fetch X.509 SVIDs")
+    };
+    let Some(timeout) = timeout else {
+        return fetch.await;
+    };
+    tokio::time::timeout(timeout, fetch).await.map_err(|_| {
+        anyhow::Error::new(ConnectTimeout {
+            agent_address: agent_address.to_string(),
+            timeout,
+        })
+    })?
+}
+
+/// Whether `context` still issues the identity of `svid`, its SPIFFE ID and hint, telling a
+/// context fetched for an update apart from one whose registration entries changed since. The
+/// certificates are not compared, since an agent may sign a fresh one for each connection.
+#[must_use]
+pub fn context_holds_svid(context: &X509Context, svid: &X509Svid) -> bool {
+    context.svids().iter().any(|candidate| {
+        candidate.spiffe_id() == svid.spiffe_id() && candidate.hint() == svid.hint()
+    })
+}
+
+/// Fetches every SVID the agent issues to the workload, within `connect_timeout`, and writes
+/// each as [`write_all_x509_svids`] does.
+pub async fn fetch_and_write_all_x509_svids(config: &Config) -> Result<Vec<String>> {
+    let context = fetch_x509_context(config.agent_address()?, config.connect_timeout()).await?;
+    write_all_x509_svids(config, &context)
+}
+
+/// Writes every SVID of `context`, with the bundle of its trust domain, to the `cert_dir`
+/// subdirectory named by [`svid_dir_name`]. Returns the names of the subdirectories written.
+pub fn write_all_x509_svids(config: &Config, context: &X509Context) -> Result<Vec<String>> {
+    let cert_dir = config
+        .cert_dir
+        .as_deref()
+        .context("write_all_svids requires cert_dir")?;
+
+    let mut names: Vec<String> = Vec::new();
+    for svid in context.svids() {
+        let name = svid_dir_name(svid);
+        if names.contains(&name) {
+            eprintln!(
+                "Warning: skipping SVID {} because another SVID is already written to {cert_dir}/{name}",
+                svid.spiffe_id()
+            );
+            continue;
+        }
+        let bundle = bundle_for_writing(
+            svid.spiffe_id().trust_domain(),
+            context.bundle_set(),
+            config.include_federated_domains(),
+        )?;
+        let bundle = with_extra_ca(bundle, config)?;
+        let svid_config = Config {
+            cert_dir: Some(
+                std::path::Path::new(cert_dir)
+                    .join(&name)
+                    .display()
+                    .to_string(),
+            ),
+            ..config_for_svid(config, svid)
+        };
+        let sink = LocalFileSystem::new(&svid_config)?.ensure()?;
+        write_x509_svid_on_update(svid, &bundle, &sink)?;
+        names.push(name);
+    }
+    Ok(names)
+}
+
+/// Digests of the content last written to each output file.
+///
+/// The daemon keeps one of these across updates so that an update carrying the same DER bytes
+/// as the files on disk does not rewrite them.
+#[derive(Debug, Default)]
+pub struct WrittenDigests {
+    certs: Option<u64>,
+    key: Option<u64>,
+    bundle: Option<u64>,
+}
+
+fn digest<'a>(parts: impl IntoIterator<Item = &'a [u8]>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for part in parts {
+        part.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Writes only the files whose content differs from what `written` recorded, in one
+/// [`CredentialSink::write_x509_update`], and updates `written` once it succeeds. Returns
+/// whether any file was written.
+pub fn write_changed_x509_svid<S: CredentialSink>(
+    svid: &X509Svid,
+    bundle: &X509Bundle,
+    cert_writer: &S,
+    written: &mut WrittenDigests,
+) -> Result<bool> {
+    let certs_digest = digest(svid.cert_chain().iter().map(AsRef::as_ref));
+    let key_digest = digest([svid.private_key().as_ref()]);
+    let bundle_digest = digest(bundle.authorities().iter().map(AsRef::as_ref));
+    let update = X509Update {
+        cert_chain: (written.certs != Some(certs_digest)).then(|| svid.cert_chain()),
+        key: (written.key != Some(key_digest)).then(|| svid.private_key().as_ref()),
+        bundle: (written.bundle != Some(bundle_digest)).then_some(bundle),
+    };
+    let changed = update.cert_chain.is_some() || update.key.is_some() || update.bundle.is_some();
+
+    if changed {
+        cert_writer.write_x509_update(&update)?;
+        written.certs = Some(certs_digest);
+        written.key = Some(key_digest);
+        written.bundle = Some(bundle_digest);
+        log_update(svid);
+    } else {
+        info!(
+            "Certificate unchanged, skipping write: spiffe_id={}",
+            svid.spiffe_id()
+        );
+    }
+
+    Ok(changed)
+}
 
+fn log_update(svid: &X509Svid) {
     // Log update with SPIFFE ID and certificate expiry
-    println!(
+    info!(
         "Updated certificate: spiffe_id={}, expires={}",
         svid.spiffe_id(),
         svid_expiry(svid)
     );
+}
 
-    Ok(())
+/// Returns the socket name when `address` refers to a Linux abstract-namespace socket, written
+/// as `unix:@name`, `unix://@name`, or with a leading NUL in place of `@`.
+pub(crate) fn abstract_socket_name(address: &str) -> Option<&str> {
+    let path = address
+        .strip_prefix("unix://")
+        .or_else(|| address.strip_prefix("unix:"))?;
+    path.strip_prefix('@').or_else(|| path.strip_prefix('\0'))
 }
 
 /// Normalizes the agent address to a format accepted by the spiffe crate.
-/// Converts "unix:///path" to "unix:/path" (single slash after scheme).
-fn normalize_endpoint(address: &str) -> String {
+/// Converts "unix:///path" to "unix:/path" (single slash after scheme). Abstract socket
+/// addresses are normalized to "unix:@name" so the name is preserved verbatim.
+pub(crate) fn normalize_endpoint(address: &str) -> String {
     const UDS_PREFIX: &str = "unix://";
+    if let Some(name) = abstract_socket_name(address) {
+        return format!("unix:@{name}");
+    }
     address
         .strip_prefix(UDS_PREFIX)
         .map_or_else(|| address.to_string(), |v| format!("unix:{v}"))
 }
 
+/// Checks that `address` is a usable agent address, so that malformed values are reported
+/// when the configuration is loaded rather than when the helper first connects.
+pub fn validate_agent_address(address: &str) -> Result<()> {
+    if let Some(name) = abstract_socket_name(address) {
+        if name.is_empty() {
+            anyhow::bail!("Invalid agent_address {address:?}: abstract socket name is empty");
+        }
+        return Ok(());
+    }
+
+    Endpoint::parse(&normalize_endpoint(address))
+        .map(|_| ())
+        .map_err(|e| anyhow::anyhow!("Invalid agent_address {address:?}: {e}"))
+}
+
+/// Describes each way the agent socket of a `unix://` address is open to tampering: a socket
+/// any user can write to, one in a directory where any user can replace it, or one not owned
+/// by `expected_uid` when that is set. Empty for TCP and abstract socket addresses, and for a
+/// socket that does not exist yet.
+#[cfg(unix)]
+#[must_use]
+pub fn agent_socket_problems(address: &str, expected_uid: Option<u32>) -> Vec<String> {
+    use std::os::unix::fs::{FileTypeExt, MetadataExt};
+
+    let mut problems = Vec::new();
+    if abstract_socket_name(address).is_some() {
+        return problems;
+    }
+    let Some(path) = address
+        .strip_prefix("unix://")
+        .or_else(|| address.strip_prefix("unix:"))
+        .map(std::path::Path::new)
+    else {
+        return problems;
+    };
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return problems;
+    };
+
+    if !metadata.file_type().is_socket() {
+        problems.push(format!("agent socket {} is not a socket", path.display()));
+    }
+    if metadata.mode() & 0o002 != 0 {
+        problems.push(format!(
+            "agent socket {} has mode {:04o}, writable by any user",
+            path.display(),
+            metadata.mode() & 0o7777
+        ));
+    }
+    if let Some(expected_uid) = expected_uid {
+        if metadata.uid() != expected_uid {
+            problems.push(format!(
+                "agent socket {} is owned by uid {}, expected agent_socket_owner_uid {expected_uid}",
+                path.display(),
+                metadata.uid()
+            ));
+        }
+    }
+    // Without the sticky bit, any user can replace a socket in a world-writable directory
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        if let Ok(dir_metadata) = std::fs::metadata(dir) {
+            if dir_metadata.mode() & 0o002 != 0 && dir_metadata.mode() & 0o1000 == 0 {
+                problems.push(format!(
+                    "agent socket directory {} is writable by any user",
+                    dir.display()
+                ));
+            }
+        }
+    }
+    problems
+}
+
+/// Checks the agent socket before connecting, warning about each of
+/// [`agent_socket_problems`], or failing on the first under `strict_permissions`.
+pub fn check_agent_socket(config: &Config) -> Result<()> {
+    #[cfg(unix)]
+    if config.check_agent_socket() {
+        let Some(address) = config.agent_address.as_deref() else {
+            return Ok(());
+        };
+        for problem in agent_socket_problems(address, config.agent_socket_owner_uid) {
+            if config.strict_permissions() {
+                anyhow::bail!(
+                    "{problem}.\n\
+                     Restrict the socket's permissions, or unset strict_permissions"
+                );
+            }
+            eprintln!("Warning: {problem}");
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = config;
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn connect_abstract_socket(name: &str) -> std::io::Result<tokio::net::UnixStream> {
+    use std::os::linux::net::SocketAddrExt;
+
+    let addr = std::os::unix::net::SocketAddr::from_abstract_name(name.as_bytes())?;
+    let stream = std::os::unix::net::UnixStream::connect_addr(&addr)?;
+    stream.set_nonblocking(true)?;
+    tokio::net::UnixStream::from_std(stream)
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn connect_abstract_socket(_name: &str) -> std::io::Result<tokio::net::UnixStream> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "abstract sockets are only supported on Linux",
+    ))
+}
+
+// Without Unix sockets there is nothing to dial; the stream type only satisfies the connector.
+#[cfg(not(unix))]
+fn connect_abstract_socket(_name: &str) -> std::io::Result<tokio::net::TcpStream> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "abstract sockets are only supported on Linux",
+    ))
+}
+
+/// Opens a stream to the agent at `address`, which may be an abstract socket.
+#[cfg(unix)]
+pub(crate) async fn connect_unix_stream(address: &str) -> std::io::Result<tokio::net::UnixStream> {
+    match abstract_socket_name(address) {
+        Some(name) => connect_abstract_socket(name),
+        None => {
+            let path = normalize_endpoint(address);
+            tokio::net::UnixStream::connect(path.trim_start_matches("unix:")).await
+        }
+    }
+}
+
+/// Connects a Workload API client to `address`, including abstract sockets, which the spiffe
+/// crate cannot dial on its own.
+pub(crate) async fn connect_client(address: &str) -> Result<WorkloadApiClient, WorkloadApiError> {
+    let Some(name) = abstract_socket_name(address) else {
+        return WorkloadApiClient::connect_to(normalize_endpoint(address)).await;
+    };
+
+    let endpoint = Endpoint::Unix(PathBuf::from(format!("@{name}")));
+    let name = name.to_string();
+    let channel = spiffe_tonic::transport::Endpoint::from_static("http://localhost")
+        .connect_with_connector(tower::service_fn(move |_| {
+            let name = name.clone();
+            async move { connect_abstract_socket(&name).map(TokioIo::new) }
+        }))
+        .await?;
+
+    Ok(WorkloadApiClient::new_with_channel(endpoint, channel))
+}
+
 /// Creates an X509Source connected to the specified agent address.
 /// This is the primary interface for creating X509Source instances with proper configuration.
 pub async fn create_x509_source(agent_address: &str) -> Result<X509Source> {
-    let endpoint = normalize_endpoint(agent_address);
-    X509SourceBuilder::new()
-        .endpoint(&endpoint)
+    let builder = if abstract_socket_name(agent_address).is_some() {
+        let address = agent_address.to_string();
+        X509SourceBuilder::new().client_factory(Arc::new(move || {
+            let address = address.clone();
+            Box::pin(async move { connect_client(&address).await }) as ClientFuture
+        }))
+    } else {
+        X509SourceBuilder::new().endpoint(normalize_endpoint(agent_address))
+    };
+
+    builder
         .reconnect_backoff(Duration::from_secs(1), Duration::from_secs(16))
         .build()
         .await
         .context("Failed to create X509Source from SPIRE agent")
 }
 
+/// The agent did not send its first X.509 response within the connect timeout, for example
+/// because it accepts connections but never replies. Callers can tell it apart from other
+/// connection failures with `anyhow::Error::downcast_ref`.
+#[derive(Debug)]
+pub struct ConnectTimeout {
+    pub agent_address: String,
+    pub timeout: Duration,
+}
+
+impl std::fmt::Display for ConnectTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Timed out after {}s waiting for the SPIRE agent at {} to respond",
+            self.timeout.as_secs(),
+            self.agent_address
+        )
+    }
+}
+
+impl std::error::Error for ConnectTimeout {}
+
+/// Like [`create_x509_source`], but fails with [`ConnectTimeout`] when the source has not
+/// received its first update within `timeout`. Without a timeout, waits indefinitely.
+pub async fn create_x509_source_with_timeout(
+    agent_address: &str,
+    timeout: Option<Duration>,
+) -> Result<X509Source> {
+    let Some(timeout) = timeout else {
+        return create_x509_source(agent_address).await;
+    };
+    tokio::time::timeout(timeout, create_x509_source(agent_address))
+        .await
+        .map_err(|_| {
+            anyhow::Error::new(ConnectTimeout {
+                agent_address: agent_address.to_string(),
+                timeout,
+            })
+        })?
+}
+
+/// Like [`create_x509_source`], but first checks that the agent accepts connections, trying up
+/// to `max_attempts` times in total with exponential backoff between attempts. Building the
+/// source alone keeps retrying its initial sync indefinitely; this bounds how long one-shot mode
+/// waits for an agent whose socket appears shortly after the helper starts.
+///
+/// With `jitter`, each delay is drawn uniformly between zero and the backoff cap, so that many
+/// helpers restarted together by an agent restart do not reconnect in lockstep. Once the agent
+/// accepts a connection, building the source is bounded by `connect_timeout` as in
+/// [`create_x509_source_with_timeout`].
+pub async fn create_x509_source_with_retry(
+    agent_address: &str,
+    max_attempts: u32,
+    jitter: bool,
+    connect_timeout: Option<Duration>,
+) -> Result<X509Source> {
+    let mut backoff = Backoff::new(jitter);
+    let mut attempt = 1;
+
+    loop {
+        match connect_client(agent_address).await {
+            Ok(_) => break,
+            Err(e) if attempt >= max_attempts => {
+                anyhow::bail!(
+                    "Failed to connect to SPIRE agent at {agent_address} after {attempt} attempts: {e}"
+                );
+            }
+            Err(e) => {
+                let delay = backoff.delay(attempt);
+                eprintln!(
+                    "Failed to connect to SPIRE agent at {agent_address} (attempt {attempt}/{max_attempts}): {e}, retrying in {:.1}s",
+                    delay.as_secs_f64()
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+        }
+    }
+
+    create_x509_source_with_timeout(agent_address, connect_timeout).await
+}
+
+/// Waits until the agent issues the workload at least one X.509 SVID, retrying an empty or
+/// failed fetch up to `max_attempts` times in total with backoff as in
+/// [`create_x509_source_with_retry`]. An agent that does not accept connections is left to the
+/// caller's connection handling. Each fetch is bounded by `connect_timeout`, failing with
+/// [`ConnectTimeout`].
+///
+/// Each failed attempt is recorded as an X.509 SVID failure in `health_status`, if given, so
+/// the health checks explain why the helper is not ready yet.
+pub async fn wait_for_x509_svid(
+    agent_address: &str,
+    max_attempts: u32,
+    jitter: bool,
+    connect_timeout: Option<Duration>,
+    health_status: Option<&SharedHealthStatus>,
+) -> Result<()> {
+    let mut backoff = Backoff::new(jitter);
+    let mut attempt = 1;
+
+    loop {
+        let probe = async {
+            let client = connect_client(agent_address).await.ok()?;
+            Some(client.fetch_x509_context().await)
+        };
+        let fetched = match connect_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, probe).await.map_err(|_| {
+                anyhow::Error::new(ConnectTimeout {
+                    agent_address: agent_address.to_string(),
+                    timeout,
+                })
+            })?,
+            None => probe.await,
+        };
+        let problem = match fetched {
+            None => return Ok(()),
+            Some(Ok(context)) if !context.svids().is_empty() => return Ok(()),
+            Some(Ok(_)) => "the agent returned no SVIDs".to_string(),
+            Some(Err(e)) => e.to_string(),
+        };
+        if let Some(health_status) = health_status {
+            health_status
+                .write()
+                .await
+                .x509_svid
+                .record_failure(format!(
+                    "agent issued no SVID, attempt {attempt}/{max_attempts}: {problem}"
+                ));
+        }
+
+        if attempt >= max_attempts {
+            anyhow::bail!(
+                "SPIRE agent at {agent_address} issued no X.509 SVID after {attempt} attempts \
+                 ({problem}); the workload is likely not registered, or the agent could not \
+                 attest it. Check that a registration entry's selectors match this workload"
+            );
+        }
+        let delay = backoff.delay(attempt);
+        eprintln!(
+            "No X.509 SVID from SPIRE agent at {agent_address} (attempt {attempt}/{max_attempts}): {problem}, retrying in {:.1}s",
+            delay.as_secs_f64()
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// Delay before retrying after the given 1-based attempt: doubling from one second, capped at
+/// 16 seconds. The delays [`Backoff::new`] caps at.
+#[cfg(test)]
+pub(crate) fn backoff_delay(attempt: u32) -> Duration {
+    scaled_backoff_delay(
+        attempt,
+        Duration::from_secs(1),
+        Duration::from_secs(MAX_BACKOFF_SECONDS),
+    )
+}
+
+/// Delay before retrying after the given 1-based attempt: doubling from `base`, capped at `max`.
+pub(crate) fn scaled_backoff_delay(attempt: u32, base: Duration, max: Duration) -> Duration {
+    1u32.checked_shl(attempt.saturating_sub(1))
+        .and_then(|factor| base.checked_mul(factor))
+        .unwrap_or(max)
+        .min(max)
+}
+
+/// Retry delays following [`scaled_backoff_delay`], optionally with full jitter.
+pub(crate) struct Backoff {
+    jitter: bool,
+    base: Duration,
+    max: Duration,
+    rng: fastrand::Rng,
+}
+
+impl Backoff {
+    /// Delays following [`backoff_delay`].
+    pub(crate) fn new(jitter: bool) -> Self {
+        Self::with_limits(
+            Duration::from_secs(1),
+            Duration::from_secs(MAX_BACKOFF_SECONDS),
+            jitter,
+        )
+    }
+
+    /// Delays doubling from `base`, capped at `max`.
+    pub(crate) fn with_limits(base: Duration, max: Duration, jitter: bool) -> Self {
+        Self {
+            jitter,
+            base,
+            max,
+            rng: fastrand::Rng::new(),
+        }
+    }
+
+    /// Draws the jitter from a seeded RNG so the delays are reproducible.
+    #[cfg(test)]
+    pub(crate) fn seeded(mut self, seed: u64) -> Self {
+        self.rng = fastrand::Rng::with_seed(seed);
+        self
+    }
+
+    /// Delay before retrying after the given 1-based attempt: the [`scaled_backoff_delay`] cap,
+    /// or with jitter a random delay between zero and the cap.
+    pub(crate) fn delay(&mut self, attempt: u32) -> Duration {
+        let cap = scaled_backoff_delay(attempt, self.base, self.max);
+        if !self.jitter {
+            return cap;
+        }
+        let cap_millis = u64::try_from(cap.as_millis()).unwrap_or(u64::MAX);
+        Duration::from_millis(self.rng.u64(0..=cap_millis))
+    }
+}
+
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
     use crate::cli::Config;
-    use crate::file_system::LocalFileSystem;
+    use crate::file_system::{LocalFileSystem, MemorySink};
     use spiffe::bundle::x509::X509Bundle;
     use spiffe::spiffe_id::TrustDomain;
     use spiffe::svid::x509::X509Svid;
+    use spire_agent_mock::svid::{SvidConfig, SvidGenerator};
     use std::fs;
+    use std::time::SystemTime;
     use tempfile::TempDir;
 
-    const TEST_CERT_PEM: &str = r"-----BEGIN CERTIFICATE-----
+    pub(crate) const TEST_CERT_PEM: &str = r"-----BEGIN CERTIFICATE-----
 MIIDNTCCAh2gAwIBAgIUGq/oNncXam0A9VgyVENC8GuQn/gwDQYJKoZIhvcNAQEL
 BQAwFDESMBAGA1UEAwwJbG9jYWxob3N0MB4XDTI1MTIyOTAwNTYyOVoXDTI2MTIy
 OTAwNTYyOVowFDESMBAGA1UEAwwJbG9jYWxob3N0MIIBIjANBgkqhkiG9w0BAQEF
@@ -159,22 +982,207 @@ fPfrHw1nYcPliVB4Zbv8d1w=
         X509Bundle::parse_from_der(td, &cert_der).expect("Failed to parse Bundle")
     }
 
-    struct DummyStorage;
+    #[test]
+    fn test_check_trust_domain() {
+        let actual = TrustDomain::new("example.org").unwrap();
+
+        assert!(check_trust_domain(&actual, "example.org").is_ok());
+        assert!(check_trust_domain(&actual, "spiffe://example.org").is_ok());
+
+        let error = check_trust_domain(&actual, "other.org").unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("trust domain example.org, but expected_trust_domain is other.org"));
+    }
 
-    impl X509CertsWriter for DummyStorage {
-        fn write_certs(&self, _certificates: &[spiffe::cert::Certificate]) -> Result<()> {
-            Ok(())
+    /// An SVID from the mock agent, valid between `not_before` and `not_after`.
+    fn svid_with_validity(not_before: SystemTime, not_after: SystemTime) -> X509Svid {
+        let generated = SvidGenerator::new(SvidConfig::default())
+            .generate_svid_with_validity(not_before, not_after);
+        X509Svid::parse_from_der(&generated.cert_chain_der, &generated.private_key_der)
+            .expect("Failed to parse SVID")
+    }
+
+    /// An SVID from the mock agent, issued an hour ago and valid for `valid_for` from now.
+    fn svid_valid_for(valid_for: Duration) -> X509Svid {
+        let now = SystemTime::now();
+        svid_with_validity(now - Duration::from_secs(3600), now + valid_for)
+    }
+
+    #[test]
+    fn test_refresh_interval_for_is_half_of_remaining_validity() {
+        let svid = svid_valid_for(Duration::from_secs(3600));
+
+        let interval =
+            refresh_interval_for(&svid, Duration::from_secs(5), Duration::from_secs(86_400));
+        // Allow for time passing between minting the certificate and computing the interval
+        assert!(
+            interval <= Duration::from_secs(1800) && interval >= Duration::from_secs(1790),
+            "{interval:?}"
+        );
+    }
+
+    #[test]
+    fn test_refresh_interval_for_clamps_to_bounds() {
+        let svid = svid_valid_for(Duration::from_secs(3600));
+
+        assert_eq!(
+            refresh_interval_for(&svid, Duration::from_secs(5), Duration::from_secs(600)),
+            Duration::from_secs(600)
+        );
+        assert_eq!(
+            refresh_interval_for(
+                &svid,
+                Duration::from_secs(7200),
+                Duration::from_secs(86_400)
+            ),
+            Duration::from_secs(7200)
+        );
+        // The floor wins over a conflicting ceiling
+        assert_eq!(
+            refresh_interval_for(&svid, Duration::from_secs(60), Duration::from_secs(30)),
+            Duration::from_secs(60)
+        );
+    }
+
+    #[test]
+    fn test_refresh_interval_for_expired_svid_returns_floor() {
+        let now = SystemTime::now();
+        let svid = svid_with_validity(
+            now - Duration::from_secs(7200),
+            now - Duration::from_secs(3600),
+        );
+
+        assert_eq!(svid_remaining_validity(&svid).unwrap(), Duration::ZERO);
+        assert_eq!(
+            refresh_interval_for(&svid, Duration::from_secs(5), Duration::from_secs(600)),
+            Duration::from_secs(5)
+        );
+    }
+
+    #[test]
+    fn test_backoff_delay_doubles_and_caps() {
+        assert_eq!(backoff_delay(1), Duration::from_secs(1));
+        assert_eq!(backoff_delay(2), Duration::from_secs(2));
+        assert_eq!(backoff_delay(5), Duration::from_secs(16));
+        assert_eq!(backoff_delay(10), Duration::from_secs(16));
+        assert_eq!(backoff_delay(100), Duration::from_secs(16));
+    }
+
+    #[test]
+    fn test_scaled_backoff_delay_doubles_from_base_and_caps() {
+        let base = Duration::from_millis(250);
+        let max = Duration::from_secs(3);
+        assert_eq!(scaled_backoff_delay(1, base, max), base);
+        assert_eq!(scaled_backoff_delay(3, base, max), Duration::from_secs(1));
+        assert_eq!(scaled_backoff_delay(5, base, max), max);
+        assert_eq!(scaled_backoff_delay(100, base, max), max);
+    }
+
+    #[test]
+    fn test_jittered_backoff_with_limits_stays_within_cap() {
+        let max = Duration::from_secs(5);
+        let mut backoff = Backoff::with_limits(Duration::from_secs(2), max, true).seeded(3);
+        for attempt in 1..=10 {
+            for _ in 0..20 {
+                assert!(backoff.delay(attempt) <= max);
+            }
         }
+    }
 
-        fn write_key(&self, _key: &[u8]) -> Result<()> {
-            Ok(())
+    #[test]
+    fn test_jittered_backoff_stays_within_cap() {
+        let mut backoff = Backoff::new(true).seeded(42);
+        let mut delays = Vec::new();
+        for attempt in 1..=8 {
+            for _ in 0..50 {
+                let delay = backoff.delay(attempt);
+                assert!(
+                    delay <= backoff_delay(attempt),
+                    "attempt {attempt}: {delay:?} exceeds the cap"
+                );
+                delays.push(delay);
+            }
         }
+        // Full jitter spreads the delays rather than settling on the cap
+        assert!(delays.iter().any(|d| *d < Duration::from_secs(8)));
+        assert!(delays.iter().any(|d| *d > Duration::from_secs(8)));
+    }
 
-        fn write_bundle(&self, _bundle: &X509Bundle) -> Result<()> {
-            Ok(())
+    #[test]
+    fn test_seeded_backoff_is_reproducible() {
+        let mut first = Backoff::new(true).seeded(7);
+        let mut second = Backoff::new(true).seeded(7);
+        for attempt in 1..=6 {
+            assert_eq!(first.delay(attempt), second.delay(attempt));
         }
     }
 
+    #[test]
+    fn test_backoff_without_jitter_uses_cap() {
+        let mut backoff = Backoff::new(false).seeded(7);
+        for attempt in 1..=6 {
+            assert_eq!(backoff.delay(attempt), backoff_delay(attempt));
+        }
+    }
+
+    #[test]
+    fn test_bundle_for_writing_skips_federated_domain_without_authorities() {
+        let own = get_test_bundle();
+        let cert_der = pem::parse(TEST_CERT_PEM).unwrap().contents;
+        let federated =
+            X509Bundle::parse_from_der(TrustDomain::new("federated.org").unwrap(), &cert_der)
+                .unwrap();
+        let empty = X509Bundle::new(TrustDomain::new("empty.org").unwrap());
+        let mut bundles = X509BundleSet::new();
+        bundles.add_bundle(own.clone());
+        bundles.add_bundle(federated);
+        bundles.add_bundle(empty);
+
+        let combined = bundle_for_writing(own.trust_domain(), &bundles, true).unwrap();
+        assert_eq!(combined.trust_domain(), own.trust_domain());
+        assert_eq!(combined.authorities().len(), 2);
+
+        let own_only = bundle_for_writing(own.trust_domain(), &bundles, false).unwrap();
+        assert_eq!(own_only.authorities().len(), 1);
+    }
+
+    #[test]
+    fn test_bundle_for_writing_requires_own_domain() {
+        let mut bundles = X509BundleSet::new();
+        bundles.add_bundle(X509Bundle::new(TrustDomain::new("federated.org").unwrap()));
+
+        let error = bundle_for_writing(&TrustDomain::new("localhost").unwrap(), &bundles, true)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("No bundle received for trust domain localhost"));
+    }
+
+    #[test]
+    fn test_sanitize_dir_name() {
+        assert_eq!(
+            sanitize_dir_name("example.org/ns/web:8443"),
+            "example.org_ns_web_8443"
+        );
+        assert_eq!(sanitize_dir_name("internal-api_v2"), "internal-api_v2");
+    }
+
+    #[test]
+    fn test_render_file_name() {
+        let spiffe_id = SpiffeId::new("spiffe://example.org/ns/prod/sa/web").unwrap();
+
+        assert_eq!(
+            render_file_name("{trust_domain}-{path}.crt", &spiffe_id, None),
+            "example.org-ns_prod_sa_web.crt"
+        );
+        assert_eq!(
+            render_file_name("{hint}_key.pem", &spiffe_id, Some("internal/api")),
+            "internal_api_key.pem"
+        );
+        assert_eq!(render_file_name("{hint}.pem", &spiffe_id, None), ".pem");
+        assert_eq!(render_file_name("svid.pem", &spiffe_id, None), "svid.pem");
+    }
+
     #[test]
     fn test_normalize_endpoint_with_triple_slash() {
         let result = normalize_endpoint("unix:///tmp/test.sock");
@@ -194,6 +1202,57 @@ fPfrHw1nYcPliVB4Zbv8d1w=
         assert_eq!(result, "tcp://127.0.0.1:8080");
     }
 
+    #[test]
+    fn test_normalize_endpoint_abstract_socket() {
+        assert_eq!(normalize_endpoint("unix://@spire"), "unix:@spire");
+        assert_eq!(normalize_endpoint("unix:@spire"), "unix:@spire");
+        assert_eq!(normalize_endpoint("unix:\0spire"), "unix:@spire");
+    }
+
+    #[test]
+    fn test_abstract_socket_name() {
+        assert_eq!(abstract_socket_name("unix://@spire"), Some("spire"));
+        assert_eq!(abstract_socket_name("unix:@spire"), Some("spire"));
+        assert_eq!(abstract_socket_name("unix:///tmp/agent.sock"), None);
+        assert_eq!(abstract_socket_name("tcp://127.0.0.1:8081"), None);
+    }
+
+    #[test]
+    fn test_validate_agent_address_accepts_supported_forms() {
+        assert!(validate_agent_address("unix:/tmp/agent.sock").is_ok());
+        assert!(validate_agent_address("unix:///tmp/agent.sock").is_ok());
+        assert!(validate_agent_address("unix://@spire").is_ok());
+        assert!(validate_agent_address("tcp://127.0.0.1:8081").is_ok());
+    }
+
+    #[test]
+    fn test_validate_agent_address_rejects_malformed() {
+        for address in [
+            "unix://",
+            "unix:",
+            "unix://@",
+            "tcp://127.0.0.1",
+            "/tmp/agent.sock",
+        ] {
+            let result = validate_agent_address(address);
+            assert!(result.is_err(), "{address:?} should be rejected");
+            assert!(result.unwrap_err().to_string().contains("agent_address"));
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_connect_unix_stream_abstract_socket() {
+        use std::os::linux::net::SocketAddrExt;
+
+        let name = format!("spiffe-helper-test-{}", std::process::id());
+        let addr = std::os::unix::net::SocketAddr::from_abstract_name(name.as_bytes()).unwrap();
+        let _listener = std::os::unix::net::UnixListener::bind_addr(&addr).unwrap();
+
+        let stream = connect_unix_stream(&format!("unix://@{name}")).await;
+        assert!(stream.is_ok(), "Failed to connect: {:?}", stream.err());
+    }
+
     #[test]
     fn test_storage_write_svid_success() {
         let temp_dir = TempDir::new().unwrap();
@@ -208,7 +1267,7 @@ fPfrHw1nYcPliVB4Zbv8d1w=
         };
         let local_fs = LocalFileSystem::new(&config).unwrap().ensure().unwrap();
 
-        local_fs.write_certs(svid.cert_chain()).unwrap();
+        local_fs.write_cert_chain(svid.cert_chain()).unwrap();
         local_fs.write_key(svid.private_key().as_ref()).unwrap();
 
         assert!(cert_dir.join("svid.pem").exists());
@@ -265,16 +1324,221 @@ fPfrHw1nYcPliVB4Zbv8d1w=
         assert!(cert_dir.join("svid_bundle.pem").exists());
     }
 
+    #[test]
+    fn test_write_x509_svid_on_update_writes_fingerprints() {
+        let temp_dir = TempDir::new().unwrap();
+        let cert_dir = temp_dir.path();
+        let config = Config {
+            cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+            write_fingerprints: Some(true),
+            ..Default::default()
+        };
+
+        let local_fs = LocalFileSystem::new(&config).unwrap().ensure().unwrap();
+        write_x509_svid_on_update(&get_test_svid(), &get_test_bundle(), &local_fs).unwrap();
+
+        for name in ["svid.pem", "svid_bundle.pem"] {
+            let written = fs::read(cert_dir.join(name)).unwrap();
+            let fingerprint = fs::read_to_string(cert_dir.join(format!("{name}.sha256"))).unwrap();
+            assert_eq!(
+                fingerprint,
+                format!("{}\n", crate::file_system::sha256_hex(&written))
+            );
+        }
+        assert!(!cert_dir.join("svid_key.pem.sha256").exists());
+        assert!(!cert_dir.join("svid.pem.sha256.tmp").exists());
+    }
+
+    #[test]
+    fn test_write_x509_svid_on_update_skips_fingerprints_by_default() {
+        let temp_dir = TempDir::new().unwrap();
+        let cert_dir = temp_dir.path();
+        let config = Config {
+            cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+            ..Default::default()
+        };
+
+        let local_fs = LocalFileSystem::new(&config).unwrap().ensure().unwrap();
+        write_x509_svid_on_update(&get_test_svid(), &get_test_bundle(), &local_fs).unwrap();
+
+        assert!(!cert_dir.join("svid.pem.sha256").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_ensure_applies_cert_dir_mode_to_created_dirs() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        fs::set_permissions(temp_dir.path(), fs::Permissions::from_mode(0o751)).unwrap();
+        let cert_dir = temp_dir.path().join("nested").join("certs");
+        let config = Config {
+            cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+            cert_dir_mode: Some("0700".to_string()),
+            ..Default::default()
+        };
+
+        LocalFileSystem::new(&config).unwrap().ensure().unwrap();
+
+        let mode =
+            |path: &std::path::Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&cert_dir), 0o700);
+        assert_eq!(mode(&temp_dir.path().join("nested")), 0o700);
+        // Pre-existing directories keep their mode
+        assert_eq!(mode(temp_dir.path()), 0o751);
+    }
+
+    #[test]
+    fn test_ensure_rejects_cert_dir_that_is_a_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let cert_dir = temp_dir.path().join("certs");
+        fs::write(&cert_dir, "").unwrap();
+        let config = Config {
+            cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+            ..Default::default()
+        };
+
+        let error_msg = LocalFileSystem::new(&config)
+            .unwrap()
+            .ensure()
+            .unwrap_err()
+            .to_string();
+
+        assert!(error_msg.contains("is not writable"));
+        assert!(error_msg.contains(cert_dir.to_str().unwrap()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_ensure_rejects_read_only_cert_dir() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // Permission bits do not restrict root
+        if nix::unistd::geteuid().is_root() {
+            return;
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let cert_dir = temp_dir.path().join("certs");
+        fs::create_dir(&cert_dir).unwrap();
+        fs::set_permissions(&cert_dir, fs::Permissions::from_mode(0o555)).unwrap();
+        let config = Config {
+            cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+            ..Default::default()
+        };
+
+        let error_msg = LocalFileSystem::new(&config)
+            .unwrap()
+            .ensure()
+            .unwrap_err()
+            .to_string();
+
+        assert!(error_msg.contains("is not writable"));
+        assert!(error_msg.contains(cert_dir.to_str().unwrap()));
+        assert!(error_msg.contains("Permission denied"));
+        assert_eq!(fs::read_dir(&cert_dir).unwrap().count(), 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_x509_svid_on_update_sets_owner_as_root() {
+        use std::os::unix::fs::MetadataExt;
+
+        if !nix::unistd::geteuid().is_root() {
+            return;
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let cert_dir = temp_dir.path();
+        let config = Config {
+            cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+            file_owner_uid: Some(1234),
+            file_owner_gid: Some(5678),
+            ..Default::default()
+        };
+
+        let local_fs = LocalFileSystem::new(&config).unwrap().ensure().unwrap();
+        write_x509_svid_on_update(&get_test_svid(), &get_test_bundle(), &local_fs).unwrap();
+
+        for name in ["svid.pem", "svid_key.pem", "svid_bundle.pem"] {
+            let metadata = fs::metadata(cert_dir.join(name)).unwrap();
+            assert_eq!(metadata.uid(), 1234, "unexpected owner of {name}");
+            assert_eq!(metadata.gid(), 5678, "unexpected group of {name}");
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_x509_svid_on_update_owner_requires_privilege() {
+        if nix::unistd::geteuid().is_root() {
+            return;
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config {
+            cert_dir: Some(temp_dir.path().to_str().unwrap().to_string()),
+            file_owner_uid: Some(0),
+            ..Default::default()
+        };
+
+        let local_fs = LocalFileSystem::new(&config).unwrap().ensure().unwrap();
+        let result = write_x509_svid_on_update(&get_test_svid(), &get_test_bundle(), &local_fs);
+
+        let error_msg = result.unwrap_err().to_string();
+        assert!(error_msg.contains("Failed to change ownership"));
+        assert!(error_msg.contains("permission denied"));
+    }
+
     #[test]
     fn test_write_x509_svid_on_update_with_dummy_writer() {
         let svid = get_test_svid();
         let bundle = get_test_bundle();
 
-        let cert_writer = DummyStorage;
+        let cert_writer = MemorySink::new();
         let result = write_x509_svid_on_update(&svid, &bundle, &cert_writer);
         assert!(result.is_ok());
     }
 
+    fn total_writes(sink: &MemorySink) -> usize {
+        let contents = sink.contents();
+        contents.cert_chain_writes + contents.key_writes + contents.bundle_writes
+    }
+
+    #[test]
+    fn test_write_changed_x509_svid_skips_identical_content() {
+        let svid = get_test_svid();
+        let bundle = get_test_bundle();
+        let cert_writer = MemorySink::new();
+        let mut written = WrittenDigests::default();
+
+        let changed = write_changed_x509_svid(&svid, &bundle, &cert_writer, &mut written).unwrap();
+        assert!(changed);
+        assert_eq!(total_writes(&cert_writer), 3);
+
+        let changed = write_changed_x509_svid(&svid, &bundle, &cert_writer, &mut written).unwrap();
+        assert!(!changed);
+        assert_eq!(total_writes(&cert_writer), 3);
+    }
+
+    #[test]
+    fn test_write_changed_x509_svid_writes_only_changed_files() {
+        let svid = get_test_svid();
+        let bundle = get_test_bundle();
+        let cert_writer = MemorySink::new();
+        let mut written = WrittenDigests::default();
+        write_changed_x509_svid(&svid, &bundle, &cert_writer, &mut written).unwrap();
+
+        // Pretend the bundle on disk differs from the one in the update
+        written.bundle = Some(0);
+        let changed = write_changed_x509_svid(&svid, &bundle, &cert_writer, &mut written).unwrap();
+
+        assert!(changed);
+        let contents = cert_writer.contents();
+        assert_eq!(contents.bundle_writes, 2);
+        assert_eq!(contents.cert_chain_writes, 1);
+        assert_eq!(contents.key_writes, 1);
+    }
+
     #[test]
     fn test_pem_encoding_logic() {
         let data = vec![0x30, 0x01, 0x01];
@@ -0,0 +1,64 @@
+//! Integration tests for overriding the configured agent address with `--agent-address`.
+
+use clap::Parser;
+use spiffe_helper::cli::Args;
+use tempfile::TempDir;
+
+const CONFIG: &str = r#"
+    agent_address = "unix:///tmp/config.sock"
+    cert_dir = "/tmp/certs"
+"#;
+
+fn args_for(config_path: &std::path::Path, extra: &[&str]) -> Result<Args, clap::Error> {
+    let mut argv = vec!["spiffe-helper", "--config", config_path.to_str().unwrap()];
+    argv.extend_from_slice(extra);
+    Args::try_parse_from(argv)
+}
+
+/// Test that `--agent-address`, and its `-s` and `--socket` forms, override the config file.
+#[test]
+fn test_agent_address_flag_overrides_config() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("helper.conf");
+    std::fs::write(&config_path, CONFIG).unwrap();
+
+    for flag in ["--agent-address", "-s", "--socket"] {
+        let config = args_for(&config_path, &[flag, "tcp://127.0.0.1:8081"])
+            .unwrap()
+            .get_operation_config()
+            .unwrap();
+
+        assert_eq!(
+            config.agent_address.as_deref(),
+            Some("tcp://127.0.0.1:8081"),
+            "{flag}"
+        );
+    }
+
+    let config = args_for(&config_path, &[])
+        .unwrap()
+        .get_operation_config()
+        .unwrap();
+    assert_eq!(
+        config.agent_address.as_deref(),
+        Some("unix:///tmp/config.sock")
+    );
+}
+
+/// Test that an address without a `unix://` or `tcp://` scheme is rejected.
+#[test]
+fn test_agent_address_flag_rejects_invalid_scheme() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("helper.conf");
+    std::fs::write(&config_path, CONFIG).unwrap();
+
+    let error = args_for(&config_path, &["--agent-address", "http://127.0.0.1:8081"]).unwrap_err();
+
+    assert_eq!(error.kind(), clap::error::ErrorKind::ValueValidation);
+    assert!(
+        error
+            .to_string()
+            .contains("must start with unix:// or tcp://"),
+        "{error}"
+    );
+}
@@ -0,0 +1,82 @@
+#![cfg(unix)]
+
+use spiffe_helper::cli::Config;
+use spiffe_helper::workload_api;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::UnixListener;
+use tempfile::TempDir;
+
+/// Binds an agent socket in a private directory and opens it to every user.
+fn world_writable_socket(temp_dir: &TempDir) -> (UnixListener, String) {
+    fs::set_permissions(temp_dir.path(), fs::Permissions::from_mode(0o700)).unwrap();
+    let socket_path = temp_dir.path().join("agent.sock");
+    let listener = UnixListener::bind(&socket_path).unwrap();
+    fs::set_permissions(&socket_path, fs::Permissions::from_mode(0o777)).unwrap();
+    (listener, format!("unix://{}", socket_path.display()))
+}
+
+/// Test that a world-writable agent socket is reported, and only warned about by default.
+#[test]
+fn test_world_writable_agent_socket_is_reported() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let (_listener, agent_address) = world_writable_socket(&temp_dir);
+
+    let problems = workload_api::agent_socket_problems(&agent_address, None);
+    assert_eq!(problems.len(), 1, "{problems:?}");
+    assert!(problems[0].contains("writable by any user"), "{problems:?}");
+
+    let config = Config {
+        agent_address: Some(agent_address),
+        ..Default::default()
+    };
+    workload_api::check_agent_socket(&config).expect("Warnings must not fail the check");
+}
+
+/// Test that `strict_permissions` turns a world-writable agent socket into an error, and that
+/// `check_agent_socket = false` skips the check.
+#[test]
+fn test_world_writable_agent_socket_fails_under_strict_permissions() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let (_listener, agent_address) = world_writable_socket(&temp_dir);
+
+    let mut config = Config {
+        agent_address: Some(agent_address),
+        strict_permissions: Some(true),
+        ..Default::default()
+    };
+    let error = workload_api::check_agent_socket(&config).unwrap_err();
+    assert!(
+        error.to_string().contains("writable by any user"),
+        "Unexpected error: {error:#}"
+    );
+
+    config.check_agent_socket = Some(false);
+    workload_api::check_agent_socket(&config).unwrap();
+}
+
+/// Test that an owner other than `agent_socket_owner_uid` is reported, and that addresses
+/// without a socket path are never checked.
+#[test]
+fn test_agent_socket_owner_and_unchecked_addresses() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let _listener = UnixListener::bind(&socket_path).unwrap();
+    let agent_address = format!("unix://{}", socket_path.display());
+
+    let owner = fs::metadata(&socket_path).map(|m| {
+        use std::os::unix::fs::MetadataExt;
+        m.uid()
+    });
+    let problems = workload_api::agent_socket_problems(&agent_address, Some(owner.unwrap() + 1));
+    assert!(
+        problems
+            .iter()
+            .any(|p| p.contains("agent_socket_owner_uid")),
+        "{problems:?}"
+    );
+
+    assert!(workload_api::agent_socket_problems("tcp://127.0.0.1:8081", None).is_empty());
+    let missing = format!("unix://{}", temp_dir.path().join("missing.sock").display());
+    assert!(workload_api::agent_socket_problems(&missing, None).is_empty());
+}
@@ -0,0 +1,121 @@
+//! Integration tests for detaching the daemon with `--background`.
+#![cfg(unix)]
+
+use spiffe_helper::signal;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+use tempfile::TempDir;
+
+mod common;
+
+const DEFAULT_ROTATION_SECONDS: u32 = 300;
+
+/// Polls until `path` exists, for up to five seconds.
+async fn wait_for_file(path: &Path) -> bool {
+    for _ in 0..50 {
+        if path.exists() {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    false
+}
+
+/// Test that `--background` returns immediately, writes the daemon's PID file, keeps writing
+/// certificates after detaching, and shuts down on SIGTERM.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_background_writes_pid_file_and_runs_detached() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+    let pid_file = temp_dir.path().join("spiffe-helper.pid");
+    let log_file = temp_dir.path().join("spiffe-helper.log");
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let config_path = temp_dir.path().join("helper.conf");
+    std::fs::write(
+        &config_path,
+        format!(
+            "agent_address = \"unix://{}\"\ncert_dir = \"{}\"\n\
+             background_pid_file = \"{}\"\nbackground_log_file = \"{}\"\n",
+            socket_path.display(),
+            cert_dir.display(),
+            pid_file.display(),
+            log_file.display()
+        ),
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_spiffe-helper"))
+        .arg("--config")
+        .arg(&config_path)
+        .arg("--background")
+        .env_remove("SPIFFE_ENDPOINT_SOCKET")
+        .output()
+        .expect("Failed to run spiffe-helper");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    assert!(wait_for_file(&pid_file).await, "PID file was not written");
+    let pid: i32 = std::fs::read_to_string(&pid_file)
+        .unwrap()
+        .trim()
+        .parse()
+        .expect("PID file should contain a PID");
+
+    assert!(
+        wait_for_file(&cert_dir.join("svid.pem")).await,
+        "Backgrounded daemon did not write the SVID"
+    );
+
+    signal::send_signal(pid, signal::Signal::SIGTERM).expect("Failed to signal the daemon");
+    let mut removed = false;
+    for _ in 0..50 {
+        if !pid_file.exists() {
+            removed = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    server_handle.abort();
+
+    assert!(removed, "Daemon did not remove its PID file on shutdown");
+    let log = std::fs::read_to_string(&log_file).unwrap();
+    assert!(log.contains("Daemon shutdown complete"), "{log}");
+}
+
+/// Test that `--background` outside daemon mode is rejected with the config error code.
+#[test]
+fn test_background_rejected_in_oneshot_mode() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("helper.conf");
+    std::fs::write(
+        &config_path,
+        format!(
+            "agent_address = \"unix://{}\"\ncert_dir = \"{}\"\ndaemon_mode = false\n",
+            temp_dir.path().join("agent.sock").display(),
+            temp_dir.path().join("certs").display()
+        ),
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_spiffe-helper"))
+        .arg("--config")
+        .arg(&config_path)
+        .arg("--background")
+        .env_remove("SPIFFE_ENDPOINT_SOCKET")
+        .output()
+        .expect("Failed to run spiffe-helper");
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("only valid in daemon mode"));
+}
@@ -0,0 +1,256 @@
+use spiffe_helper::cli::Config;
+use spiffe_helper::daemon;
+use spiffe_helper::{oneshot, workload_api};
+use spire_agent_mock::svid::SvidGenerator;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tempfile::TempDir;
+
+mod common;
+
+const LEAF_ROTATION_SECONDS: u32 = 300;
+
+/// Test that with `include_federated_domains`, a federated trust domain without authorities
+/// is skipped while the local and other federated bundles are still written.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_federated_domain_without_authorities_is_skipped() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+
+    let service = common::mock_agent_service(LEAF_ROTATION_SECONDS)
+        .with_static_svid()
+        .with_federated_trust_domain("federated.org")
+        .with_empty_federated_trust_domain("pending.org");
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::serve_mock_agent(&socket_path_clone, service).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        daemon_mode: Some(false),
+        include_federated_domains: Some(true),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    oneshot::run(source, config)
+        .await
+        .expect("A federated domain without authorities should not fail the write");
+
+    let bundle = fs::read_to_string(cert_dir.join("svid_bundle.pem")).unwrap();
+    assert_eq!(
+        pem::parse_many(&bundle).unwrap().len(),
+        2,
+        "Bundle should hold the local CA and the federated.org CA"
+    );
+
+    server_handle.abort();
+}
+
+/// Test that the certificates of `extra_ca_file` are appended to the agent's bundle, with a
+/// certificate listed twice in the file written once.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_extra_ca_file_is_merged_into_bundle() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+
+    let service = common::mock_agent_service(LEAF_ROTATION_SECONDS).with_static_svid();
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::serve_mock_agent(&socket_path_clone, service).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let static_ca = SvidGenerator::generate_ca_der("legacy.example.com");
+    let static_ca_pem = pem::encode(&pem::Pem {
+        tag: "CERTIFICATE".to_string(),
+        contents: static_ca.clone(),
+    });
+    let extra_ca_path = temp_dir.path().join("legacy-ca.pem");
+    fs::write(&extra_ca_path, static_ca_pem.repeat(2)).unwrap();
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        daemon_mode: Some(false),
+        extra_ca_file: Some(extra_ca_path.to_str().unwrap().to_string()),
+        ..Default::default()
+    };
+    config.validate().expect("extra_ca_file should be accepted");
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    oneshot::run(source, config)
+        .await
+        .expect("Failed to write the merged bundle");
+    server_handle.abort();
+
+    let bundle = fs::read_to_string(cert_dir.join("svid_bundle.pem")).unwrap();
+    let authorities: Vec<_> = pem::parse_many(&bundle)
+        .unwrap()
+        .into_iter()
+        .map(|block| block.contents)
+        .collect();
+    assert_eq!(
+        authorities.len(),
+        2,
+        "Bundle should hold the agent's CA and the static CA once"
+    );
+    assert_ne!(authorities[0], static_ca, "The agent's CA is missing");
+    assert_eq!(authorities[1], static_ca, "The static CA is missing");
+}
+
+/// Test that `primary_bundle_trust_domain` writes a federated domain's authorities as the
+/// primary bundle, followed by the SVID's own trust domain with `include_federated_domains`,
+/// and that a domain without a bundle fails the write.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_primary_bundle_trust_domain_selects_federated_bundle() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+
+    let service = common::mock_agent_service(LEAF_ROTATION_SECONDS)
+        .with_static_svid()
+        .with_federated_trust_domain("federated.org");
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::serve_mock_agent(&socket_path_clone, service).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+    let agent_address = format!("unix://{}", socket_path.display());
+
+    let write_bundle = |name: &str, primary: Option<&str>, include_federated: bool| {
+        let cert_dir = temp_dir.path().join(name);
+        let config = Config {
+            agent_address: Some(agent_address.clone()),
+            cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+            daemon_mode: Some(false),
+            primary_bundle_trust_domain: primary.map(str::to_string),
+            include_federated_domains: Some(include_federated),
+            ..Default::default()
+        };
+        async move {
+            let source = workload_api::create_x509_source(config.agent_address.as_ref().unwrap())
+                .await
+                .expect("Failed to create X509Source");
+            oneshot::run(source, config).await.map(|()| {
+                let bundle = fs::read_to_string(cert_dir.join("svid_bundle.pem")).unwrap();
+                pem::parse_many(bundle)
+                    .unwrap()
+                    .into_iter()
+                    .map(|block| block.contents)
+                    .collect::<Vec<_>>()
+            })
+        }
+    };
+
+    let own = write_bundle("own", None, false).await.unwrap();
+    let federated = write_bundle("federated", Some("federated.org"), false)
+        .await
+        .unwrap();
+    let combined = write_bundle("combined", Some("federated.org"), true)
+        .await
+        .unwrap();
+    let missing = write_bundle("missing", Some("missing.org"), false).await;
+
+    server_handle.abort();
+
+    assert_eq!(own.len(), 1);
+    assert_eq!(federated.len(), 1);
+    assert_ne!(own, federated, "Primary bundle still holds the own domain");
+    assert_eq!(combined, [federated[0].clone(), own[0].clone()]);
+    let error = missing.expect_err("A primary domain without a bundle should fail the write");
+    assert!(
+        format!("{error:#}").contains("primary_bundle_trust_domain missing.org"),
+        "{error:#}"
+    );
+}
+
+/// Test that the bundle file is rewritten when the agent rotates its authorities
+/// while the leaf SVID stays the same.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_daemon_rewrites_bundle_on_authority_rotation() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+
+    // A static leaf keeps every Workload API stream on the same SVID, so only the bundle changes
+    let service = common::mock_agent_service(LEAF_ROTATION_SECONDS)
+        .with_static_svid()
+        .with_bundle_rotation(Duration::from_secs(1))
+        .with_federated_trust_domain("federated.org");
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::serve_mock_agent(&socket_path_clone, service).await;
+    });
+
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        daemon_mode: Some(true),
+        svid_file_name: Some("svid.pem".to_string()),
+        svid_bundle_file_name: Some("bundle.pem".to_string()),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let daemon_handle = tokio::spawn(async move { daemon::run(source, config).await });
+
+    let cert_path = cert_dir.join("svid.pem");
+    let bundle_path = cert_dir.join("bundle.pem");
+
+    let initial_bundle = wait_for_content(&bundle_path, None).await;
+    let initial_cert = fs::read_to_string(&cert_path).expect("Failed to read certificate");
+
+    let rotated_bundle = wait_for_content(&bundle_path, Some(&initial_bundle)).await;
+    let current_cert = fs::read_to_string(&cert_path).expect("Failed to read certificate");
+
+    assert_ne!(initial_bundle, rotated_bundle, "Bundle did not rotate");
+    assert_eq!(
+        initial_cert, current_cert,
+        "Leaf SVID changed during a bundle-only rotation"
+    );
+    assert_eq!(
+        pem::parse_many(&rotated_bundle).unwrap().len(),
+        2,
+        "Bundle should hold the signing CA and the rotating authority"
+    );
+
+    daemon_handle.abort();
+    server_handle.abort();
+}
+
+/// Wait for a file to exist with content different from `previous` (with timeout).
+async fn wait_for_content(path: &Path, previous: Option<&str>) -> String {
+    let max_attempts = 50;
+    let delay = Duration::from_millis(100);
+
+    for _ in 0..max_attempts {
+        if let Ok(content) = fs::read_to_string(path) {
+            if !content.is_empty() && previous != Some(content.as_str()) {
+                return content;
+            }
+        }
+        tokio::time::sleep(delay).await;
+    }
+
+    panic!(
+        "File content did not change within timeout: {}",
+        path.display()
+    );
+}
@@ -0,0 +1,105 @@
+//! Integration tests for `bundle_only`, which writes the trust bundle without an SVID.
+
+use spiffe_helper::bundle_only;
+use spiffe_helper::cli::Config;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tempfile::TempDir;
+
+mod common;
+
+const DEFAULT_ROTATION_SECONDS: u32 = 300;
+
+/// Test that bundle-only mode writes the trust bundle, rewrites it when the agent rotates its
+/// authorities, and never writes an SVID or key.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_bundle_only_rewrites_bundle_on_rotation() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+
+    let service = common::mock_agent_service(DEFAULT_ROTATION_SECONDS)
+        .with_bundle_rotation(Duration::from_secs(1));
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::serve_mock_agent(&socket_path_clone, service).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let config = Config {
+        agent_address: Some(format!("unix://{}", socket_path.display())),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        daemon_mode: Some(true),
+        bundle_only: Some(true),
+        primary_bundle_trust_domain: Some("example.org".to_string()),
+        ..Default::default()
+    };
+    let handle = tokio::spawn(bundle_only::run(config));
+
+    let bundle_path = cert_dir.join("svid_bundle.pem");
+    let initial_bundle = wait_for_content(&bundle_path, None).await;
+    let rotated_bundle = wait_for_content(&bundle_path, Some(&initial_bundle)).await;
+
+    let finished = handle.is_finished();
+    handle.abort();
+    server_handle.abort();
+
+    assert!(!finished, "Bundle-only mode stopped");
+    assert_ne!(initial_bundle, rotated_bundle, "Bundle did not rotate");
+    assert!(!cert_dir.join("svid.pem").exists(), "An SVID was written");
+    assert!(!cert_dir.join("svid_key.pem").exists(), "A key was written");
+}
+
+/// Test that outside daemon mode, bundle-only mode writes the bundle once and returns.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_bundle_only_oneshot_writes_bundle() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let config = Config {
+        agent_address: Some(format!("unix://{}", socket_path.display())),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        daemon_mode: Some(false),
+        bundle_only: Some(true),
+        primary_bundle_trust_domain: Some("example.org".to_string()),
+        ..Default::default()
+    };
+    let result = tokio::time::timeout(Duration::from_secs(10), bundle_only::run(config))
+        .await
+        .expect("Bundle-only mode did not return");
+    server_handle.abort();
+
+    assert!(
+        result.is_ok(),
+        "Bundle-only mode failed: {:?}",
+        result.err()
+    );
+    let bundle = fs::read_to_string(cert_dir.join("svid_bundle.pem")).expect("No bundle written");
+    assert_eq!(pem::parse_many(&bundle).unwrap().len(), 1);
+    assert!(!cert_dir.join("svid.pem").exists(), "An SVID was written");
+}
+
+/// Wait for a file to exist with content different from `previous` (with timeout).
+async fn wait_for_content(path: &Path, previous: Option<&str>) -> String {
+    for _ in 0..50 {
+        if let Ok(content) = fs::read_to_string(path) {
+            if !content.is_empty() && previous != Some(content.as_str()) {
+                return content;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    panic!(
+        "File content did not change within timeout: {}",
+        path.display()
+    );
+}
@@ -0,0 +1,95 @@
+//! Integration tests for the `check-agent` operation.
+
+use std::path::Path;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+mod common;
+
+const DEFAULT_ROTATION_SECONDS: u32 = 300;
+
+fn write_config(path: &Path, socket_path: &Path, cert_dir: &Path, extra: &str) {
+    std::fs::write(
+        path,
+        format!(
+            "agent_address = \"unix://{}\"\ncert_dir = \"{}\"\n{extra}",
+            socket_path.display(),
+            cert_dir.display()
+        ),
+    )
+    .unwrap();
+}
+
+async fn run_check_agent(config_path: &Path) -> Output {
+    let mut command =
+        tokio::process::Command::from(Command::new(env!("CARGO_BIN_EXE_spiffe-helper")));
+    command
+        .arg("check-agent")
+        .arg("--config")
+        .arg(config_path)
+        .env_remove("SPIFFE_ENDPOINT_SOCKET")
+        .output()
+        .await
+        .expect("Failed to run spiffe-helper")
+}
+
+/// Test that `check-agent` prints the SVID's SPIFFE ID and expiry, exits 0, and writes nothing.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_check_agent_prints_svid_without_writing() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+    let config_path = temp_dir.path().join("helper.conf");
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+    write_config(&config_path, &socket_path, &cert_dir, "");
+
+    let output = run_check_agent(&config_path).await;
+    server_handle.abort();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        stdout.contains("SPIFFE ID: spiffe://example.org/"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("Expires at: "), "{stdout}");
+    assert!(stdout.contains("Time to expiry: "), "{stdout}");
+    assert!(!cert_dir.exists(), "check-agent must not create cert_dir");
+}
+
+/// Test that an SVID outside `expected_trust_domain` fails the check with the connect exit code.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_check_agent_fails_on_unexpected_trust_domain() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+    let config_path = temp_dir.path().join("helper.conf");
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+    write_config(
+        &config_path,
+        &socket_path,
+        &cert_dir,
+        "expected_trust_domain = \"other.org\"\n",
+    );
+
+    let output = run_check_agent(&config_path).await;
+    server_handle.abort();
+
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("expected_trust_domain is other.org"));
+    assert!(!cert_dir.exists());
+}
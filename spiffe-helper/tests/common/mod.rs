@@ -1,12 +1,18 @@
+// Not every integration test binary uses every helper.
+#![allow(dead_code)]
+
 use spire_agent_mock::server::{MockWorkloadApi, SpiffeWorkloadApiServer};
 use spire_agent_mock::svid::SvidConfig;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tokio::net::UnixListener;
 use tokio_stream::wrappers::UnixListenerStream;
 use tonic::transport::Server;
 
 const VALIDITY_LEEWAY_SECONDS: i64 = 15;
+pub const SVID_TTL_SECONDS: u32 = 300;
+pub const JWT_TTL_SECONDS: u32 = 300;
 
 /// Wait for the socket file to exist (with timeout).
 pub async fn assert_socket_ready(socket_path: &Path) {
@@ -132,8 +138,25 @@ pub fn assert_x509_key(path: &Path) -> Vec<u8> {
     key_pem.contents
 }
 
-/// Start the mock SPIRE agent on the given socket path.
+/// Start the mock SPIRE agent on the given socket path, pushing a freshly signed
+/// X.509 SVID every `rotation_seconds`.
 pub async fn start_mock_agent(socket_path: &PathBuf, rotation_seconds: u32) {
+    serve_mock_agent(socket_path, mock_agent_service(rotation_seconds)).await;
+}
+
+/// Build the mock Workload API used by the tests, rotating the leaf every `rotation_seconds`.
+pub fn mock_agent_service(rotation_seconds: u32) -> MockWorkloadApi {
+    let config = SvidConfig {
+        trust_domain: "example.org".to_string(),
+        workload_path: "/test/workload".to_string(),
+        ttl_seconds: SVID_TTL_SECONDS,
+        jwt_ttl_seconds: JWT_TTL_SECONDS,
+    };
+    MockWorkloadApi::with_config_and_rotation(config, Duration::from_secs(rotation_seconds.into()))
+}
+
+/// Serve the given mock Workload API on the given socket path.
+pub async fn serve_mock_agent(socket_path: &PathBuf, service: MockWorkloadApi) {
     if socket_path.exists() {
         fs::remove_file(socket_path).unwrap();
     }
@@ -143,20 +166,53 @@ pub async fn start_mock_agent(socket_path: &PathBuf, rotation_seconds: u32) {
     }
 
     let uds = UnixListener::bind(socket_path).expect("Failed to bind to socket");
-    let uds_stream = UnixListenerStream::new(uds);
-
-    let config = SvidConfig {
-        trust_domain: "example.org".to_string(),
-        workload_path: "/test/workload".to_string(),
-        ttl_seconds: rotation_seconds,
-    };
-    let service = MockWorkloadApi::with_config(config);
 
     println!("starting mock agent at {:?}", socket_path);
 
+    serve_mock_agent_on(uds, service).await;
+}
+
+/// Serve the given mock Workload API on an already bound listener.
+pub async fn serve_mock_agent_on(uds: UnixListener, service: MockWorkloadApi) {
+    let uds_stream = UnixListenerStream::new(uds);
+
     Server::builder()
         .add_service(SpiffeWorkloadApiServer::new(service))
         .serve_with_incoming(uds_stream)
         .await
         .unwrap();
 }
+
+/// Reserve a free local port for the health check listener.
+pub fn free_port() -> u16 {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+/// Issue a plain HTTP GET and return the response status code and body.
+pub async fn http_get(port: u16, path: &str) -> (u16, String) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut stream = tokio::net::TcpStream::connect(("127.0.0.1", port))
+        .await
+        .expect("Failed to connect to health server");
+    stream
+        .write_all(format!("GET {path} HTTP/1.0\r\nHost: localhost\r\n\r\n").as_bytes())
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    let status = response
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .expect("Malformed HTTP response");
+    let body = response
+        .split_once("\r\n\r\n")
+        .map(|(_, body)| body.to_string())
+        .unwrap_or_default();
+    (status, body)
+}
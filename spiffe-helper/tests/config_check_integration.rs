@@ -0,0 +1,101 @@
+//! Integration tests for validating a config file standalone with `--config-check`.
+
+use std::path::Path;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+/// Run `spiffe-helper --config-check` against a config that manages `cmd`.
+fn config_check(temp_dir: &Path, cmd: &str) -> Output {
+    config_check_with(temp_dir, &format!("cmd = \"{cmd}\"\n"))
+}
+
+/// Run `spiffe-helper --config-check` against a config with `extra` appended.
+fn config_check_with(temp_dir: &Path, extra: &str) -> Output {
+    let config_path = temp_dir.join("helper.conf");
+    std::fs::write(
+        &config_path,
+        format!(
+            "agent_address = \"unix://{}\"\ncert_dir = \"{}\"\n{extra}",
+            temp_dir.join("agent.sock").display(),
+            temp_dir.join("certs").display()
+        ),
+    )
+    .unwrap();
+
+    Command::new(env!("CARGO_BIN_EXE_spiffe-helper"))
+        .arg("--config")
+        .arg(&config_path)
+        .arg("--config-check")
+        .env_remove("SPIFFE_ENDPOINT_SOCKET")
+        .output()
+        .expect("Failed to run spiffe-helper")
+}
+
+/// Test that a config with an executable `cmd` passes without an agent running.
+#[test]
+fn test_config_check_accepts_executable_cmd() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+
+    let output = config_check(temp_dir.path(), "/bin/sh");
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Configuration OK"));
+    // Checking the config must not create anything
+    assert!(!temp_dir.path().join("certs").exists());
+}
+
+/// Test that a missing `cmd` fails the check with the config error code.
+#[test]
+fn test_config_check_rejects_missing_cmd() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let missing = temp_dir.path().join("missing-app");
+
+    let output = config_check(temp_dir.path(), missing.to_str().unwrap());
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("does not exist"));
+}
+
+/// Test that a `cmd` without the executable bit fails the check.
+#[cfg(unix)]
+#[test]
+fn test_config_check_rejects_non_executable_cmd() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let script = temp_dir.path().join("app.sh");
+    std::fs::write(&script, "#!/bin/sh\n").unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+    let output = config_check(temp_dir.path(), script.to_str().unwrap());
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("is not an executable file"));
+}
+
+/// Test that a world-readable `key_file_mode` passes with a warning unless `strict_permissions`.
+#[test]
+fn test_config_check_warns_on_readable_key_mode() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+
+    let output = config_check_with(temp_dir.path(), "key_file_mode = \"0644\"\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    assert!(
+        stderr
+            .contains("Warning: key_file_mode 0644 lets group or other users read the private key"),
+        "{stderr}"
+    );
+
+    let output = config_check_with(
+        temp_dir.path(),
+        "key_file_mode = \"0644\"\nstrict_permissions = true\n",
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(2), "{stderr}");
+    assert!(stderr.contains("unset strict_permissions"), "{stderr}");
+}
@@ -249,3 +249,58 @@ fn test_config_escaped_quotes_in_hcl() {
     // The shell argument should contain escaped quotes
     assert!(args[1].contains("echo"));
 }
+
+/// Test that heredoc `cmd_args` with single and double quotes are taken verbatim.
+#[test]
+fn test_config_heredoc_cmd_args() {
+    let config_content = r#"
+        agent_address = "unix:///run/spire/sockets/agent.sock"
+        cert_dir = "/tmp/certs"
+        cmd = <<EOT
+/bin/sh
+EOT
+        cmd_args = <<-EOT
+            -c 'echo "reloading $HOSTNAME" && kill -HUP "$(cat /run/app.pid)"'
+            EOT
+        renew_signal = <<EOT
+SIGHUP
+EOT
+    "#;
+
+    let file = create_temp_config(config_content);
+    let config = parse_hcl_config(file.path()).unwrap();
+
+    assert_eq!(config.cmd.as_deref(), Some("/bin/sh"));
+    assert_eq!(config.renew_signal.as_deref(), Some("SIGHUP"));
+    let args = parse_cmd_args(config.cmd_args.as_ref().unwrap()).unwrap();
+    assert_eq!(
+        args,
+        vec![
+            "-c",
+            r#"echo "reloading $HOSTNAME" && kill -HUP "$(cat /run/app.pid)""#
+        ]
+    );
+}
+
+/// Test that a multi-line heredoc `cmd_args` splits across lines like whitespace.
+#[test]
+fn test_config_multiline_heredoc_cmd_args() {
+    let config_content = r#"
+        agent_address = "unix:///run/spire/sockets/agent.sock"
+        cert_dir = "/tmp/certs"
+        cmd = "/usr/bin/nginx"
+        cmd_args = <<-EOT
+            -c /etc/nginx/nginx.conf
+            -g "daemon off;"
+            EOT
+    "#;
+
+    let file = create_temp_config(config_content);
+    let config = parse_hcl_config(file.path()).unwrap();
+
+    let args = parse_cmd_args(config.cmd_args.as_ref().unwrap()).unwrap();
+    assert_eq!(
+        args,
+        vec!["-c", "/etc/nginx/nginx.conf", "-g", "daemon off;"]
+    );
+}
@@ -0,0 +1,75 @@
+//! Integration tests for reading the config from standard input with `--config-stdin`.
+
+use clap::Parser;
+use spiffe_helper::cli::Args;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const CONFIG: &str = r#"
+    agent_address = "unix:///tmp/agent.sock"
+    cert_dir = "/tmp/certs"
+"#;
+
+/// Test that `--config-stdin` parses the config from the given reader instead of a file.
+#[test]
+fn test_config_stdin_parses_reader() {
+    let args = Args::try_parse_from(["spiffe-helper", "--config-stdin"]).unwrap();
+
+    let config = args.get_operation_config_from(CONFIG.as_bytes()).unwrap();
+
+    assert_eq!(
+        config.agent_address.as_deref(),
+        Some("unix:///tmp/agent.sock")
+    );
+    assert_eq!(args.config_name(), "<stdin>");
+}
+
+/// Test that a syntax error on stdin is reported against `<stdin>`.
+#[test]
+fn test_config_stdin_reports_parse_error() {
+    let args = Args::try_parse_from(["spiffe-helper", "--config-stdin"]).unwrap();
+
+    let error = args
+        .get_operation_config_from("agent_address = \n".as_bytes())
+        .unwrap_err();
+
+    let message = format!("{error:#}");
+    assert!(message.contains("<stdin>"), "{message}");
+}
+
+/// Test that `--config-stdin` cannot be combined with `--config`.
+#[test]
+fn test_config_stdin_conflicts_with_config() {
+    let result =
+        Args::try_parse_from(["spiffe-helper", "--config-stdin", "--config", "helper.conf"]);
+
+    assert!(result.is_err());
+}
+
+/// Test that the binary reads a config piped to it.
+#[test]
+fn test_config_stdin_reads_piped_config() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_spiffe-helper"))
+        .arg("--config-stdin")
+        .arg("--config-check")
+        .env_remove("SPIFFE_ENDPOINT_SOCKET")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to run spiffe-helper");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(CONFIG.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Configuration OK: <stdin>"));
+}
@@ -0,0 +1,75 @@
+#![cfg(unix)]
+
+use spiffe_helper::workload_api::{self, ConnectTimeout};
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+use tokio::net::UnixListener;
+
+/// Test that connecting to an agent that accepts connections but never replies fails with a
+/// `ConnectTimeout` once the timeout passes.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_connect_timeout_fires_for_unresponsive_agent() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let listener = UnixListener::bind(&socket_path).unwrap();
+    // Accept every connection and hold it open without ever answering
+    let server_handle = tokio::spawn(async move {
+        let mut connections = Vec::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            connections.push(stream);
+        }
+    });
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let started = Instant::now();
+    let result =
+        workload_api::create_x509_source_with_timeout(&agent_address, Some(Duration::from_secs(1)))
+            .await;
+    let elapsed = started.elapsed();
+    server_handle.abort();
+
+    let error = result
+        .err()
+        .expect("Connecting to a silent agent succeeded");
+    let timeout = error
+        .downcast_ref::<ConnectTimeout>()
+        .unwrap_or_else(|| panic!("Expected a connect timeout, got: {error:#}"));
+    assert_eq!(timeout.timeout, Duration::from_secs(1));
+    assert!(error.to_string().contains("Timed out after 1s"), "{error}");
+    assert!(
+        elapsed < Duration::from_secs(10),
+        "Timeout took {elapsed:?}"
+    );
+}
+
+/// Test that fetching every SVID from an agent that never replies, as the daemon does for
+/// `write_all_svids`, fails with a `ConnectTimeout` instead of waiting indefinitely.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_fetch_x509_context_times_out_for_unresponsive_agent() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let listener = UnixListener::bind(&socket_path).unwrap();
+    let server_handle = tokio::spawn(async move {
+        let mut connections = Vec::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            connections.push(stream);
+        }
+    });
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let result = tokio::time::timeout(
+        Duration::from_secs(10),
+        workload_api::fetch_x509_context(&agent_address, Some(Duration::from_secs(1))),
+    )
+    .await
+    .expect("Fetching from a silent agent did not time out");
+    server_handle.abort();
+
+    let error = result
+        .err()
+        .expect("Fetching from a silent agent succeeded");
+    assert!(
+        error.downcast_ref::<ConnectTimeout>().is_some(),
+        "Expected a connect timeout, got: {error:#}"
+    );
+}
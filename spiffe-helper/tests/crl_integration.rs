@@ -0,0 +1,86 @@
+use spiffe_helper::cli::Config;
+use spiffe_helper::{oneshot, workload_api};
+use std::path::Path;
+use tempfile::TempDir;
+
+mod common;
+
+const DEFAULT_ROTATION_SECONDS: u32 = 300;
+
+/// Runs one-shot mode against a mock agent that sends `crls`, writing to `cert_dir`.
+async fn run_oneshot_with_crls(temp_dir: &Path, crls: Vec<Vec<u8>>, write_empty_crl: bool) {
+    let socket_path = temp_dir.join("agent.sock");
+    let cert_dir = temp_dir.join("certs");
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        let service = crls.into_iter().fold(
+            common::mock_agent_service(DEFAULT_ROTATION_SECONDS),
+            spire_agent_mock::server::MockWorkloadApi::with_crl,
+        );
+        common::serve_mock_agent(&socket_path_clone, service).await;
+    });
+
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(cert_dir.display().to_string()),
+        daemon_mode: Some(false),
+        svid_file_name: Some("svid.pem".to_string()),
+        svid_key_file_name: Some("svid_key.pem".to_string()),
+        crl_file_name: Some("ca.crl".to_string()),
+        write_empty_crl: Some(write_empty_crl),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let result = oneshot::run(source, config).await;
+    assert!(result.is_ok(), "One-shot mode failed: {:?}", result.err());
+
+    server_handle.abort();
+}
+
+/// Test that the CRLs sent by the agent are written to `crl_file_name` as PEM, in order.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_oneshot_writes_crls_from_agent() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let crls = vec![b"first-crl".to_vec(), b"second-crl".to_vec()];
+
+    run_oneshot_with_crls(temp_dir.path(), crls.clone(), true).await;
+
+    let contents = std::fs::read_to_string(temp_dir.path().join("certs").join("ca.crl"))
+        .expect("CRL file should be written");
+    let parsed = pem::parse_many(&contents).expect("CRL file should be PEM");
+    assert_eq!(parsed.len(), 2);
+    for (pem, der) in parsed.iter().zip(&crls) {
+        assert_eq!(pem.tag, "X509 CRL");
+        assert_eq!(&pem.contents, der);
+    }
+}
+
+/// Test that an agent without CRLs produces an empty CRL file by default.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_oneshot_writes_empty_crl_file() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+
+    run_oneshot_with_crls(temp_dir.path(), Vec::new(), true).await;
+
+    let contents = std::fs::read(temp_dir.path().join("certs").join("ca.crl"))
+        .expect("CRL file should be written");
+    assert!(contents.is_empty());
+}
+
+/// Test that `write_empty_crl = false` leaves the CRL file unwritten when there are no CRLs.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_oneshot_skips_empty_crl_when_disabled() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+
+    run_oneshot_with_crls(temp_dir.path(), Vec::new(), false).await;
+
+    assert!(temp_dir.path().join("certs").join("svid.pem").exists());
+    assert!(!temp_dir.path().join("certs").join("ca.crl").exists());
+}
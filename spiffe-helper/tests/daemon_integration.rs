@@ -4,6 +4,7 @@ use spiffe_helper::signal;
 use spiffe_helper::workload_api;
 use std::fs;
 use std::path::Path;
+use std::time::Instant;
 use tempfile::TempDir;
 
 mod common;
@@ -83,6 +84,156 @@ async fn test_daemon_rotates_certs_then_shutdown() {
     server_handle.abort();
 }
 
+/// Test that the fallback refresh timer re-reads the source when the agent stops pushing updates.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_fallback_refresh_rereads_without_push() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+
+    // The mock never rotates within the test, so no update is pushed after the first
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, 3600).await;
+    });
+
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        daemon_mode: Some(true),
+        write_on_unchanged: Some(true),
+        min_refresh_interval_seconds: Some(1),
+        max_refresh_interval_seconds: Some(1),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let daemon_handle = tokio::spawn(async move { daemon::run(source, config).await });
+
+    let cert_path = cert_dir.join("svid.pem");
+    assert_file_exists(&cert_path).await;
+
+    // The source may still deliver its startup update, so delete twice: after the first
+    // reappearance only a re-read by the timer can bring the certificate back
+    let mut serials = Vec::new();
+    for _ in 0..2 {
+        fs::remove_file(&cert_path).unwrap();
+        assert_file_exists(&cert_path).await;
+        serials.push(common::assert_x509_cert(&cert_path));
+    }
+
+    daemon_handle.abort();
+    server_handle.abort();
+
+    assert_eq!(serials[0], serials[1], "SVID changed without a push");
+}
+
+/// Test that the daemon records each rotation in `renew_sentinel_file`.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rotation_writes_renew_sentinel_file() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+    let sentinel = temp_dir.path().join("rotated");
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, 1).await;
+    });
+
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        daemon_mode: Some(true),
+        renew_sentinel_file: Some(sentinel.to_str().unwrap().to_string()),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let daemon_handle = tokio::spawn(async move { daemon::run(source, config).await });
+
+    assert_file_exists(&sentinel).await;
+    let content = fs::read_to_string(&sentinel).unwrap();
+
+    daemon_handle.abort();
+    server_handle.abort();
+
+    assert!(content.trim().parse::<u64>().is_ok(), "{content}");
+}
+
+/// Test that `renew_signal_delay_ms` holds the rotation notification back after the write.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_renew_signal_delay_elapses_before_notification() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+    let sentinel = temp_dir.path().join("rotated");
+    let delay = std::time::Duration::from_millis(700);
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, 2).await;
+    });
+
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        daemon_mode: Some(true),
+        renew_sentinel_file: Some(sentinel.to_str().unwrap().to_string()),
+        renew_signal_delay_ms: Some(delay.as_millis().try_into().unwrap()),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let svid_path = cert_dir.join("svid.pem");
+    // The last poll that still finds no certificate bounds the write from below
+    let started = Instant::now();
+    let mut before_write = started;
+    let daemon_handle = tokio::spawn(async move { daemon::run(source, config).await });
+
+    loop {
+        let polled_at = Instant::now();
+        if svid_path.exists() {
+            break;
+        }
+        before_write = polled_at;
+        assert!(
+            polled_at.duration_since(started) < std::time::Duration::from_secs(5),
+            "Certificate was not written within timeout"
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    }
+    assert!(!sentinel.exists(), "Notified before the write completed");
+
+    assert_file_exists(&sentinel).await;
+    // The notification happened no later than the poll that found the sentinel
+    let notified = Instant::now();
+
+    daemon_handle.abort();
+    server_handle.abort();
+
+    let elapsed = notified.duration_since(before_write);
+    assert!(
+        elapsed >= delay,
+        "Notified only {elapsed:?} after the write"
+    );
+}
+
 /// Wait for a file to exist (with timeout).
 async fn assert_file_exists(path: &Path) {
     let max_attempts = 50;
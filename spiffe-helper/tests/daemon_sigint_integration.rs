@@ -0,0 +1,77 @@
+//! SIGINT is delivered to the whole test process, so this test lives in its own binary to keep
+//! it from stopping the daemons of other tests.
+
+use spiffe_helper::cli::{Config, HealthChecksConfig};
+use spiffe_helper::{daemon, signal, workload_api};
+use std::time::Duration;
+use tempfile::TempDir;
+
+mod common;
+
+const DEFAULT_ROTATION_SECONDS: u32 = 300;
+
+/// Test that SIGINT shuts the daemon down through the same graceful path as SIGTERM.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_daemon_shuts_down_on_sigint() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+    let port = common::free_port();
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        daemon_mode: Some(true),
+        health_checks: Some(HealthChecksConfig {
+            listener_enabled: true,
+            bind_port: port,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let daemon_handle = tokio::spawn(async move { daemon::run(source, config).await });
+
+    // The signal handlers are registered right after the health server starts listening
+    let mut listening = false;
+    for _ in 0..50 {
+        if tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .is_ok()
+        {
+            listening = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(listening, "Health server did not start");
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    signal::send_signal(
+        std::process::id().try_into().unwrap(),
+        signal::Signal::SIGINT,
+    )
+    .expect("Failed to send SIGINT to daemon");
+
+    let daemon_result = tokio::time::timeout(Duration::from_secs(5), daemon_handle)
+        .await
+        .expect("Daemon did not shut down within timeout");
+    server_handle.abort();
+
+    let run_result = daemon_result.expect("Daemon task panicked");
+    assert!(
+        run_result.is_ok(),
+        "Daemon mode failed: {:?}",
+        run_result.err()
+    );
+}
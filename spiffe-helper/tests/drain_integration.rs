@@ -0,0 +1,120 @@
+//! SIGTERM is delivered to the whole test process, so these checks live in their own binary,
+//! and run one after the other in a single test, to keep them from stopping other daemons.
+
+#![cfg(unix)]
+
+use spiffe_helper::cli::{Config, HealthChecksConfig};
+use spiffe_helper::{daemon, signal, workload_api};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+use tokio::task::JoinHandle;
+
+mod common;
+
+const DEFAULT_ROTATION_SECONDS: u32 = 300;
+const DRAIN_SECONDS: u64 = 3;
+
+/// Start a daemon with `drain_seconds` and wait until its signal handlers are registered.
+async fn start_draining_daemon(
+    agent_address: &str,
+    cert_dir: &Path,
+) -> JoinHandle<anyhow::Result<()>> {
+    let port = common::free_port();
+    let config = Config {
+        agent_address: Some(agent_address.to_string()),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        daemon_mode: Some(true),
+        drain_seconds: Some(DRAIN_SECONDS),
+        health_checks: Some(HealthChecksConfig {
+            listener_enabled: true,
+            bind_port: port,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let daemon_handle = tokio::spawn(async move { daemon::run(source, config).await });
+
+    // The signal handlers are registered right after the health server starts listening
+    let mut listening = false;
+    for _ in 0..50 {
+        if tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .is_ok()
+        {
+            listening = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(listening, "Health server did not start");
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    daemon_handle
+}
+
+fn send_sigterm() {
+    signal::send_signal(
+        std::process::id().try_into().unwrap(),
+        signal::Signal::SIGTERM,
+    )
+    .expect("Failed to send SIGTERM to daemon");
+}
+
+/// Test that SIGTERM shuts the daemon down only after `drain_seconds`, and that a second
+/// SIGTERM during the drain period stops it immediately.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_sigterm_drains_before_shutdown() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+    let agent_address = format!("unix://{}", socket_path.display());
+
+    // A single SIGTERM waits out the drain period
+    let daemon_handle = start_draining_daemon(&agent_address, &cert_dir).await;
+    let signalled = Instant::now();
+    send_sigterm();
+    let drained = tokio::time::timeout(Duration::from_secs(DRAIN_SECONDS + 5), daemon_handle)
+        .await
+        .expect("Daemon did not shut down after the drain period")
+        .expect("Daemon task panicked");
+    let elapsed = signalled.elapsed();
+
+    // A second SIGTERM cuts the drain period short
+    let daemon_handle = start_draining_daemon(&agent_address, &cert_dir).await;
+    let signalled = Instant::now();
+    send_sigterm();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    send_sigterm();
+    let interrupted = tokio::time::timeout(Duration::from_secs(DRAIN_SECONDS + 5), daemon_handle)
+        .await
+        .expect("Daemon did not shut down after the second SIGTERM")
+        .expect("Daemon task panicked");
+    let interrupted_elapsed = signalled.elapsed();
+
+    server_handle.abort();
+
+    assert!(drained.is_ok(), "Daemon mode failed: {:?}", drained.err());
+    assert!(
+        elapsed >= Duration::from_secs(DRAIN_SECONDS),
+        "Daemon shut down after {elapsed:?}, before the drain period"
+    );
+    assert!(
+        interrupted.is_ok(),
+        "Daemon mode failed: {:?}",
+        interrupted.err()
+    );
+    assert!(
+        interrupted_elapsed < Duration::from_secs(DRAIN_SECONDS),
+        "Second SIGTERM did not stop the daemon immediately: {interrupted_elapsed:?}"
+    );
+}
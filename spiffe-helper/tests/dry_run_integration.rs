@@ -0,0 +1,74 @@
+//! Integration tests for `--dry-run`.
+
+use std::process::Stdio;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+
+mod common;
+
+const DEFAULT_ROTATION_SECONDS: u32 = 300;
+
+/// Test that a daemon started with `--dry-run` logs the certificate it would write, with its
+/// SPIFFE ID, but creates no files.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_dry_run_logs_writes_without_creating_files() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let config_path = temp_dir.path().join("helper.conf");
+    std::fs::write(
+        &config_path,
+        format!(
+            "agent_address = \"unix://{}\"\ncert_dir = \"{}\"\ndaemon_mode = true\n",
+            socket_path.display(),
+            cert_dir.display()
+        ),
+    )
+    .unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_spiffe-helper"))
+        .arg("--config")
+        .arg(&config_path)
+        .arg("--dry-run")
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .expect("Failed to run spiffe-helper");
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+
+    let expected = format!(
+        "Dry run: would write {}",
+        cert_dir.join("svid.pem").display()
+    );
+    let logged = tokio::time::timeout(Duration::from_secs(30), async {
+        while let Some(line) = lines.next_line().await.unwrap() {
+            if line.starts_with(&expected) {
+                return Some(line);
+            }
+        }
+        None
+    })
+    .await
+    .expect("Dry run did not log the certificate write");
+    child.kill().await.unwrap();
+    server_handle.abort();
+
+    let logged = logged.expect("spiffe-helper exited without logging the certificate write");
+    assert!(
+        logged.contains("SPIFFE ID spiffe://example.org/test/workload"),
+        "{logged}"
+    );
+    assert!(
+        !cert_dir.exists(),
+        "--dry-run created {}",
+        cert_dir.display()
+    );
+}
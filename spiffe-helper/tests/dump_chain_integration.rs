@@ -0,0 +1,78 @@
+//! Integration tests for the `dump-chain` operation.
+
+use spire_agent_mock::svid::{SvidConfig, SvidGenerator};
+use std::path::Path;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+fn run_dump_chain(cert_dir: &Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_spiffe-helper"))
+        .arg("dump-chain")
+        .arg("--cert-dir")
+        .arg(cert_dir)
+        .output()
+        .expect("Failed to run spiffe-helper")
+}
+
+fn to_pem(der: &[u8]) -> String {
+    pem::encode(&pem::Pem {
+        tag: "CERTIFICATE".to_string(),
+        contents: der.to_vec(),
+    })
+}
+
+/// Test that the certificates written to `cert_dir` are printed with the leaf's SPIFFE ID.
+#[test]
+fn test_dump_chain_prints_written_certificates() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let svid = SvidGenerator::new(SvidConfig {
+        trust_domain: "example.org".to_string(),
+        workload_path: "/workload".to_string(),
+        ..Default::default()
+    })
+    .generate_svid();
+
+    // The mock's chain is the leaf followed by the CA certificate
+    let leaf_der = &svid.cert_chain_der[..svid.cert_chain_der.len() - svid.bundle_der.len()];
+    std::fs::write(
+        temp_dir.path().join("svid.pem"),
+        format!("{}{}", to_pem(leaf_der), to_pem(&svid.bundle_der)),
+    )
+    .unwrap();
+    std::fs::write(
+        temp_dir.path().join("svid_bundle.pem"),
+        to_pem(&svid.bundle_der),
+    )
+    .unwrap();
+
+    let output = run_dump_chain(temp_dir.path());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    assert!(stdout.contains("svid.pem (2 certificates)"), "{stdout}");
+    assert!(
+        stdout.contains("svid_bundle.pem (1 certificates)"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains(&format!("  SPIFFE IDs: {}\n", svid.spiffe_id)),
+        "{stdout}"
+    );
+    assert!(stdout.contains("  CA: true"), "{stdout}");
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("expired"));
+}
+
+/// Test that a directory without certificate files is an error.
+#[test]
+fn test_dump_chain_fails_without_files() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+
+    let output = run_dump_chain(temp_dir.path());
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No certificate files found"));
+}
@@ -0,0 +1,60 @@
+use spiffe_helper::cli::Config;
+use spiffe_helper::oneshot::{self, OneshotError};
+use spiffe_helper::workload_api;
+use tempfile::TempDir;
+
+mod common;
+
+const DEFAULT_ROTATION_SECONDS: u32 = 300;
+
+/// Test that one-shot mode refuses to write an SVID from a trust domain other than
+/// `expected_trust_domain`, and writes it once the domains match.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_oneshot_refuses_unexpected_trust_domain() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+
+    // The mock agent issues SVIDs in example.org
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let mut config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        daemon_mode: Some(false),
+        expected_trust_domain: Some("other.org".to_string()),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let error = oneshot::run(source, config.clone())
+        .await
+        .expect_err("An SVID from the wrong trust domain must not be written");
+
+    assert!(matches!(error, OneshotError::Connect(_)), "{error}");
+    assert!(
+        error
+            .to_string()
+            .contains("trust domain example.org, but expected_trust_domain is other.org"),
+        "{error}"
+    );
+    assert!(!cert_dir.join("svid.pem").exists());
+
+    config.expected_trust_domain = Some("example.org".to_string());
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    oneshot::run(source, config)
+        .await
+        .expect("An SVID from the expected trust domain should be written");
+    common::assert_x509_cert(&cert_dir.join("svid.pem"));
+
+    server_handle.abort();
+}
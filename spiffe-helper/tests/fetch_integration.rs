@@ -0,0 +1,54 @@
+//! Integration tests for the in-memory fetch API.
+
+use spiffe_helper::fetch;
+use tempfile::TempDir;
+
+mod common;
+
+const DEFAULT_ROTATION_SECONDS: u32 = 300;
+
+/// Test that the X.509 and JWT SVIDs are returned without writing any files.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_fetch_svids_in_memory() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let svid = fetch::fetch_x509_svid(&agent_address)
+        .await
+        .expect("Failed to fetch X.509 SVID");
+    let jwt = fetch::fetch_jwt_svid(&agent_address, &["my-audience".to_string()])
+        .await
+        .expect("Failed to fetch JWT SVID");
+
+    server_handle.abort();
+
+    assert_eq!(
+        svid.spiffe_id().to_string(),
+        "spiffe://example.org/test/workload"
+    );
+    assert_eq!(
+        jwt.spiffe_id().to_string(),
+        "spiffe://example.org/test/workload"
+    );
+    assert_eq!(jwt.audience(), ["my-audience".to_string()]);
+
+    // Only the agent socket exists; nothing was written next to it
+    let entries: Vec<_> = std::fs::read_dir(temp_dir.path()).unwrap().collect();
+    assert_eq!(entries.len(), 1);
+}
+
+/// Test that a malformed address is rejected before connecting.
+#[tokio::test]
+async fn test_fetch_rejects_malformed_address() {
+    assert!(fetch::fetch_x509_svid("unix://").await.is_err());
+    assert!(fetch::fetch_jwt_svid("unix:///tmp/agent.sock", &[])
+        .await
+        .is_err());
+}
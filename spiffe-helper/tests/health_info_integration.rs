@@ -0,0 +1,279 @@
+use spiffe_helper::cli::{Config, HealthChecksConfig};
+use spiffe_helper::{daemon, health, workload_api};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tempfile::TempDir;
+
+mod common;
+
+const DEFAULT_ROTATION_SECONDS: u32 = 300;
+
+const INFO_PATH: &str = "/info";
+
+fn health_checks(port: u16) -> HealthChecksConfig {
+    HealthChecksConfig {
+        listener_enabled: true,
+        bind_port: port,
+        ..Default::default()
+    }
+}
+
+/// Test that `/info` reports the SPIFFE ID and validity of the certificate the daemon wrote.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_info_reports_written_svid() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+    let port = common::free_port();
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        daemon_mode: Some(true),
+        health_checks: Some(health_checks(port)),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let daemon_handle = tokio::spawn(async move { daemon::run(source, config).await });
+
+    // Wait for the health server to come up after the initial write
+    let mut response = None;
+    for _ in 0..50 {
+        if tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .is_ok()
+        {
+            response = Some(common::http_get(port, INFO_PATH).await);
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    let (status, body) = response.expect("Health server did not start");
+
+    daemon_handle.abort();
+    server_handle.abort();
+
+    assert_eq!(status, 200);
+    let info: serde_json::Value = serde_json::from_str(&body).expect("Invalid /info JSON");
+    assert_eq!(info["spiffe_id"], "spiffe://example.org/test/workload");
+
+    let pem = std::fs::read_to_string(cert_dir.join("svid.pem")).unwrap();
+    let leaf = pem::parse(&pem).unwrap();
+    let (_, cert) = x509_parser::parse_x509_certificate(&leaf.contents).unwrap();
+    assert_eq!(info["serial"], cert.raw_serial_as_string());
+    assert_eq!(info["not_before"], cert.validity().not_before.timestamp());
+    assert_eq!(info["not_after"], cert.validity().not_after.timestamp());
+}
+
+/// Test that `/info` lists every SVID the agent offers, with its hint, next to the selected one.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_info_lists_observed_svids() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+    let port = common::free_port();
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        let service = common::mock_agent_service(DEFAULT_ROTATION_SECONDS)
+            .with_additional_svid("/test/admin", "admin-api");
+        common::serve_mock_agent(&socket_path_clone, service).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        daemon_mode: Some(true),
+        health_checks: Some(health_checks(port)),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let daemon_handle = tokio::spawn(async move { daemon::run(source, config).await });
+
+    // The offered SVIDs are fetched in the background once the selected one is written
+    let mut info = None;
+    for _ in 0..50 {
+        if tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .is_ok()
+        {
+            let (status, body) = common::http_get(port, INFO_PATH).await;
+            if status == 200 && body.contains("observed_svids") {
+                info = Some(serde_json::from_str::<serde_json::Value>(&body).unwrap());
+                break;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    let info = info.expect("Health server did not list the observed SVIDs on /info");
+
+    daemon_handle.abort();
+    server_handle.abort();
+
+    assert_eq!(info["spiffe_id"], "spiffe://example.org/test/workload");
+    assert_eq!(
+        info["observed_svids"],
+        serde_json::json!([
+            {"spiffe_id": "spiffe://example.org/test/workload", "hint": null},
+            {"spiffe_id": "spiffe://example.org/test/admin", "hint": "admin-api"},
+        ])
+    );
+}
+
+/// Test that `/info` is unavailable until an SVID has been processed.
+#[tokio::test]
+async fn test_info_unavailable_before_first_svid() {
+    let port = common::free_port();
+    let mut server =
+        health::HealthCheckServer::new(Some(&health_checks(port)), health::create_health_status())
+            .await
+            .expect("Failed to start health server");
+
+    let (status, _) = common::http_get(port, INFO_PATH).await;
+    server.shutdown();
+
+    assert_eq!(status, 503);
+}
+
+/// Test that readiness fails once the leaf is within `readiness_expiry_threshold_seconds` of
+/// expiring, even though every write succeeded.
+#[tokio::test]
+async fn test_readiness_fails_near_expiry() {
+    let port = common::free_port();
+    let status = health::create_health_status();
+    let now = i64::try_from(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+    )
+    .unwrap();
+    let svid_info = |not_after| health::SvidInfo {
+        spiffe_id: "spiffe://example.org/test/workload".to_string(),
+        serial: "01".to_string(),
+        not_before: now - 60,
+        not_after,
+        observed_svids: Vec::new(),
+    };
+    {
+        let mut status = status.write().await;
+        status.x509_svid.record_success();
+        status.svid_info = Some(svid_info(now + 3600));
+    }
+
+    let mut config = health_checks(port);
+    config.readiness_expiry_threshold_seconds = Some(300);
+    let mut server = health::HealthCheckServer::new(Some(&config), status.clone())
+        .await
+        .expect("Failed to start health server");
+    let readiness_path = config.readiness_path();
+
+    let (far_from_expiry, _) = common::http_get(port, &readiness_path).await;
+    status.write().await.svid_info = Some(svid_info(now + 60));
+    let (near_expiry, _) = common::http_get(port, &readiness_path).await;
+    server.shutdown();
+
+    assert_eq!(far_from_expiry, 200);
+    assert_eq!(near_expiry, 503);
+}
+
+/// Test that with `readiness_requires_child`, readiness flips to 503 once the managed process
+/// exits.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_readiness_fails_after_child_exits() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+    let port = common::free_port();
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let mut health_checks = health_checks(port);
+    health_checks.readiness_requires_child = Some(true);
+    let readiness_path = health_checks.readiness_path();
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        daemon_mode: Some(true),
+        cmd: Some("sleep".to_string()),
+        cmd_args: Some("2".to_string()),
+        health_checks: Some(health_checks),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let daemon_handle = tokio::spawn(async move { daemon::run(source, config).await });
+
+    let mut while_running = None;
+    for _ in 0..50 {
+        if tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .is_ok()
+        {
+            while_running = Some(common::http_get(port, &readiness_path).await.0);
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    let mut after_exit = None;
+    for _ in 0..100 {
+        let (status, _) = common::http_get(port, &readiness_path).await;
+        if status == 503 {
+            after_exit = Some(status);
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    daemon_handle.abort();
+    server_handle.abort();
+
+    assert_eq!(while_running, Some(200));
+    assert_eq!(
+        after_exit,
+        Some(503),
+        "Readiness stayed up after the child exited"
+    );
+}
+
+/// Test that `bind_port = 0` binds an OS-chosen port and reports it.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_ephemeral_port_is_reported() {
+    let mut server =
+        health::HealthCheckServer::new(Some(&health_checks(0)), health::create_health_status())
+            .await
+            .expect("Failed to start health check server");
+
+    let port = server
+        .local_addr()
+        .expect("Enabled server should report its address")
+        .port();
+    assert_ne!(port, 0);
+    assert!(tokio::net::TcpStream::connect(("127.0.0.1", port))
+        .await
+        .is_ok());
+
+    server.shutdown();
+}
@@ -0,0 +1,279 @@
+//! Integration tests for serving the health check endpoints over TLS with the helper's SVID.
+
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, SignatureScheme};
+use spiffe_helper::cli::{Config, HealthChecksConfig};
+use spiffe_helper::{daemon, workload_api};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_rustls::TlsConnector;
+use x509_parser::extensions::GeneralName;
+
+mod common;
+
+const DEFAULT_ROTATION_SECONDS: u32 = 300;
+const LIVENESS_PATH: &str = "/health/live";
+
+/// Accepts any server certificate and keeps it, since SVIDs carry no DNS name to verify.
+#[derive(Debug, Default)]
+struct RecordingVerifier {
+    served: Mutex<Option<Vec<u8>>>,
+}
+
+impl ServerCertVerifier for RecordingVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        *self.served.lock().unwrap() = Some(end_entity.to_vec());
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        rustls::crypto::ring::default_provider()
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+fn health_checks(
+    port: u16,
+    require_client_cert: bool,
+    allowed_client_ids: Option<Vec<String>>,
+) -> HealthChecksConfig {
+    HealthChecksConfig {
+        listener_enabled: true,
+        bind_port: port,
+        tls_enabled: Some(true),
+        tls_require_client_cert: Some(require_client_cert),
+        allowed_client_ids,
+        ..Default::default()
+    }
+}
+
+/// The client certificate chain and key from the files the daemon wrote.
+fn client_identity(cert_dir: &Path) -> (Vec<CertificateDer<'static>>, PrivateKeyDer<'static>) {
+    let chain = pem::parse_many(std::fs::read(cert_dir.join("svid.pem")).unwrap())
+        .unwrap()
+        .into_iter()
+        .map(|block| CertificateDer::from(block.contents))
+        .collect();
+    let key = pem::parse(std::fs::read(cert_dir.join("svid_key.pem")).unwrap()).unwrap();
+    (chain, PrivateKeyDer::try_from(key.contents).unwrap())
+}
+
+/// Sends a GET for `path` over TLS, returning the HTTP status code, or the error if the
+/// handshake or exchange failed.
+async fn https_get(
+    port: u16,
+    path: &str,
+    verifier: Arc<RecordingVerifier>,
+    client_identity: Option<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)>,
+) -> std::io::Result<u16> {
+    let builder =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .dangerous()
+            .with_custom_certificate_verifier(verifier);
+    let config = match client_identity {
+        Some((chain, key)) => builder.with_client_auth_cert(chain, key).unwrap(),
+        None => builder.with_no_client_auth(),
+    };
+
+    let stream = tokio::net::TcpStream::connect(("127.0.0.1", port)).await?;
+    let mut stream = TlsConnector::from(Arc::new(config))
+        .connect(ServerName::try_from("localhost").unwrap(), stream)
+        .await?;
+    stream
+        .write_all(format!("GET {path} HTTP/1.0\r\nHost: localhost\r\n\r\n").as_bytes())
+        .await?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+    Ok(response
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .expect("Malformed HTTP response"))
+}
+
+/// Starts the mock agent and a daemon serving health checks over TLS, returning the health
+/// port once the server accepts connections.
+async fn start_daemon(
+    temp_dir: &TempDir,
+    require_client_cert: bool,
+    allowed_client_ids: Option<Vec<String>>,
+) -> (
+    u16,
+    tokio::task::JoinHandle<()>,
+    tokio::task::JoinHandle<()>,
+) {
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+    let port = common::free_port();
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        daemon_mode: Some(true),
+        health_checks: Some(health_checks(port, require_client_cert, allowed_client_ids)),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let daemon_handle = tokio::spawn(async move {
+        let _ = daemon::run(source, config).await;
+    });
+
+    for _ in 0..50 {
+        if tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .is_ok()
+        {
+            return (port, server_handle, daemon_handle);
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    panic!("Health check server did not start");
+}
+
+/// Test that the health endpoint is served over TLS with the SVID the daemon wrote.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_health_server_presents_svid_over_tls() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let (port, server_handle, daemon_handle) = start_daemon(&temp_dir, false, None).await;
+
+    let verifier = Arc::new(RecordingVerifier::default());
+    let status = https_get(port, LIVENESS_PATH, verifier.clone(), None).await;
+    let served = verifier.served.lock().unwrap().clone();
+
+    daemon_handle.abort();
+    server_handle.abort();
+
+    assert_eq!(status.expect("HTTPS request failed"), 200);
+    let served = served.expect("Server presented no certificate");
+    let (_, cert) = x509_parser::parse_x509_certificate(&served).unwrap();
+    let spiffe_ids: Vec<String> = cert
+        .subject_alternative_name()
+        .unwrap()
+        .unwrap()
+        .value
+        .general_names
+        .iter()
+        .filter_map(|name| match name {
+            GeneralName::URI(uri) => Some(uri.to_string()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(spiffe_ids.len(), 1);
+    assert!(
+        spiffe_ids[0].starts_with("spiffe://example.org/"),
+        "{spiffe_ids:?}"
+    );
+
+    // The served certificate is the one written to disk
+    let written = pem::parse(std::fs::read(temp_dir.path().join("certs/svid.pem")).unwrap())
+        .unwrap()
+        .contents;
+    assert_eq!(served, written);
+}
+
+/// Test that `tls_require_client_cert` rejects clients without a certificate from the trust
+/// bundle and accepts the workload's own SVID.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_health_server_requires_client_cert() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let (port, server_handle, daemon_handle) = start_daemon(&temp_dir, true, None).await;
+
+    let anonymous = https_get(
+        port,
+        LIVENESS_PATH,
+        Arc::new(RecordingVerifier::default()),
+        None,
+    )
+    .await;
+    let authenticated = https_get(
+        port,
+        LIVENESS_PATH,
+        Arc::new(RecordingVerifier::default()),
+        Some(client_identity(&temp_dir.path().join("certs"))),
+    )
+    .await;
+
+    daemon_handle.abort();
+    server_handle.abort();
+
+    assert!(
+        anonymous.is_err(),
+        "A client without a certificate was served: {anonymous:?}"
+    );
+    assert_eq!(authenticated.expect("mTLS request failed"), 200);
+}
+
+/// Sends an mTLS request for the liveness path with the workload's own SVID against a daemon
+/// that only allows `allowed_client_ids`.
+async fn liveness_with_allowed_client_ids(allowed_client_ids: Vec<String>) -> u16 {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let (port, server_handle, daemon_handle) =
+        start_daemon(&temp_dir, true, Some(allowed_client_ids)).await;
+
+    let status = https_get(
+        port,
+        LIVENESS_PATH,
+        Arc::new(RecordingVerifier::default()),
+        Some(client_identity(&temp_dir.path().join("certs"))),
+    )
+    .await;
+
+    daemon_handle.abort();
+    server_handle.abort();
+
+    status.expect("mTLS request failed")
+}
+
+/// Test that `allowed_client_ids` serves listed client identities and rejects others with 403.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_health_server_allowed_client_ids() {
+    let allowed =
+        liveness_with_allowed_client_ids(vec!["spiffe://example.org/test/workload".to_string()])
+            .await;
+    assert_eq!(allowed, 200);
+
+    let disallowed =
+        liveness_with_allowed_client_ids(vec!["spiffe://example.org/scraper".to_string()]).await;
+    assert_eq!(disallowed, 403);
+}
@@ -0,0 +1,110 @@
+//! Integration tests for how a failing JWT SVID fetch affects the daemon's X.509 rotation.
+
+use spiffe_helper::cli::{Config, JwtSvid};
+use spiffe_helper::file_system::MemorySink;
+use spiffe_helper::{daemon, workload_api};
+use std::path::Path;
+use std::time::Duration;
+use tempfile::TempDir;
+
+mod common;
+
+const REJECTED_AUDIENCE: &str = "misconfigured-audience";
+
+/// Starts a mock agent rotating the leaf every second and denying `REJECTED_AUDIENCE`, and
+/// returns a daemon config requesting a JWT SVID for that audience.
+async fn start_agent_rejecting_audience(temp_dir: &Path) -> (tokio::task::JoinHandle<()>, Config) {
+    let socket_path = temp_dir.join("agent.sock");
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        let service = common::mock_agent_service(1).with_rejected_jwt_audience(REJECTED_AUDIENCE);
+        common::serve_mock_agent(&socket_path_clone, service).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let config = Config {
+        agent_address: Some(format!("unix://{}", socket_path.display())),
+        cert_dir: Some(temp_dir.join("certs").display().to_string()),
+        daemon_mode: Some(true),
+        jwt_svids: Some(vec![JwtSvid {
+            jwt_audience: REJECTED_AUDIENCE.to_string(),
+            jwt_extra_audiences: None,
+            jwt_svid_file_name: "svid.jwt".to_string(),
+        }]),
+        ..Default::default()
+    };
+    (server_handle, config)
+}
+
+/// Test that a JWT audience the agent keeps denying does not hold up X.509 rotation.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_failing_jwt_fetch_does_not_block_x509_rotation() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let (server_handle, config) = start_agent_rejecting_audience(temp_dir.path()).await;
+    // Each JWT fetch keeps retrying far longer than the test runs
+    let config = Config {
+        jwt_fetch_timeout_seconds: Some(60),
+        ..config
+    };
+
+    let source = workload_api::create_x509_source(config.agent_address.as_deref().unwrap())
+        .await
+        .expect("Failed to create X509Source");
+    let sink = MemorySink::new();
+    let daemon_sink = sink.clone();
+    let daemon_handle =
+        tokio::spawn(async move { daemon::run_with_sink(source, config, daemon_sink).await });
+
+    let mut contents = sink.contents();
+    for _ in 0..100 {
+        if contents.cert_chain_writes >= 3 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        contents = sink.contents();
+    }
+    let finished = daemon_handle.is_finished();
+    daemon_handle.abort();
+    server_handle.abort();
+
+    assert!(
+        !finished,
+        "Daemon stopped on a JWT failure without fail_fast"
+    );
+    assert!(
+        contents.cert_chain_writes >= 3,
+        "Expected X.509 rotations while the JWT fetch fails, got {} writes",
+        contents.cert_chain_writes
+    );
+    assert_eq!(contents.jwt_writes, 0);
+}
+
+/// Test that `fail_fast` stops the daemon with an error once a JWT refresh fails.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_fail_fast_stops_daemon_on_jwt_failure() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let (server_handle, config) = start_agent_rejecting_audience(temp_dir.path()).await;
+    let config = Config {
+        jwt_fetch_timeout_seconds: Some(1),
+        fail_fast: Some(true),
+        ..config
+    };
+
+    let source = workload_api::create_x509_source(config.agent_address.as_deref().unwrap())
+        .await
+        .expect("Failed to create X509Source");
+    let result = tokio::time::timeout(
+        Duration::from_secs(10),
+        daemon::run_with_sink(source, config, MemorySink::new()),
+    )
+    .await;
+    server_handle.abort();
+
+    let error = result
+        .expect("Daemon kept running after a JWT failure with fail_fast")
+        .unwrap_err();
+    assert!(
+        error.to_string().contains("JWT"),
+        "Unexpected error: {error:#}"
+    );
+}
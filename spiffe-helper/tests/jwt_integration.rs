@@ -0,0 +1,159 @@
+use spiffe_helper::cli::{Config, JwtSvid};
+use spiffe_helper::file_system::{CredentialSink, LocalFileSystem};
+use spiffe_helper::{jwt, oneshot, workload_api};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tempfile::TempDir;
+
+mod common;
+
+const DEFAULT_ROTATION_SECONDS: u32 = 300;
+
+/// Test that a JWT SVID fetched from the mock SPIRE agent carries the requested
+/// audiences, the workload SPIFFE ID, and an expiry matching the mock's TTL.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_fetch_jwt_svid_from_mock_agent() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+
+    common::assert_socket_ready(&socket_path).await;
+
+    let config = Config {
+        agent_address: Some(format!("unix://{}", socket_path.display())),
+        jwt_fetch_timeout_seconds: Some(10),
+        ..Default::default()
+    };
+    let jwt_svid = JwtSvid {
+        jwt_audience: "primary-audience".to_string(),
+        jwt_extra_audiences: Some(vec!["extra-audience".to_string()]),
+        jwt_svid_file_name: "svid.jwt".to_string(),
+    };
+
+    let fetched = jwt::fetch_jwt_svid_for_config(&config, &jwt_svid)
+        .await
+        .expect("Failed to fetch JWT SVID");
+
+    assert_eq!(
+        fetched.spiffe_id().to_string(),
+        "spiffe://example.org/test/workload"
+    );
+
+    let claims = fetched.claims();
+    assert_eq!(claims.sub(), "spiffe://example.org/test/workload");
+    assert_eq!(claims.aud(), ["primary-audience", "extra-audience"]);
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let remaining = claims.exp() - now;
+    assert!(
+        remaining > 0 && remaining <= i64::from(common::JWT_TTL_SECONDS),
+        "Unexpected JWT lifetime: {remaining}s"
+    );
+
+    server_handle.abort();
+}
+
+/// Test that the JWT bundle written to disk contains the key that signed the JWT SVID.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_written_jwt_bundle_contains_svid_signing_key() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+
+    common::assert_socket_ready(&socket_path).await;
+
+    let config = Config {
+        agent_address: Some(format!("unix://{}", socket_path.display())),
+        cert_dir: Some(cert_dir.display().to_string()),
+        jwt_bundle_file_name: Some("bundle.jwks".to_string()),
+        jwt_fetch_timeout_seconds: Some(10),
+        ..Default::default()
+    };
+    let jwt_svid = JwtSvid {
+        jwt_audience: "primary-audience".to_string(),
+        jwt_extra_audiences: None,
+        jwt_svid_file_name: "svid.jwt".to_string(),
+    };
+
+    let fetched = jwt::fetch_jwt_svid_for_config(&config, &jwt_svid)
+        .await
+        .expect("Failed to fetch JWT SVID");
+    let bundles = jwt::fetch_jwt_bundles_for_config(&config)
+        .await
+        .expect("Failed to fetch JWT bundles");
+
+    let local_fs = LocalFileSystem::new(&config)
+        .and_then(LocalFileSystem::ensure)
+        .expect("Failed to prepare cert_dir");
+    local_fs
+        .write_jwt_bundle(&bundles)
+        .expect("Failed to write JWT bundle");
+
+    let content =
+        std::fs::read_to_string(cert_dir.join("bundle.jwks")).expect("Failed to read JWT bundle");
+    let written: serde_json::Value =
+        serde_json::from_str(&content).expect("JWT bundle is not valid JSON");
+
+    let keys = written["spiffe://example.org"]["keys"]
+        .as_array()
+        .expect("JWT bundle has no JWKS for example.org");
+    assert!(
+        keys.iter().any(|key| key["kid"] == fetched.key_id()),
+        "No key in the JWT bundle matches kid {}",
+        fetched.key_id()
+    );
+
+    server_handle.abort();
+}
+
+/// Test that one-shot mode writes each configured JWT SVID to its file.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_oneshot_writes_jwt_svids() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(cert_dir.display().to_string()),
+        daemon_mode: Some(false),
+        jwt_svids: Some(vec![JwtSvid {
+            jwt_audience: "primary-audience".to_string(),
+            jwt_extra_audiences: None,
+            jwt_svid_file_name: "svid.jwt".to_string(),
+        }]),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    oneshot::run(source, config)
+        .await
+        .expect("One-shot mode failed");
+
+    let token = std::fs::read_to_string(cert_dir.join("svid.jwt")).expect("JWT SVID not written");
+    let parsed = spiffe::svid::jwt::JwtSvid::parse_insecure(&token).expect("Invalid JWT SVID");
+    assert_eq!(parsed.audience(), ["primary-audience".to_string()]);
+
+    server_handle.abort();
+}
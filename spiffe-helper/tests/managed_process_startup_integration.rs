@@ -0,0 +1,110 @@
+#![cfg(unix)]
+
+use spiffe_helper::cli::Config;
+use spiffe_helper::{daemon, workload_api};
+use std::path::Path;
+use std::time::Duration;
+use tempfile::TempDir;
+
+mod common;
+
+const DEFAULT_ROTATION_SECONDS: u32 = 300;
+
+/// A daemon config whose managed process creates `marker` as soon as it starts.
+fn config_with_marker_process(agent_address: &str, cert_dir: &Path, marker: &Path) -> Config {
+    Config {
+        agent_address: Some(agent_address.to_string()),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        daemon_mode: Some(true),
+        cmd: Some("sh".to_string()),
+        cmd_args: Some(format!(
+            "-c \"touch {}; while true; do sleep 0.1; done\"",
+            marker.display()
+        )),
+        ..Default::default()
+    }
+}
+
+/// Test that the managed process is spawned once the initial credentials are on disk.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_managed_process_starts_after_initial_write() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+    let marker = temp_dir.path().join("started");
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = config_with_marker_process(&agent_address, &cert_dir, &marker);
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let daemon_handle = tokio::spawn(async move { daemon::run(source, config).await });
+
+    let mut started = false;
+    for _ in 0..100 {
+        if marker.exists() {
+            started = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    let written: Vec<bool> = ["svid.pem", "svid_key.pem", "svid_bundle.pem"]
+        .iter()
+        .map(|name| cert_dir.join(name).exists())
+        .collect();
+
+    daemon_handle.abort();
+    server_handle.abort();
+
+    assert!(started, "Managed process was never spawned");
+    assert_eq!(
+        written, [true; 3],
+        "Managed process started before every write"
+    );
+}
+
+/// Test that a failed initial fetch fails startup without ever spawning the managed process.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_managed_process_not_spawned_when_initial_fetch_fails() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+    let marker = temp_dir.path().join("started");
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        // The mock issues SVIDs in example.org, so the initial fetch is rejected
+        expected_trust_domain: Some("other.org".to_string()),
+        ..config_with_marker_process(&agent_address, &cert_dir, &marker)
+    };
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+
+    let result = tokio::time::timeout(Duration::from_secs(10), daemon::run(source, config))
+        .await
+        .expect("Daemon kept running after the initial fetch failed");
+    // Give a wrongly spawned process time to create its marker
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    server_handle.abort();
+
+    let error = result.expect_err("Daemon started without writing credentials");
+    assert!(
+        format!("{error:#}").contains("expected_trust_domain is other.org"),
+        "Unexpected error: {error:#}"
+    );
+    assert!(!marker.exists(), "Managed process was spawned");
+    assert!(!cert_dir.join("svid.pem").exists());
+}
@@ -0,0 +1,107 @@
+//! Integration tests driving the daemon and one-shot write paths against an in-memory sink.
+
+use spiffe_helper::cli::{Config, JwtSvid};
+use spiffe_helper::file_system::MemorySink;
+use spiffe_helper::{daemon, oneshot, workload_api};
+use std::time::Duration;
+use tempfile::TempDir;
+
+mod common;
+
+const DEFAULT_ROTATION_SECONDS: u32 = 300;
+
+/// Test that the daemon writes each rotation to the sink and nothing to the filesystem.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_daemon_writes_rotations_to_memory_sink() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, 1).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(cert_dir.display().to_string()),
+        daemon_mode: Some(true),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let sink = MemorySink::new();
+    let daemon_sink = sink.clone();
+    let daemon_handle =
+        tokio::spawn(async move { daemon::run_with_sink(source, config, daemon_sink).await });
+
+    let mut contents = sink.contents();
+    for _ in 0..100 {
+        if contents.cert_chain_writes >= 2 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        contents = sink.contents();
+    }
+    daemon_handle.abort();
+    server_handle.abort();
+
+    assert!(
+        contents.cert_chain_writes >= 2,
+        "Expected a rotation to be written, got {} writes",
+        contents.cert_chain_writes
+    );
+    assert!(!contents
+        .cert_chain
+        .expect("No certificate chain")
+        .is_empty());
+    assert!(!contents.key.expect("No private key").is_empty());
+    assert!(!contents.bundle.expect("No bundle").is_empty());
+    assert!(!cert_dir.exists(), "Daemon wrote to the local filesystem");
+}
+
+/// Test that one-shot mode writes the X.509 and JWT SVIDs to the sink without a `cert_dir`.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_oneshot_writes_to_memory_sink() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        daemon_mode: Some(false),
+        jwt_svids: Some(vec![JwtSvid {
+            jwt_audience: "primary-audience".to_string(),
+            jwt_extra_audiences: None,
+            jwt_svid_file_name: "svid.jwt".to_string(),
+        }]),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let sink = MemorySink::new();
+    oneshot::run_with_sink(source, config, sink.clone())
+        .await
+        .expect("One-shot mode failed");
+    server_handle.abort();
+
+    let contents = sink.contents();
+    assert_eq!(contents.cert_chain_writes, 1);
+    assert_eq!(contents.key_writes, 1);
+    assert_eq!(contents.bundle_writes, 1);
+    let token = contents.jwts.get("svid.jwt").expect("JWT SVID not written");
+    let parsed = spiffe::svid::jwt::JwtSvid::parse_insecure(token).expect("Invalid JWT SVID");
+    assert_eq!(parsed.audience(), ["primary-audience".to_string()]);
+}
@@ -0,0 +1,95 @@
+use spiffe_helper::cli::{Config, OutputConfig};
+use spiffe_helper::{daemon, workload_api};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::time::Duration;
+use tempfile::TempDir;
+
+mod common;
+
+fn mode(path: &Path) -> u32 {
+    std::fs::metadata(path).unwrap().permissions().mode() & 0o777
+}
+
+/// Test that the daemon writes every rotation to each `output` block, with each output's own
+/// file names and modes.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_daemon_writes_rotations_to_every_output() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let first_dir = temp_dir.path().join("first");
+    let second_dir = temp_dir.path().join("second");
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, 1).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        daemon_mode: Some(true),
+        key_file_mode: Some("0600".to_string()),
+        outputs: Some(vec![
+            OutputConfig {
+                cert_dir: first_dir.display().to_string(),
+                ..Default::default()
+            },
+            OutputConfig {
+                cert_dir: second_dir.display().to_string(),
+                svid_file_name: Some("tls.crt".to_string()),
+                svid_key_file_name: Some("tls.key".to_string()),
+                cert_file_mode: Some("0640".to_string()),
+                key_file_mode: Some("0640".to_string()),
+                ..Default::default()
+            },
+        ]),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let daemon_handle = tokio::spawn(async move { daemon::run(source, config).await });
+
+    let first_cert = first_dir.join("svid.pem");
+    let second_cert = second_dir.join("tls.crt");
+
+    // Wait for both outputs to hold the same certificate, after at least one rotation
+    let mut initial = None;
+    let mut rotated = false;
+    for _ in 0..80 {
+        let first = std::fs::read(&first_cert).ok();
+        let second = std::fs::read(&second_cert).ok();
+        if let (Some(first), Some(second)) = (first, second) {
+            if first == second {
+                match &initial {
+                    None => initial = Some(first),
+                    Some(initial) if *initial != first => {
+                        rotated = true;
+                        break;
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    daemon_handle.abort();
+    server_handle.abort();
+
+    assert!(
+        rotated,
+        "Both outputs did not receive a rotated certificate"
+    );
+    common::assert_x509_cert(&first_cert);
+    common::assert_x509_cert(&second_cert);
+    common::assert_x509_key(&first_dir.join("svid_key.pem"));
+    common::assert_x509_key(&second_dir.join("tls.key"));
+
+    assert_eq!(mode(&first_cert), 0o644);
+    assert_eq!(mode(&first_dir.join("svid_key.pem")), 0o600);
+    assert_eq!(mode(&second_cert), 0o640);
+    assert_eq!(mode(&second_dir.join("tls.key")), 0o640);
+}
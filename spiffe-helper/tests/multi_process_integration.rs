@@ -0,0 +1,83 @@
+use spiffe_helper::cli::{Config, ManagedProcess};
+use spiffe_helper::daemon;
+use spiffe_helper::workload_api;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tempfile::TempDir;
+
+mod common;
+
+/// Rotation interval of the mock agent; every push carries a freshly signed SVID.
+const ROTATION_SECONDS: u32 = 1;
+
+/// A shell process that appends the name of every SIGUSR1/SIGUSR2 it receives to `signal_log`.
+fn signal_recorder(signal_log: &Path, renew_signal: &str) -> ManagedProcess {
+    let log = signal_log.display();
+    ManagedProcess {
+        cmd: "sh".to_string(),
+        cmd_args: Some(format!(
+            "-c \"trap 'echo USR1 >> {log}' USR1; trap 'echo USR2 >> {log}' USR2; \
+             while true; do sleep 0.1; done\""
+        )),
+        renew_signal: Some(renew_signal.to_string()),
+    }
+}
+
+fn read_signals(signal_log: &Path) -> Vec<String> {
+    fs::read_to_string(signal_log)
+        .map(|log| log.lines().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Test that the daemon spawns every `process` block and sends each its own renew signal.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_daemon_signals_each_process_with_its_own_signal() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+    let app_log = temp_dir.path().join("app.log");
+    let reloader_log = temp_dir.path().join("reloader.log");
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        daemon_mode: Some(true),
+        processes: Some(vec![
+            signal_recorder(&app_log, "SIGUSR1"),
+            signal_recorder(&reloader_log, "SIGUSR2"),
+        ]),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let daemon_handle = tokio::spawn(async move { daemon::run(source, config).await });
+
+    // Let the mock rotate the SVID a few times
+    tokio::time::sleep(Duration::from_secs(u64::from(ROTATION_SECONDS) * 3 + 1)).await;
+
+    daemon_handle.abort();
+    server_handle.abort();
+
+    let app_signals = read_signals(&app_log);
+    let reloader_signals = read_signals(&reloader_log);
+    assert!(!app_signals.is_empty(), "First process was never signalled");
+    assert!(
+        !reloader_signals.is_empty(),
+        "Second process was never signalled"
+    );
+    assert!(app_signals.iter().all(|s| s == "USR1"), "{app_signals:?}");
+    assert!(
+        reloader_signals.iter().all(|s| s == "USR2"),
+        "{reloader_signals:?}"
+    );
+}
@@ -0,0 +1,178 @@
+//! Integration tests for an agent that accepts connections but issues no X.509 SVID.
+
+use spiffe_helper::{health, workload_api};
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+use tempfile::TempDir;
+
+mod common;
+
+const DEFAULT_ROTATION_SECONDS: u32 = 300;
+
+/// Serve a mock agent on `socket_path` that returns an empty SVID list.
+async fn start_agent_without_svids(socket_path: &Path) -> tokio::task::JoinHandle<()> {
+    let service = common::mock_agent_service(DEFAULT_ROTATION_SECONDS).with_no_svids();
+    let socket_path_clone = socket_path.to_path_buf();
+    let server_handle = tokio::spawn(async move {
+        common::serve_mock_agent(&socket_path_clone, service).await;
+    });
+    common::assert_socket_ready(socket_path).await;
+    server_handle
+}
+
+/// Test that waiting for an SVID gives up after `max_attempts` with an error explaining that
+/// the workload is likely not registered.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_wait_for_svid_fails_after_max_attempts() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let server_handle = start_agent_without_svids(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let result = tokio::time::timeout(
+        Duration::from_secs(20),
+        workload_api::wait_for_x509_svid(
+            &agent_address,
+            2,
+            false,
+            Some(Duration::from_secs(5)),
+            None,
+        ),
+    )
+    .await
+    .expect("Waiting for an SVID did not give up");
+    server_handle.abort();
+
+    let error = result.expect_err("An agent without SVIDs was accepted");
+    let message = error.to_string();
+    assert!(message.contains("after 2 attempts"), "{message}");
+    assert!(message.contains("likely not registered"), "{message}");
+}
+
+/// Test that the daemon exits with the connect error code and the descriptive error once
+/// `no_svid_max_attempts` fetches returned no SVID.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_daemon_reports_unregistered_workload() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let config_path = temp_dir.path().join("helper.conf");
+    let server_handle = start_agent_without_svids(&socket_path).await;
+    std::fs::write(
+        &config_path,
+        format!(
+            "agent_address = \"unix://{}\"\ncert_dir = \"{}\"\ndaemon_mode = true\n\
+             no_svid_max_attempts = 2\nconnect_backoff_jitter = false\n",
+            socket_path.display(),
+            temp_dir.path().join("certs").display()
+        ),
+    )
+    .unwrap();
+
+    let mut command =
+        tokio::process::Command::from(Command::new(env!("CARGO_BIN_EXE_spiffe-helper")));
+    command
+        .arg("--config")
+        .arg(&config_path)
+        .env_remove("SPIFFE_ENDPOINT_SOCKET")
+        .kill_on_drop(true);
+    let output = tokio::time::timeout(Duration::from_secs(30), command.output())
+        .await
+        .expect("Daemon kept waiting for an SVID")
+        .expect("Failed to run spiffe-helper");
+    server_handle.abort();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(3), "{stderr}");
+    assert!(stderr.contains("likely not registered"), "{stderr}");
+    assert!(stderr.contains("attempt 1/2"), "{stderr}");
+}
+
+/// Test that each attempt that finds no SVID is recorded in the health status.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_wait_for_svid_records_attempts_in_health_status() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let server_handle = start_agent_without_svids(&socket_path).await;
+    let health_status = health::create_health_status();
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let result = tokio::time::timeout(
+        Duration::from_secs(20),
+        workload_api::wait_for_x509_svid(
+            &agent_address,
+            2,
+            false,
+            Some(Duration::from_secs(5)),
+            Some(&health_status),
+        ),
+    )
+    .await
+    .expect("Waiting for an SVID did not give up");
+    server_handle.abort();
+    assert!(result.is_err(), "An agent without SVIDs was accepted");
+
+    let status = health_status.read().await;
+    assert!(!status.is_live());
+    assert!(!status.is_ready());
+    assert_eq!(status.x509_svid.consecutive_failures, 2);
+    assert_eq!(
+        status.failure_reason().as_deref(),
+        Some("X.509 SVID: agent issued no SVID, attempt 2/2: the agent returned no SVIDs")
+    );
+}
+
+/// Test that the daemon serves the health checks while it waits for an SVID, reporting why it
+/// is not ready.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_daemon_reports_missing_svid_on_readiness_probe() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let config_path = temp_dir.path().join("helper.conf");
+    let port = common::free_port();
+    let server_handle = start_agent_without_svids(&socket_path).await;
+    std::fs::write(
+        &config_path,
+        format!(
+            "agent_address = \"unix://{}\"\ncert_dir = \"{}\"\ndaemon_mode = true\n\
+             no_svid_max_attempts = 3\nconnect_backoff_jitter = false\n\
+             health_checks {{\n  listener_enabled = true\n  bind_port = {port}\n}}\n",
+            socket_path.display(),
+            temp_dir.path().join("certs").display()
+        ),
+    )
+    .unwrap();
+
+    let mut child = tokio::process::Command::new(env!("CARGO_BIN_EXE_spiffe-helper"))
+        .arg("--config")
+        .arg(&config_path)
+        .env_remove("SPIFFE_ENDPOINT_SOCKET")
+        .kill_on_drop(true)
+        .spawn()
+        .expect("Failed to run spiffe-helper");
+
+    // The first attempt fails at once; the retries that follow take a few seconds
+    let mut response = None;
+    for _ in 0..50 {
+        if tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .is_ok()
+        {
+            let (status, body) = common::http_get(port, "/health/ready").await;
+            if !body.is_empty() {
+                response = Some((status, body));
+                break;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    child.kill().await.unwrap();
+    server_handle.abort();
+
+    let (status, body) = response.expect("Readiness probe never reported a reason");
+    assert_eq!(status, 503);
+    assert!(
+        body.starts_with("X.509 SVID: agent issued no SVID, attempt 1/3"),
+        "{body}"
+    );
+}
@@ -0,0 +1,112 @@
+use spiffe_helper::cli::Config;
+use spiffe_helper::{oneshot, workload_api};
+use std::path::Path;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::process::Command;
+
+mod common;
+
+const DEFAULT_ROTATION_SECONDS: u32 = 300;
+
+/// Run the spiffe-helper binary in one-shot mode against `config_path` and return its exit code.
+async fn run_oneshot_binary(config_path: &Path) -> i32 {
+    let output = tokio::time::timeout(
+        Duration::from_secs(30),
+        Command::new(env!("CARGO_BIN_EXE_spiffe-helper"))
+            .arg("--config")
+            .arg(config_path)
+            .arg("--daemon-mode")
+            .arg("false")
+            .env_remove("SPIFFE_ENDPOINT_SOCKET")
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .expect("spiffe-helper did not exit")
+    .expect("Failed to run spiffe-helper");
+
+    output.status.code().expect("spiffe-helper was killed")
+}
+
+/// Test that an unreadable configuration file exits with the config error code.
+#[tokio::test]
+async fn test_oneshot_config_error_exit_code() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+
+    let code = run_oneshot_binary(&temp_dir.path().join("missing.conf")).await;
+    assert_eq!(code, 2);
+}
+
+/// Test that failing to write into `cert_dir` exits with the write error code.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_oneshot_write_error_exit_code() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let config_path = temp_dir.path().join("helper.conf");
+
+    // A regular file where a directory is expected makes creating cert_dir fail
+    let blocker = temp_dir.path().join("not-a-dir");
+    std::fs::write(&blocker, "").unwrap();
+    let cert_dir = blocker.join("certs");
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    std::fs::write(
+        &config_path,
+        format!(
+            "agent_address = \"unix://{}\"\ncert_dir = \"{}\"\n",
+            socket_path.display(),
+            cert_dir.display()
+        ),
+    )
+    .unwrap();
+
+    let code = run_oneshot_binary(&config_path).await;
+    assert_eq!(code, 4);
+
+    server_handle.abort();
+}
+
+/// Test that failing to reach the agent for the JWT bundle reports a connect error.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_oneshot_connect_error_exit_code() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+
+    // The JWT bundle is fetched from agent_address, which points at a socket nobody serves
+    let config = Config {
+        agent_address: Some(format!(
+            "unix://{}",
+            temp_dir.path().join("missing.sock").display()
+        )),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        daemon_mode: Some(false),
+        jwt_bundle_file_name: Some("jwt_bundle.json".to_string()),
+        jwt_fetch_timeout_seconds: Some(1),
+        ..Default::default()
+    };
+
+    let error = oneshot::run(source, config)
+        .await
+        .expect_err("One-shot mode should fail without an agent");
+    assert_eq!(error.exit_code(), 3);
+
+    server_handle.abort();
+}
@@ -94,3 +94,81 @@ async fn test_oneshot_creates_dir_and_custom_files() {
 
     server_handle.abort();
 }
+
+/// Test that file name templates are filled in from the fetched SVID's SPIFFE ID.
+#[tokio::test]
+async fn test_oneshot_renders_file_name_templates() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        daemon_mode: Some(false),
+        svid_file_name: Some("{path}.crt".to_string()),
+        svid_key_file_name: Some("{path}.key".to_string()),
+        svid_bundle_file_name: Some("{trust_domain}.pem".to_string()),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let result = oneshot::run(source, config).await;
+    assert!(result.is_ok(), "One-shot mode failed: {:?}", result.err());
+
+    // The mock issues spiffe://example.org/test/workload
+    common::assert_x509_cert(&cert_dir.join("test_workload.crt"));
+    common::assert_x509_key(&cert_dir.join("test_workload.key"));
+    assert!(cert_dir.join("example.org.pem").exists());
+
+    server_handle.abort();
+}
+
+/// Test that one-shot mode can reach an agent listening on a Linux abstract socket.
+#[cfg(target_os = "linux")]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_oneshot_over_abstract_socket() {
+    use std::os::linux::net::SocketAddrExt;
+
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let cert_dir = temp_dir.path().join("certs");
+
+    let name = format!("spiffe-helper-oneshot-{}", std::process::id());
+    let addr = std::os::unix::net::SocketAddr::from_abstract_name(name.as_bytes()).unwrap();
+    let listener = std::os::unix::net::UnixListener::bind_addr(&addr).unwrap();
+    listener.set_nonblocking(true).unwrap();
+    let uds = tokio::net::UnixListener::from_std(listener).unwrap();
+    let server_handle = tokio::spawn(async move {
+        common::serve_mock_agent_on(uds, common::mock_agent_service(DEFAULT_ROTATION_SECONDS))
+            .await;
+    });
+
+    let agent_address = format!("unix://@{name}");
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        daemon_mode: Some(false),
+        ..Default::default()
+    };
+    config.validate().expect("Abstract socket address rejected");
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let result = oneshot::run(source, config).await;
+    assert!(result.is_ok(), "One-shot mode failed: {:?}", result.err());
+
+    let _cert_bytes = common::assert_x509_cert(&cert_dir.join("svid.pem"));
+
+    server_handle.abort();
+}
@@ -0,0 +1,79 @@
+//! Integration tests for retrying the initial agent connection in one-shot mode.
+
+use std::path::Path;
+use std::process::Output;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::process::Command;
+
+mod common;
+
+const DEFAULT_ROTATION_SECONDS: u32 = 300;
+
+/// Write a one-shot config pointing at `socket_path` and return its path.
+fn write_config(temp_dir: &Path, socket_path: &Path) -> std::path::PathBuf {
+    let config_path = temp_dir.join("helper.conf");
+    std::fs::write(
+        &config_path,
+        format!(
+            "agent_address = \"unix://{}\"\ncert_dir = \"{}\"\ndaemon_mode = false\n",
+            socket_path.display(),
+            temp_dir.join("certs").display()
+        ),
+    )
+    .unwrap();
+    config_path
+}
+
+async fn run_binary(config_path: &Path, extra: &[&str]) -> Output {
+    tokio::time::timeout(
+        Duration::from_secs(30),
+        Command::new(env!("CARGO_BIN_EXE_spiffe-helper"))
+            .arg("--config")
+            .arg(config_path)
+            .args(extra)
+            .env_remove("SPIFFE_ENDPOINT_SOCKET")
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .expect("spiffe-helper did not exit")
+    .expect("Failed to run spiffe-helper")
+}
+
+/// Test that `--oneshot-retry` waits for an agent that starts after the helper.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_oneshot_retry_waits_for_late_agent() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let config_path = write_config(temp_dir.path(), &socket_path);
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+
+    let output = run_binary(&config_path, &["--oneshot-retry", "5"]).await;
+    server_handle.abort();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    common::assert_x509_cert(&temp_dir.path().join("certs").join("svid.pem"));
+}
+
+/// Test that a missing agent fails with the connect error code once the attempts run out.
+#[tokio::test]
+async fn test_oneshot_retry_gives_up_after_max_attempts() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = write_config(temp_dir.path(), &temp_dir.path().join("missing.sock"));
+
+    let output = run_binary(&config_path, &["--oneshot-retry", "2"]).await;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(3), "{stderr}");
+    assert!(stderr.contains("after 2 attempts"), "{stderr}");
+}
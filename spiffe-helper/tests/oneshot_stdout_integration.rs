@@ -0,0 +1,111 @@
+//! Integration tests for printing the SVID to stdout in one-shot mode.
+
+use std::path::Path;
+use std::process::Output;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::process::Command;
+
+mod common;
+
+const DEFAULT_ROTATION_SECONDS: u32 = 300;
+
+async fn run_binary(temp_dir: &Path, daemon_mode: bool, extra: &[&str]) -> Output {
+    let config_path = temp_dir.join("helper.conf");
+    std::fs::write(
+        &config_path,
+        format!(
+            "agent_address = \"unix://{}\"\ndaemon_mode = {daemon_mode}\n",
+            temp_dir.join("agent.sock").display()
+        ),
+    )
+    .unwrap();
+
+    tokio::time::timeout(
+        Duration::from_secs(30),
+        Command::new(env!("CARGO_BIN_EXE_spiffe-helper"))
+            .arg("--config")
+            .arg(&config_path)
+            .args(extra)
+            .current_dir(temp_dir)
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .expect("spiffe-helper did not exit")
+    .expect("Failed to run spiffe-helper")
+}
+
+/// Test that `--stdout` prints only PEM blocks, starting with a parseable certificate.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_stdout_prints_certificate_chain() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let output = run_binary(temp_dir.path(), false, &["--stdout"]).await;
+    server_handle.abort();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let pems = pem::parse_many(&output.stdout).expect("stdout is not PEM");
+    assert!(!pems.is_empty(), "No PEM blocks on stdout");
+    assert!(pems.iter().all(|p| p.tag == "CERTIFICATE"));
+    x509_parser::parse_x509_certificate(&pems[0].contents).expect("Invalid certificate");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with("-----BEGIN CERTIFICATE-----"),
+        "{stdout}"
+    );
+    assert!(!stdout.contains("Successfully"), "{stdout}");
+
+    let written: Vec<_> = std::fs::read_dir(temp_dir.path())
+        .unwrap()
+        .map(|e| e.unwrap().file_name())
+        .filter(|name| name.to_string_lossy().ends_with(".pem"))
+        .collect();
+    assert!(written.is_empty(), "Files written: {written:?}");
+}
+
+/// Test that `--stdout-key` appends the private key after the chain.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_stdout_key_appends_private_key() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let output = run_binary(temp_dir.path(), false, &["--stdout", "--stdout-key"]).await;
+    server_handle.abort();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let pems = pem::parse_many(&output.stdout).expect("stdout is not PEM");
+    assert_eq!(pems.first().unwrap().tag, "CERTIFICATE");
+    assert_eq!(pems.last().unwrap().tag, "PRIVATE KEY");
+}
+
+/// Test that `--stdout` is rejected outside one-shot mode.
+#[tokio::test]
+async fn test_stdout_rejected_in_daemon_mode() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+
+    let output = run_binary(temp_dir.path(), true, &["--stdout"]).await;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(2), "{stderr}");
+    assert!(stderr.contains("only valid in one-shot mode"), "{stderr}");
+}
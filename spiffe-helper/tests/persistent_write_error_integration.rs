@@ -0,0 +1,94 @@
+//! Integration tests for how the daemon reacts to X.509 updates that keep failing to be written.
+
+use spiffe_helper::cli::Config;
+use spiffe_helper::{daemon, workload_api};
+use std::fs;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::task::JoinHandle;
+
+mod common;
+
+/// Rotation interval of the mock agent, so every second brings an update to write.
+const ROTATION_SECONDS: u32 = 1;
+
+/// Starts the daemon with `on_persistent_write_error` set to `action` against a mock agent
+/// rotating every second, waits for the first write, and then replaces `svid.pem` with a
+/// directory so that every later write fails.
+async fn start_daemon_with_failing_writes(
+    temp_dir: &TempDir,
+    action: &str,
+) -> (JoinHandle<()>, JoinHandle<anyhow::Result<()>>) {
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        daemon_mode: Some(true),
+        on_persistent_write_error: Some(action.to_string()),
+        persistent_write_error_threshold: Some(2),
+        ..Default::default()
+    };
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let daemon_handle = tokio::spawn(async move { daemon::run(source, config).await });
+
+    let cert_path = cert_dir.join("svid.pem");
+    for _ in 0..100 {
+        if cert_path.exists() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    fs::remove_file(&cert_path).expect("Initial certificate was not written");
+    fs::create_dir(&cert_path).unwrap();
+
+    (server_handle, daemon_handle)
+}
+
+/// Test that `on_persistent_write_error = "exit"` stops the daemon with an error once the
+/// threshold of consecutive failed updates is reached.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_exit_on_persistent_write_error() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let (server_handle, daemon_handle) = start_daemon_with_failing_writes(&temp_dir, "exit").await;
+
+    let result = tokio::time::timeout(Duration::from_secs(15), daemon_handle)
+        .await
+        .expect("Daemon kept running after repeated write failures")
+        .unwrap();
+    server_handle.abort();
+
+    let error = result.expect_err("Daemon exited without an error");
+    assert!(
+        format!("{error:#}").contains("X.509 update failed 2 times in a row"),
+        "Unexpected error: {error:#}"
+    );
+}
+
+/// Test that the default `degrade` action keeps the daemon running through the same failures.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_degrade_on_persistent_write_error() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let (server_handle, daemon_handle) =
+        start_daemon_with_failing_writes(&temp_dir, "degrade").await;
+
+    tokio::time::sleep(Duration::from_secs(u64::from(ROTATION_SECONDS) * 4)).await;
+    let finished = daemon_handle.is_finished();
+    daemon_handle.abort();
+    server_handle.abort();
+
+    assert!(
+        !finished,
+        "Daemon stopped under on_persistent_write_error = degrade"
+    );
+}
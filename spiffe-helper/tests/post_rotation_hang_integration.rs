@@ -0,0 +1,89 @@
+//! SIGTERM is delivered to the whole test process, so this test lives in its own binary to keep
+//! it from stopping the daemons of other tests.
+
+use spiffe_helper::cli::Config;
+use spiffe_helper::{daemon, signal, workload_api};
+use std::fs;
+use std::time::Duration;
+use tempfile::TempDir;
+
+mod common;
+
+const ROTATION_SECONDS: u32 = 1;
+
+/// Test that a post-rotation command that never exits holds up neither later rotations nor
+/// shutdown on SIGTERM.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_hung_post_rotation_cmd_does_not_block_daemon() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+    let started = temp_dir.path().join("hook-started");
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        daemon_mode: Some(true),
+        post_rotation_cmd: Some("sh".to_string()),
+        post_rotation_cmd_args: Some(format!(
+            "-c \"echo started >> {}; exec sleep 3600\"",
+            started.display()
+        )),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let daemon_handle = tokio::spawn(async move { daemon::run(source, config).await });
+
+    let cert_path = cert_dir.join("svid.pem");
+    let mut hung_at = None;
+    for _ in 0..100 {
+        if started.exists() {
+            hung_at = fs::read(&cert_path).ok();
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    let hung_at = hung_at.expect("Post-rotation command never started");
+
+    // The hook is still sleeping, yet the next rotation is written
+    let mut rotated = false;
+    for _ in 0..100 {
+        if fs::read(&cert_path).is_ok_and(|cert| cert != hung_at) {
+            rotated = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(
+        rotated,
+        "Rotation was blocked by the running post-rotation command"
+    );
+
+    signal::send_signal(
+        std::process::id().try_into().unwrap(),
+        signal::Signal::SIGTERM,
+    )
+    .expect("Failed to send SIGTERM to daemon");
+
+    let daemon_result = tokio::time::timeout(Duration::from_secs(5), daemon_handle)
+        .await
+        .expect("Daemon did not shut down within timeout");
+    server_handle.abort();
+
+    let run_result = daemon_result.expect("Daemon task panicked");
+    assert!(
+        run_result.is_ok(),
+        "Daemon mode failed: {:?}",
+        run_result.err()
+    );
+}
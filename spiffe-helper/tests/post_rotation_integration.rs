@@ -0,0 +1,93 @@
+use spiffe_helper::cli::Config;
+use spiffe_helper::daemon;
+use spiffe_helper::workload_api;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tempfile::TempDir;
+
+mod common;
+
+const ROTATION_SECONDS: u32 = 1;
+
+/// Run the daemon with `post_rotation_cmd = sh -c <script>` against a rotating mock agent
+/// until `sentinel` holds `runs` lines, then return whether the daemon was still running.
+async fn run_hook_until(script: &str, sentinel: &Path, temp_dir: &TempDir, runs: usize) -> bool {
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, ROTATION_SECONDS).await;
+    });
+
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        daemon_mode: Some(true),
+        post_rotation_cmd: Some("sh".to_string()),
+        post_rotation_cmd_args: Some(format!("-c \"{script}\"")),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let daemon_handle = tokio::spawn(async move { daemon::run(source, config).await });
+
+    let max_attempts = 100;
+    let delay = Duration::from_millis(100);
+    for _ in 0..max_attempts {
+        let lines = fs::read_to_string(sentinel)
+            .map(|content| content.lines().count())
+            .unwrap_or(0);
+        if lines >= runs {
+            break;
+        }
+        tokio::time::sleep(delay).await;
+    }
+
+    let running = !daemon_handle.is_finished();
+    daemon_handle.abort();
+    server_handle.abort();
+    running
+}
+
+/// Test that the post-rotation command runs after every rotation.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_post_rotation_cmd_runs_on_each_rotation() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let sentinel = temp_dir.path().join("rotations.log");
+    let script = format!("echo rotated >> {}", sentinel.display());
+
+    run_hook_until(&script, &sentinel, &temp_dir, 2).await;
+
+    let content = fs::read_to_string(&sentinel).expect("Post-rotation command never ran");
+    assert!(
+        content.lines().count() >= 2,
+        "Post-rotation command did not run on each rotation: {content:?}"
+    );
+}
+
+/// Test that a failing post-rotation command is logged without stopping the daemon.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_failing_post_rotation_cmd_keeps_daemon_running() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let sentinel = temp_dir.path().join("rotations.log");
+    let script = format!("echo rotated >> {}; exit 1", sentinel.display());
+
+    let running = run_hook_until(&script, &sentinel, &temp_dir, 2).await;
+
+    let content = fs::read_to_string(&sentinel).expect("Post-rotation command never ran");
+    assert!(
+        content.lines().count() >= 2,
+        "Daemon stopped running the hook after a failure: {content:?}"
+    );
+    assert!(
+        running,
+        "Daemon exited after a failing post-rotation command"
+    );
+}
@@ -249,3 +249,49 @@ async fn test_spawn_process_with_env_expansion() {
     let stdout = String::from_utf8_lossy(&output.stdout);
     assert_eq!(stdout.trim(), "integration_test_value");
 }
+
+/// Test that a child's stdout and stderr are relayed line by line with a stream tag.
+#[tokio::test]
+async fn test_relay_child_output_tags_stdout_and_stderr() {
+    use spiffe_helper::process::{relay_child_output, ChildStream};
+    use std::process::Stdio;
+    use tokio::process::Command as TokioCommand;
+
+    let args = parse_cmd_args(r#"-c "echo out-line; echo err-line >&2; echo out-again""#).unwrap();
+
+    let mut child = TokioCommand::new("/bin/sh")
+        .args(&args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+
+    let mut captured_stdout = Vec::new();
+    let mut captured_stderr = Vec::new();
+    let (out_res, err_res) = tokio::join!(
+        relay_child_output(stdout, ChildStream::Stdout, &mut captured_stdout),
+        relay_child_output(stderr, ChildStream::Stderr, &mut captured_stderr),
+    );
+    out_res.unwrap();
+    err_res.unwrap();
+
+    let status = child.wait().await.unwrap();
+    assert!(status.success());
+
+    let captured_stdout = String::from_utf8(captured_stdout).unwrap();
+    let captured_stderr = String::from_utf8(captured_stderr).unwrap();
+    assert_eq!(
+        captured_stdout.lines().collect::<Vec<_>>(),
+        vec![
+            "[child_stream=stdout] out-line",
+            "[child_stream=stdout] out-again"
+        ]
+    );
+    assert_eq!(
+        captured_stderr.lines().collect::<Vec<_>>(),
+        vec!["[child_stream=stderr] err-line"]
+    );
+}
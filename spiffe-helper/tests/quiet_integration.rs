@@ -0,0 +1,121 @@
+//! Integration tests for `--quiet`.
+
+use std::process::Stdio;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::process::Command;
+
+mod common;
+
+const DEFAULT_ROTATION_SECONDS: u32 = 300;
+
+/// Test that a successful one-shot run under `--quiet` prints nothing to stdout but still
+/// writes the credentials.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_quiet_oneshot_prints_nothing() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let config_path = temp_dir.path().join("helper.conf");
+    std::fs::write(
+        &config_path,
+        format!(
+            "agent_address = \"unix://{}\"\ncert_dir = \"{}\"\ndaemon_mode = false\n",
+            socket_path.display(),
+            cert_dir.display()
+        ),
+    )
+    .unwrap();
+
+    let output = tokio::time::timeout(
+        Duration::from_secs(30),
+        Command::new(env!("CARGO_BIN_EXE_spiffe-helper"))
+            .arg("--config")
+            .arg(&config_path)
+            .arg("--quiet")
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .expect("spiffe-helper did not exit")
+    .expect("Failed to run spiffe-helper");
+    server_handle.abort();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        output.stdout.is_empty(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    common::assert_x509_cert(&cert_dir.join("svid.pem"));
+}
+
+/// Test that under `--quiet` a captured managed process's stdout is dropped like the helper's
+/// own informational output, while its stderr is still relayed.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_quiet_drops_captured_child_stdout() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let config_path = temp_dir.path().join("helper.conf");
+    std::fs::write(
+        &config_path,
+        format!(
+            "agent_address = \"unix://{}\"\ncert_dir = \"{}\"\ndaemon_mode = true\n\
+             cmd = \"/bin/sh\"\ncmd_args = \"-c 'echo out-line; echo err-line >&2; sleep 30'\"\n\
+             capture_child_output = true\n",
+            socket_path.display(),
+            cert_dir.display()
+        ),
+    )
+    .unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_spiffe-helper"))
+        .arg("--config")
+        .arg(&config_path)
+        .arg("--quiet")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .expect("Failed to run spiffe-helper");
+    let mut stdout = child.stdout.take().unwrap();
+    let mut stderr_lines = BufReader::new(child.stderr.take().unwrap()).lines();
+
+    let relayed = tokio::time::timeout(Duration::from_secs(30), async {
+        while let Some(line) = stderr_lines.next_line().await.unwrap() {
+            if line == "[child_stream=stderr] err-line" {
+                return true;
+            }
+        }
+        false
+    })
+    .await
+    .expect("Managed process stderr was not relayed");
+    // Give the stdout relay, which read its line first, time to have written it
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    child.kill().await.unwrap();
+    server_handle.abort();
+
+    assert!(relayed, "spiffe-helper exited without relaying stderr");
+    let mut captured = String::new();
+    stdout.read_to_string(&mut captured).await.unwrap();
+    assert!(captured.is_empty(), "{captured}");
+}
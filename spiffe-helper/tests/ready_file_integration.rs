@@ -0,0 +1,73 @@
+use spiffe_helper::cli::{Config, JwtSvid};
+use spiffe_helper::{daemon, workload_api};
+use std::fs;
+use tempfile::TempDir;
+
+mod common;
+
+const DEFAULT_ROTATION_SECONDS: u32 = 300;
+const STALE_CONTENT: &str = "stale\n";
+
+/// Test that the daemon replaces a stale `ready_file` only once the X.509 and JWT credentials
+/// have all been written.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_ready_file_appears_after_first_full_write() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+    let ready_file = temp_dir.path().join("ready");
+    fs::write(&ready_file, STALE_CONTENT).unwrap();
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        daemon_mode: Some(true),
+        svid_bundle_file_name: Some("bundle.pem".to_string()),
+        jwt_svids: Some(vec![JwtSvid {
+            jwt_audience: "audience".to_string(),
+            jwt_extra_audiences: None,
+            jwt_svid_file_name: "svid.jwt".to_string(),
+        }]),
+        jwt_fetch_timeout_seconds: Some(10),
+        ready_file: Some(ready_file.to_str().unwrap().to_string()),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let daemon_handle = tokio::spawn(async move { daemon::run(source, config).await });
+
+    let mut content = None;
+    for _ in 0..100 {
+        match fs::read_to_string(&ready_file) {
+            Ok(current) if current != STALE_CONTENT && !current.is_empty() => {
+                content = Some(current);
+                break;
+            }
+            _ => tokio::time::sleep(std::time::Duration::from_millis(50)).await,
+        }
+    }
+    // Every credential must already be on disk when the ready file appears
+    let written: Vec<bool> = ["svid.pem", "svid_key.pem", "bundle.pem", "svid.jwt"]
+        .iter()
+        .map(|name| cert_dir.join(name).exists())
+        .collect();
+
+    daemon_handle.abort();
+    server_handle.abort();
+
+    let content = content.expect("Ready file was not written");
+    content
+        .trim()
+        .parse::<u64>()
+        .expect("Ready file does not hold a timestamp");
+    assert_eq!(written, [true; 4], "Ready file appeared before every write");
+}
@@ -0,0 +1,76 @@
+//! The refresh signal is delivered to the whole test process, so this test lives in its own
+//! binary to keep it from reaching the daemons of other tests.
+#![cfg(unix)]
+
+use spiffe_helper::cli::Config;
+use spiffe_helper::{daemon, signal, workload_api};
+use std::fs;
+use std::time::Duration;
+use tempfile::TempDir;
+
+mod common;
+
+const DEFAULT_ROTATION_SECONDS: u32 = 300;
+
+/// Test that `refresh_signal` rewrites the certificate at once, although the agent pushed no
+/// update and the SVID did not change.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_refresh_signal_rewrites_credentials() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        daemon_mode: Some(true),
+        refresh_signal: Some("SIGUSR2".to_string()),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let daemon_handle = tokio::spawn(async move { daemon::run(source, config).await });
+
+    let cert_path = cert_dir.join("svid.pem");
+    for _ in 0..100 {
+        if cert_path.exists() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    let initial_mtime = fs::metadata(&cert_path)
+        .and_then(|m| m.modified())
+        .expect("Initial certificate was not written");
+    // Let the signal handler register and keep the rewrite from sharing the initial mtime
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+
+    signal::send_signal(
+        std::process::id().try_into().unwrap(),
+        signal::Signal::SIGUSR2,
+    )
+    .expect("Failed to send SIGUSR2 to daemon");
+
+    let mut rewritten = false;
+    for _ in 0..50 {
+        let mtime = fs::metadata(&cert_path).and_then(|m| m.modified());
+        if mtime.is_ok_and(|mtime| mtime > initial_mtime) {
+            rewritten = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    daemon_handle.abort();
+    server_handle.abort();
+
+    assert!(rewritten, "Certificate was not rewritten after SIGUSR2");
+}
@@ -0,0 +1,94 @@
+//! Integration tests for resolving relative paths against the config file's directory.
+
+use clap::Parser;
+use spiffe_helper::cli::Args;
+use tempfile::TempDir;
+
+/// Write `content` to `helper.conf` in a fresh temp dir and load it through the CLI.
+fn load_config(content: &str) -> (TempDir, spiffe_helper::cli::Config) {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("helper.conf");
+    std::fs::write(&config_path, content).unwrap();
+
+    let args =
+        Args::try_parse_from(["spiffe-helper", "--config", config_path.to_str().unwrap()]).unwrap();
+    let config = args.get_operation_config().unwrap();
+
+    (temp_dir, config)
+}
+
+/// Test that a relative `cert_dir` resolves next to the config file when enabled.
+#[test]
+fn test_relative_cert_dir_resolves_against_config_dir() {
+    let (temp_dir, config) = load_config(
+        r#"
+        agent_address = "unix:///tmp/agent.sock"
+        cert_dir = "certs"
+        pid_file_name = "run/app.pid"
+        paths_relative_to_config = true
+    "#,
+    );
+
+    assert_eq!(
+        config.cert_dir.as_deref(),
+        Some(temp_dir.path().join("certs").to_str().unwrap())
+    );
+    assert_eq!(
+        config.pid_file_name.as_deref(),
+        Some(temp_dir.path().join("run/app.pid").to_str().unwrap())
+    );
+}
+
+/// Test that absolute paths are left unchanged when resolution is enabled.
+#[test]
+fn test_absolute_cert_dir_is_unchanged() {
+    let (_temp_dir, config) = load_config(
+        r#"
+        agent_address = "unix:///tmp/agent.sock"
+        cert_dir = "/var/run/certs"
+        paths_relative_to_config = true
+    "#,
+    );
+
+    assert_eq!(config.cert_dir.as_deref(), Some("/var/run/certs"));
+}
+
+/// Test that relative paths stay relative to the working directory by default.
+#[test]
+fn test_relative_cert_dir_kept_by_default() {
+    let (_temp_dir, config) = load_config(
+        r#"
+        agent_address = "unix:///tmp/agent.sock"
+        cert_dir = "certs"
+    "#,
+    );
+
+    assert_eq!(config.cert_dir.as_deref(), Some("certs"));
+}
+
+/// Test that the `cert_dir` of each `output` block resolves next to the config file, like the
+/// top-level one.
+#[test]
+fn test_relative_output_cert_dirs_resolve_against_config_dir() {
+    let (temp_dir, config) = load_config(
+        r#"
+        agent_address = "unix:///tmp/agent.sock"
+        paths_relative_to_config = true
+
+        output {
+            cert_dir = "certs/app-a"
+        }
+
+        output {
+            cert_dir = "/var/run/app-b"
+        }
+    "#,
+    );
+
+    let outputs = config.outputs.as_deref().expect("Missing output blocks");
+    assert_eq!(
+        outputs[0].cert_dir,
+        temp_dir.path().join("certs/app-a").to_str().unwrap()
+    );
+    assert_eq!(outputs[1].cert_dir, "/var/run/app-b");
+}
@@ -0,0 +1,346 @@
+use spiffe_helper::cli::{Config, HealthChecksConfig, JwtSvid, SignalTarget};
+use spiffe_helper::daemon;
+use spiffe_helper::file_system::MemorySink;
+use spiffe_helper::workload_api;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::process::{Child, Command};
+
+mod common;
+
+/// Rotation interval of the mock agent; every push re-sends the same SVID.
+const ROTATION_SECONDS: u32 = 1;
+
+const LIVENESS_PATH: &str = "/live";
+
+/// Spawn a shell that appends a line to `signal_log` on every `signal` (such as `USR1`) and
+/// record its PID in `pid_file`, so the daemon can signal it through a PID file.
+async fn spawn_signal_recorder(signal: &str, signal_log: &Path, pid_file: &Path) -> Child {
+    let script = format!(
+        "trap 'echo {signal} >> {}' {signal}; while true; do sleep 0.1; done",
+        signal_log.display()
+    );
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(script)
+        .kill_on_drop(true)
+        .spawn()
+        .expect("Failed to spawn signal recorder");
+    fs::write(pid_file, child.id().unwrap().to_string()).expect("Failed to write PID file");
+
+    // Give the shell time to install its trap before any signal can arrive
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    child
+}
+
+/// Run the daemon against a mock agent that keeps re-sending an identical SVID and return
+/// the number of renew signals the recorder received.
+async fn count_signals_for_identical_svid(write_on_unchanged: bool) -> usize {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+    let signal_log = temp_dir.path().join("signals.log");
+    let pid_file = temp_dir.path().join("app.pid");
+
+    let service = common::mock_agent_service(ROTATION_SECONDS).with_static_svid();
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::serve_mock_agent(&socket_path_clone, service).await;
+    });
+
+    common::assert_socket_ready(&socket_path).await;
+    let _recorder = spawn_signal_recorder("USR1", &signal_log, &pid_file).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        daemon_mode: Some(true),
+        renew_signal: Some("SIGUSR1".to_string()),
+        pid_file_name: Some(pid_file.to_str().unwrap().to_string()),
+        write_on_unchanged: Some(write_on_unchanged),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let daemon_handle = tokio::spawn(async move { daemon::run(source, config).await });
+
+    // Let the mock push the same SVID several times
+    tokio::time::sleep(Duration::from_secs(u64::from(ROTATION_SECONDS) * 3 + 1)).await;
+
+    daemon_handle.abort();
+    server_handle.abort();
+
+    fs::read_to_string(&signal_log)
+        .map(|log| log.lines().count())
+        .unwrap_or(0)
+}
+
+/// Test that an update carrying the same SVID is neither rewritten nor signalled.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_no_renew_signal_for_identical_svid() {
+    let signals = count_signals_for_identical_svid(false).await;
+    assert_eq!(signals, 0, "Renew signal sent for an unchanged SVID");
+}
+
+/// Test that `write_on_unchanged` restores rewriting and signalling on every update.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_write_on_unchanged_signals_identical_svid() {
+    let signals = count_signals_for_identical_svid(true).await;
+    assert!(signals > 0, "No renew signal sent with write_on_unchanged");
+}
+
+/// Run the daemon with a managed process whose SIGUSR1 handler runs `on_signal`, and return
+/// the liveness status once several fresh SVIDs have been signalled.
+async fn liveness_with_confirm_file(
+    on_signal: &str,
+    confirm_file: &Path,
+    temp_dir: &TempDir,
+) -> u16 {
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+    let port = common::free_port();
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        daemon_mode: Some(true),
+        cmd: Some("sh".to_string()),
+        cmd_args: Some(format!(
+            "-c \"trap '{on_signal}' USR1; while true; do sleep 0.1; done\""
+        )),
+        renew_signal: Some("SIGUSR1".to_string()),
+        renew_signal_confirm_file: Some(confirm_file.to_str().unwrap().to_string()),
+        renew_signal_confirm_timeout_seconds: Some(1),
+        health_checks: Some(HealthChecksConfig {
+            listener_enabled: true,
+            bind_port: port,
+            liveness_path: Some(LIVENESS_PATH.to_string()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let daemon_handle = tokio::spawn(async move { daemon::run(source, config).await });
+
+    // Let a few rotations be signalled and their confirmation windows elapse
+    tokio::time::sleep(Duration::from_secs(u64::from(ROTATION_SECONDS) * 3 + 1)).await;
+    let (status, _) = common::http_get(port, LIVENESS_PATH).await;
+
+    daemon_handle.abort();
+    server_handle.abort();
+    status
+}
+
+/// Test that the daemon observes the renew signal handler touching the confirm file.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_renew_signal_confirmed_by_touched_file() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let confirm_file = temp_dir.path().join("reloaded");
+    let on_signal = format!("touch {}", confirm_file.display());
+
+    let status = liveness_with_confirm_file(&on_signal, &confirm_file, &temp_dir).await;
+
+    assert!(confirm_file.exists(), "Signal handler never ran");
+    assert_eq!(status, 200, "Confirmed renew signal reported as unhealthy");
+}
+
+/// Test that a renew signal without confirmation marks the daemon as not live.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_unconfirmed_renew_signal_degrades_liveness() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let confirm_file = temp_dir.path().join("reloaded");
+
+    let status = liveness_with_confirm_file("true", &confirm_file, &temp_dir).await;
+
+    assert!(!confirm_file.exists());
+    assert_eq!(status, 503, "Unconfirmed renew signal not reported");
+}
+
+/// Run the daemon against a mock agent whose X.509 SVID never changes while its JWT SVID is
+/// refreshed every second, and return the number of renew signals the recorder received.
+async fn count_signals_for_jwt_refresh(renew_signal_on: &[&str]) -> usize {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+    let signal_log = temp_dir.path().join("signals.log");
+    let pid_file = temp_dir.path().join("app.pid");
+
+    let service = common::mock_agent_service(3600).with_static_svid();
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::serve_mock_agent(&socket_path_clone, service).await;
+    });
+
+    common::assert_socket_ready(&socket_path).await;
+    let _recorder = spawn_signal_recorder(&signal_log, &pid_file).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        daemon_mode: Some(true),
+        renew_signal: Some("SIGUSR1".to_string()),
+        renew_signal_on: Some(renew_signal_on.iter().map(ToString::to_string).collect()),
+        pid_file_name: Some(pid_file.to_str().unwrap().to_string()),
+        jwt_svids: Some(vec![JwtSvid {
+            jwt_audience: "my-audience".to_string(),
+            jwt_extra_audiences: None,
+            jwt_svid_file_name: "svid.jwt".to_string(),
+        }]),
+        min_refresh_interval_seconds: Some(1),
+        max_refresh_interval_seconds: Some(1),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let daemon_handle = tokio::spawn(async move { daemon::run(source, config).await });
+
+    // Let the JWT SVID be refreshed a few times
+    tokio::time::sleep(Duration::from_millis(3500)).await;
+
+    daemon_handle.abort();
+    server_handle.abort();
+
+    assert!(
+        cert_dir.join("svid.jwt").exists(),
+        "JWT SVID was not written"
+    );
+    fs::read_to_string(&signal_log)
+        .map(|log| log.lines().count())
+        .unwrap_or(0)
+}
+
+/// Test that a JWT-only refresh signals the process when `renew_signal_on` includes `jwt`.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_jwt_refresh_signals_when_enabled() {
+    let signals = count_signals_for_jwt_refresh(&["x509", "jwt"]).await;
+    assert!(signals > 0, "No renew signal sent for a JWT refresh");
+}
+
+/// Test that a JWT-only refresh is not signalled with the default `renew_signal_on`.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_jwt_refresh_not_signalled_by_default() {
+    let signals = count_signals_for_jwt_refresh(&["x509"]).await;
+    assert_eq!(signals, 0, "Renew signal sent for a JWT refresh");
+}
+
+/// Test that every `signal_targets` process is sent its own signal on rotation.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_signal_targets_signal_each_pid_file() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+    let usr1_log = temp_dir.path().join("usr1.log");
+    let usr2_log = temp_dir.path().join("usr2.log");
+    let usr1_pid_file = temp_dir.path().join("usr1.pid");
+    let usr2_pid_file = temp_dir.path().join("usr2.pid");
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+    let _usr1_recorder = spawn_signal_recorder("USR1", &usr1_log, &usr1_pid_file).await;
+    let _usr2_recorder = spawn_signal_recorder("USR2", &usr2_log, &usr2_pid_file).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let target = |pid_file: &Path, signal: &str| SignalTarget {
+        pid_file: pid_file.to_str().unwrap().to_string(),
+        signal: signal.to_string(),
+    };
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        daemon_mode: Some(true),
+        signal_targets: Some(vec![
+            target(&usr1_pid_file, "SIGUSR1"),
+            target(&usr2_pid_file, "SIGUSR2"),
+        ]),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let daemon_handle = tokio::spawn(async move { daemon::run(source, config).await });
+
+    // Let the mock rotate the SVID a few times
+    tokio::time::sleep(Duration::from_secs(u64::from(ROTATION_SECONDS) * 3 + 1)).await;
+
+    daemon_handle.abort();
+    server_handle.abort();
+
+    let usr1 = fs::read_to_string(&usr1_log).unwrap_or_default();
+    let usr2 = fs::read_to_string(&usr2_log).unwrap_or_default();
+    assert!(usr1.lines().count() > 0, "USR1 target was never signalled");
+    assert!(usr1.lines().all(|line| line == "USR1"), "{usr1}");
+    assert!(usr2.lines().count() > 0, "USR2 target was never signalled");
+    assert!(usr2.lines().all(|line| line == "USR2"), "{usr2}");
+}
+
+/// Test that a burst of rotations within `update_debounce_ms` is written and signalled once.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rotation_burst_is_written_and_signalled_once() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let signal_log = temp_dir.path().join("signals.log");
+    let pid_file = temp_dir.path().join("app.pid");
+
+    // Three fresh SVIDs 50ms apart, two seconds after the first
+    let service = common::mock_agent_service(2).with_rotation_burst(3, Duration::from_millis(50));
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::serve_mock_agent(&socket_path_clone, service).await;
+    });
+
+    common::assert_socket_ready(&socket_path).await;
+    let _recorder = spawn_signal_recorder("USR1", &signal_log, &pid_file).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        daemon_mode: Some(true),
+        renew_signal: Some("SIGUSR1".to_string()),
+        pid_file_name: Some(pid_file.to_str().unwrap().to_string()),
+        update_debounce_ms: Some(300),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let sink = MemorySink::new();
+    let daemon_sink = sink.clone();
+    let daemon_handle =
+        tokio::spawn(async move { daemon::run_with_sink(source, config, daemon_sink).await });
+
+    // Past the debounced burst, but before the next one
+    tokio::time::sleep(Duration::from_millis(3300)).await;
+
+    daemon_handle.abort();
+    server_handle.abort();
+
+    // The initial write plus one for the whole burst
+    assert_eq!(sink.contents().cert_chain_writes, 2);
+    let signals = fs::read_to_string(&signal_log)
+        .map(|log| log.lines().count())
+        .unwrap_or(0);
+    assert_eq!(signals, 1, "Expected one renew signal for the burst");
+}
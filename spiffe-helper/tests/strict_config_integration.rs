@@ -0,0 +1,75 @@
+//! Integration tests for rejecting unknown config keys with `--strict-config`.
+
+use clap::Parser;
+use spiffe_helper::cli::Args;
+use tempfile::TempDir;
+
+const TYPO_CONFIG: &str = r#"
+    agent_address = "unix:///tmp/agent.sock"
+    cert_dir = "/tmp/certs"
+    svid_flie_name = "tls.crt"
+"#;
+
+fn args_for(config_path: &std::path::Path, extra: &[&str]) -> Args {
+    let mut argv = vec!["spiffe-helper", "--config", config_path.to_str().unwrap()];
+    argv.extend_from_slice(extra);
+    Args::try_parse_from(argv).unwrap()
+}
+
+/// Test that `--strict-config` reports a typo'd key along with the key it resembles.
+#[test]
+fn test_strict_config_flag_rejects_typo() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("helper.conf");
+    std::fs::write(&config_path, TYPO_CONFIG).unwrap();
+
+    let error = args_for(&config_path, &["--strict-config"])
+        .get_operation_config()
+        .unwrap_err();
+
+    let message = format!("{error:#}");
+    assert!(
+        message.contains("svid_flie_name (did you mean svid_file_name?)"),
+        "{message}"
+    );
+}
+
+/// Test that the same typo is ignored without `--strict-config`.
+#[test]
+fn test_lenient_config_ignores_typo() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("helper.conf");
+    std::fs::write(&config_path, TYPO_CONFIG).unwrap();
+
+    let config = args_for(&config_path, &[]).get_operation_config().unwrap();
+
+    assert_eq!(config.svid_file_name(), "svid.pem");
+}
+
+/// Test that `--strict-config` turns a `renew_signal` with nothing to signal into an error,
+/// while the same config only warns without it.
+#[test]
+fn test_strict_config_rejects_renew_signal_without_target() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("helper.conf");
+    std::fs::write(
+        &config_path,
+        r#"
+            agent_address = "unix:///tmp/agent.sock"
+            cert_dir = "/tmp/certs"
+            renew_signal = "SIGHUP"
+        "#,
+    )
+    .unwrap();
+
+    let error = args_for(&config_path, &["--strict-config"])
+        .get_operation_config()
+        .unwrap_err();
+    let message = format!("{error:#}");
+    assert!(
+        message.contains("renew_signal is set but there is no cmd"),
+        "{message}"
+    );
+
+    assert!(args_for(&config_path, &[]).get_operation_config().is_ok());
+}
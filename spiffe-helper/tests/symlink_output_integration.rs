@@ -0,0 +1,75 @@
+#![cfg(unix)]
+
+use spiffe::cert::Certificate;
+use spiffe_helper::cli::Config;
+use spiffe_helper::file_system::{CredentialSink, LocalFileSystem};
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::path::Path;
+use tempfile::TempDir;
+
+/// A `cert_dir` whose `svid.pem` is a relative symlink into a separate `volume` directory.
+fn symlinked_cert_dir(temp_dir: &TempDir) -> (Config, std::path::PathBuf) {
+    let cert_dir = temp_dir.path().join("certs");
+    let volume = temp_dir.path().join("volume");
+    fs::create_dir_all(&cert_dir).unwrap();
+    fs::create_dir_all(&volume).unwrap();
+    fs::write(volume.join("svid.pem"), "old").unwrap();
+    symlink(Path::new("../volume/svid.pem"), cert_dir.join("svid.pem")).unwrap();
+
+    let config = Config {
+        cert_dir: Some(cert_dir.display().to_string()),
+        ..Default::default()
+    };
+    (config, volume.join("svid.pem"))
+}
+
+fn certificate() -> Certificate {
+    let der = spire_agent_mock::svid::SvidGenerator::generate_ca_der("example.org");
+    Certificate::try_from(der).unwrap()
+}
+
+/// Test that a symlinked certificate path is written through to its target by default,
+/// leaving the link in place.
+#[test]
+fn test_symlinked_cert_path_is_written_through() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let (config, target) = symlinked_cert_dir(&temp_dir);
+    let link = temp_dir.path().join("certs/svid.pem");
+
+    let sink = LocalFileSystem::new(&config).unwrap();
+    sink.write_cert_chain(&[certificate()]).unwrap();
+
+    assert!(fs::symlink_metadata(&link)
+        .unwrap()
+        .file_type()
+        .is_symlink());
+    let written = fs::read_to_string(&target).unwrap();
+    assert!(
+        written.starts_with("-----BEGIN CERTIFICATE-----"),
+        "{written}"
+    );
+}
+
+/// Test that `follow_symlinks = false` refuses to write through a symlinked certificate path
+/// and leaves both the link and its target untouched.
+#[test]
+fn test_symlinked_cert_path_is_rejected_without_follow_symlinks() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let (mut config, target) = symlinked_cert_dir(&temp_dir);
+    config.follow_symlinks = Some(false);
+    let link = temp_dir.path().join("certs/svid.pem");
+
+    let sink = LocalFileSystem::new(&config).unwrap();
+    let error = sink.write_cert_chain(&[certificate()]).unwrap_err();
+
+    assert!(
+        error.to_string().contains("is a symlink"),
+        "Unexpected error: {error:#}"
+    );
+    assert!(fs::symlink_metadata(&link)
+        .unwrap()
+        .file_type()
+        .is_symlink());
+    assert_eq!(fs::read_to_string(&target).unwrap(), "old");
+}
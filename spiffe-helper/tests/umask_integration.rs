@@ -0,0 +1,92 @@
+//! The umask is process-wide, so this test lives in its own binary to keep it from changing the
+//! modes of files other tests create.
+#![cfg(unix)]
+
+use nix::sys::stat::{umask, Mode};
+use spiffe_helper::cli::Config;
+use spiffe_helper::{daemon, workload_api};
+use std::os::unix::fs::PermissionsExt;
+use std::time::Duration;
+use tempfile::TempDir;
+
+mod common;
+
+/// Reads the current umask without changing it.
+fn current_umask() -> nix::libc::mode_t {
+    let mask = umask(Mode::from_bits_truncate(0o022));
+    umask(mask);
+    mask.bits()
+}
+
+/// Test that the daemon applies `umask` to files it creates without an explicit mode, runs its
+/// managed process and post-rotation command with the original umask, and restores the
+/// original umask when it stops.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_daemon_applies_and_restores_umask() {
+    umask(Mode::from_bits_truncate(0o022));
+
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let sentinel = temp_dir.path().join("rotated");
+    let child_umask = temp_dir.path().join("child-umask");
+    let hook_umask = temp_dir.path().join("hook-umask");
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, 1).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(temp_dir.path().join("certs").display().to_string()),
+        daemon_mode: Some(true),
+        renew_sentinel_file: Some(sentinel.display().to_string()),
+        umask: Some("0077".to_string()),
+        cmd: Some("sh".to_string()),
+        cmd_args: Some(format!(
+            "-c 'umask > {}; exec sleep 30'",
+            child_umask.display()
+        )),
+        post_rotation_cmd: Some("sh".to_string()),
+        post_rotation_cmd_args: Some(format!("-c 'umask > {}'", hook_umask.display())),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let daemon_handle = tokio::spawn(async move { daemon::run(source, config).await });
+
+    // The sentinel is written with the default mode, so only the umask restricts it
+    let mut mode = None;
+    for _ in 0..50 {
+        if let Ok(metadata) = std::fs::metadata(&sentinel) {
+            mode = Some(metadata.permissions().mode() & 0o777);
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(mode, Some(0o600), "Sentinel mode should reflect umask 0077");
+    assert_eq!(current_umask(), 0o077);
+
+    // The managed process and the post-rotation command keep the umask the helper started with
+    for path in [&child_umask, &hook_umask] {
+        let mut reported = String::new();
+        for _ in 0..50 {
+            reported = std::fs::read_to_string(path).unwrap_or_default();
+            if !reported.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(reported.trim(), "0022", "{}", path.display());
+    }
+
+    daemon_handle.abort();
+    let _ = daemon_handle.await;
+    server_handle.abort();
+
+    assert_eq!(current_umask(), 0o022, "umask was not restored");
+}
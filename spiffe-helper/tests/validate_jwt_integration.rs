@@ -0,0 +1,105 @@
+//! Integration tests for the `validate-jwt` operation.
+
+use spire_agent_mock::jwt::JwtSigner;
+use std::path::Path;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+const SPIFFE_ID: &str = "spiffe://example.org/workload";
+
+fn run_validate_jwt(jwt_path: &Path, extra: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_spiffe-helper"))
+        .arg("validate-jwt")
+        .arg(jwt_path)
+        .args(extra)
+        .output()
+        .expect("Failed to run spiffe-helper")
+}
+
+/// Mint a token for two audiences, write it with a trailing newline, and return its path.
+fn write_token(temp_dir: &TempDir, signer: &JwtSigner) -> std::path::PathBuf {
+    let token = signer.mint(
+        SPIFFE_ID,
+        &["service-a".to_string(), "service-b".to_string()],
+    );
+    let path = temp_dir.path().join("svid.jwt");
+    std::fs::write(&path, format!("{token}\n")).unwrap();
+    path
+}
+
+/// Test that the claims of a known token are printed without verifying the signature.
+#[test]
+fn test_validate_jwt_prints_claims() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let jwt_path = write_token(&temp_dir, &JwtSigner::new(3600));
+
+    let output = run_validate_jwt(&jwt_path, &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    assert!(
+        stdout.contains(&format!("SPIFFE ID: {SPIFFE_ID}\n")),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("Audiences: service-a, service-b\n"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("Issued at: "), "{stdout}");
+    assert!(stdout.contains("Expires at: "), "{stdout}");
+    let remaining: u64 = stdout
+        .lines()
+        .find_map(|l| l.strip_prefix("Time to expiry: "))
+        .and_then(|v| v.strip_suffix('s'))
+        .expect("Missing time to expiry")
+        .parse()
+        .unwrap();
+    assert!((3590..=3600).contains(&remaining), "{stdout}");
+    assert!(stdout.contains("Signature: not verified"), "{stdout}");
+}
+
+/// Test that --jwks verifies the signature against both a plain JWKS and a helper JWT bundle.
+#[test]
+fn test_validate_jwt_verifies_signature() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let signer = JwtSigner::new(3600);
+    let jwt_path = write_token(&temp_dir, &signer);
+
+    let jwks_path = temp_dir.path().join("jwks.json");
+    std::fs::write(&jwks_path, signer.jwks().to_string()).unwrap();
+    let bundle_path = temp_dir.path().join("jwt_bundle.json");
+    std::fs::write(
+        &bundle_path,
+        serde_json::json!({ "example.org": signer.jwks() }).to_string(),
+    )
+    .unwrap();
+
+    for path in [&jwks_path, &bundle_path] {
+        let output = run_validate_jwt(&jwt_path, &["--jwks", path.to_str().unwrap()]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert!(stdout.contains("Signature: valid"), "{stdout}");
+    }
+}
+
+/// Test that a token signed by a different key fails verification.
+#[test]
+fn test_validate_jwt_rejects_wrong_key() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let jwt_path = write_token(&temp_dir, &JwtSigner::new(3600));
+    let jwks_path = temp_dir.path().join("jwks.json");
+    std::fs::write(&jwks_path, JwtSigner::new(3600).jwks().to_string()).unwrap();
+
+    let output = run_validate_jwt(&jwt_path, &["--jwks", jwks_path.to_str().unwrap()]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("Signature verification failed"), "{stderr}");
+}
@@ -0,0 +1,38 @@
+//! Integration tests for the version flag and the `version` subcommand.
+
+use std::process::Command;
+
+fn run(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_spiffe-helper"))
+        .args(args)
+        .output()
+        .expect("Failed to run spiffe-helper");
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Test that `--version` and `version` print only the package version.
+#[test]
+fn test_version_prints_package_version() {
+    let expected = format!("{}\n", env!("CARGO_PKG_VERSION"));
+
+    assert_eq!(run(&["--version"]), expected);
+    assert_eq!(run(&["-v"]), expected);
+    assert_eq!(run(&["version"]), expected);
+}
+
+/// Test that `version --verbose` adds the build details after the package version.
+#[test]
+fn test_version_verbose_prints_build_details() {
+    let output = run(&["version", "--verbose"]);
+    let mut lines = output.lines();
+
+    assert_eq!(lines.next(), Some(env!("CARGO_PKG_VERSION")));
+    let details: Vec<&str> = lines.collect();
+    for label in ["git commit: ", "built: ", "target: ", "spiffe crate: "] {
+        assert!(
+            details.iter().any(|line| line.starts_with(label)),
+            "Missing {label:?} in {output}"
+        );
+    }
+}
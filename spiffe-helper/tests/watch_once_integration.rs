@@ -0,0 +1,102 @@
+use spiffe_helper::cli::Config;
+use spiffe_helper::{oneshot, workload_api};
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::process::Command;
+
+mod common;
+
+const DEFAULT_ROTATION_SECONDS: u32 = 300;
+
+/// Test that `--watch-once` writes the certificate files and the process exits successfully
+/// once the served certificate is valid for long enough.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_watch_once_writes_files_and_exits() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+    let config_path = temp_dir.path().join("helper.conf");
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    std::fs::write(
+        &config_path,
+        format!(
+            "agent_address = \"unix://{}\"\ncert_dir = \"{}\"\nwatch_once_min_validity_seconds = {}\n",
+            socket_path.display(),
+            cert_dir.display(),
+            common::SVID_TTL_SECONDS / 2
+        ),
+    )
+    .unwrap();
+
+    let output = tokio::time::timeout(
+        Duration::from_secs(30),
+        Command::new(env!("CARGO_BIN_EXE_spiffe-helper"))
+            .arg("--config")
+            .arg(&config_path)
+            .arg("--watch-once")
+            .env_remove("SPIFFE_ENDPOINT_SOCKET")
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .expect("spiffe-helper did not exit in watch-once mode")
+    .expect("Failed to run spiffe-helper");
+
+    assert!(
+        output.status.success(),
+        "watch-once mode failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let _cert_bytes = common::assert_x509_cert(&cert_dir.join("svid.pem"));
+    let _key_bytes = common::assert_x509_key(&cert_dir.join("svid_key.pem"));
+    assert!(cert_dir.join("svid_bundle.pem").exists());
+
+    server_handle.abort();
+}
+
+/// Test that watch-once mode keeps waiting, without writing anything, while the served
+/// certificate expires sooner than the required validity.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_watch_once_waits_for_sufficient_validity() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        common::start_mock_agent(&socket_path_clone, DEFAULT_ROTATION_SECONDS).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(cert_dir.to_str().unwrap().to_string()),
+        watch_once: Some(true),
+        watch_once_min_validity_seconds: Some(u64::from(common::SVID_TTL_SECONDS) * 2),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let result = tokio::time::timeout(
+        Duration::from_secs(2),
+        oneshot::run_watch_once(source, config),
+    )
+    .await;
+
+    assert!(
+        result.is_err(),
+        "watch-once mode accepted a certificate that expires too soon"
+    );
+    assert!(!cert_dir.join("svid.pem").exists());
+
+    server_handle.abort();
+}
@@ -0,0 +1,59 @@
+use spiffe_helper::cli::Config;
+use spiffe_helper::{oneshot, workload_api};
+use tempfile::TempDir;
+
+mod common;
+
+const DEFAULT_ROTATION_SECONDS: u32 = 300;
+
+/// Test that `write_all_svids` writes a complete file set for each SVID the agent issues, named
+/// by SPIFFE ID or by hint, alongside the selected SVID in `cert_dir`.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_oneshot_writes_every_svid_to_its_own_directory() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("agent.sock");
+    let cert_dir = temp_dir.path().join("certs");
+
+    let socket_path_clone = socket_path.clone();
+    let server_handle = tokio::spawn(async move {
+        let service = common::mock_agent_service(DEFAULT_ROTATION_SECONDS)
+            .with_additional_svid("/test/admin", "admin-api");
+        common::serve_mock_agent(&socket_path_clone, service).await;
+    });
+    common::assert_socket_ready(&socket_path).await;
+
+    let agent_address = format!("unix://{}", socket_path.display());
+    let config = Config {
+        agent_address: Some(agent_address.clone()),
+        cert_dir: Some(cert_dir.display().to_string()),
+        daemon_mode: Some(false),
+        write_all_svids: Some(true),
+        ..Default::default()
+    };
+
+    let source = workload_api::create_x509_source(&agent_address)
+        .await
+        .expect("Failed to create X509Source");
+    let result = oneshot::run(source, config).await;
+    server_handle.abort();
+    assert!(result.is_ok(), "One-shot mode failed: {:?}", result.err());
+
+    common::assert_x509_cert(&cert_dir.join("svid.pem"));
+    for dir in ["example.org_test_workload", "admin-api"] {
+        for file in ["svid.pem", "svid_key.pem", "svid_bundle.pem"] {
+            let path = cert_dir.join(dir).join(file);
+            assert!(path.exists(), "Missing {}", path.display());
+        }
+    }
+    common::assert_x509_cert(&cert_dir.join("example.org_test_workload/svid.pem"));
+    common::assert_x509_key(&cert_dir.join("admin-api/svid_key.pem"));
+
+    let admin = std::fs::read_to_string(cert_dir.join("admin-api/svid.pem")).unwrap();
+    let admin = pem::parse(&admin).unwrap();
+    let (_, leaf) = x509_parser::parse_x509_certificate(&admin.contents).unwrap();
+    let common_name = leaf.subject().iter_common_name().next().unwrap();
+    assert_eq!(
+        common_name.as_str().unwrap(),
+        "spiffe://example.org/test/admin"
+    );
+}
@@ -0,0 +1,68 @@
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::svid::SvidGenerator;
+
+/// An extra trust bundle authority that is replaced on a fixed interval.
+///
+/// The authority for a given period is derived from the elapsed time, so every stream that
+/// asks for it observes the same certificate and the same rotation boundaries.
+pub struct RotatingAuthority {
+    trust_domain: String,
+    interval: Duration,
+    started: Instant,
+    current: Mutex<(u32, Vec<u8>)>,
+}
+
+impl RotatingAuthority {
+    /// Create a rotating authority for `trust_domain`, replaced every `interval`
+    pub fn new(trust_domain: &str, interval: Duration) -> Self {
+        Self {
+            trust_domain: trust_domain.to_string(),
+            interval,
+            started: Instant::now(),
+            current: Mutex::new((0, SvidGenerator::generate_ca_der(trust_domain))),
+        }
+    }
+
+    fn period(&self) -> u32 {
+        (self.started.elapsed().as_nanos() / self.interval.as_nanos()) as u32
+    }
+
+    /// DER-encoded authority for the current rotation period
+    pub fn current(&self) -> Vec<u8> {
+        let period = self.period();
+        let mut current = self.current.lock().unwrap();
+        if current.0 != period {
+            *current = (period, SvidGenerator::generate_ca_der(&self.trust_domain));
+        }
+        current.1.clone()
+    }
+
+    /// When the authority is next replaced
+    pub fn next_rotation(&self) -> Instant {
+        self.started + self.interval * (self.period() + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_current_is_stable_within_period() {
+        let authority = RotatingAuthority::new("example.org", Duration::from_secs(60));
+        assert_eq!(authority.current(), authority.current());
+    }
+
+    #[tokio::test]
+    async fn test_current_changes_after_rotation() {
+        let authority = RotatingAuthority::new("example.org", Duration::from_millis(50));
+        let first = authority.current();
+
+        tokio::time::sleep_until(authority.next_rotation()).await;
+
+        assert_ne!(first, authority.current());
+    }
+}
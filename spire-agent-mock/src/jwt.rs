@@ -0,0 +1,165 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+use serde_json::json;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Signs SPIFFE JWT SVIDs with a locally generated ES256 (P-256) key
+pub struct JwtSigner {
+    key_pair: EcdsaKeyPair,
+    key_id: String,
+    rng: SystemRandom,
+    ttl_seconds: u32,
+}
+
+impl JwtSigner {
+    /// Create a signer with a fresh P-256 key pair
+    pub fn new(ttl_seconds: u32) -> Self {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+        let key_pair =
+            EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng)
+                .unwrap();
+
+        // Derive a stable key ID from the public key so it can be matched against the JWKS
+        let key_id = URL_SAFE_NO_PAD.encode(&key_pair.public_key().as_ref()[1..17]);
+
+        Self {
+            key_pair,
+            key_id,
+            rng,
+            ttl_seconds,
+        }
+    }
+
+    /// The key ID placed in the `kid` header of every minted token
+    #[allow(dead_code)]
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// The JWKS document containing the signing public key, as served by `FetchJWTBundles`
+    pub fn jwks(&self) -> serde_json::Value {
+        // Uncompressed SEC1 point: 0x04 || X (32 bytes) || Y (32 bytes)
+        let public_key = self.key_pair.public_key().as_ref();
+        json!({
+            "keys": [{
+                "kty": "EC",
+                "crv": "P-256",
+                "x": URL_SAFE_NO_PAD.encode(&public_key[1..33]),
+                "y": URL_SAFE_NO_PAD.encode(&public_key[33..65]),
+                "kid": self.key_id,
+                "use": "jwt-svid",
+            }]
+        })
+    }
+
+    /// Mint a signed JWT SVID for `spiffe_id` valid for the given audiences
+    pub fn mint(&self, spiffe_id: &str, audiences: &[String]) -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let header = json!({
+            "alg": "ES256",
+            "kid": self.key_id,
+            "typ": "JWT",
+        });
+        let claims = json!({
+            "sub": spiffe_id,
+            "aud": audiences,
+            "iat": now,
+            "exp": now + u64::from(self.ttl_seconds),
+        });
+
+        let signing_input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+        let signature = self
+            .key_pair
+            .sign(&self.rng, signing_input.as_bytes())
+            .unwrap();
+
+        format!(
+            "{signing_input}.{}",
+            URL_SAFE_NO_PAD.encode(signature.as_ref())
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::signature::{UnparsedPublicKey, ECDSA_P256_SHA256_FIXED};
+
+    fn decode_segment(segment: &str) -> serde_json::Value {
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(segment).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_mint_sets_claims() {
+        let signer = JwtSigner::new(120);
+        let token = signer.mint(
+            "spiffe://example.org/workload",
+            &["aud1".to_string(), "aud2".to_string()],
+        );
+
+        let parts: Vec<&str> = token.split('.').collect();
+        assert_eq!(parts.len(), 3);
+
+        let header = decode_segment(parts[0]);
+        assert_eq!(header["alg"], "ES256");
+        assert_eq!(header["kid"], signer.key_id());
+
+        let claims = decode_segment(parts[1]);
+        assert_eq!(claims["sub"], "spiffe://example.org/workload");
+        assert_eq!(claims["aud"], json!(["aud1", "aud2"]));
+        let ttl = claims["exp"].as_u64().unwrap() - claims["iat"].as_u64().unwrap();
+        assert_eq!(ttl, 120);
+    }
+
+    #[test]
+    fn test_mint_signature_verifies() {
+        let signer = JwtSigner::new(60);
+        let token = signer.mint("spiffe://example.org/workload", &["aud".to_string()]);
+
+        let (signing_input, signature) = token.rsplit_once('.').unwrap();
+        let public_key = UnparsedPublicKey::new(
+            &ECDSA_P256_SHA256_FIXED,
+            signer.key_pair.public_key().as_ref(),
+        );
+
+        public_key
+            .verify(
+                signing_input.as_bytes(),
+                &URL_SAFE_NO_PAD.decode(signature).unwrap(),
+            )
+            .expect("signature should verify against the signing key");
+    }
+
+    #[test]
+    fn test_jwks_contains_signing_key() {
+        let signer = JwtSigner::new(60);
+        let jwks = signer.jwks();
+
+        let keys = jwks["keys"].as_array().unwrap();
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0]["kty"], "EC");
+        assert_eq!(keys[0]["crv"], "P-256");
+        assert_eq!(keys[0]["kid"], signer.key_id());
+
+        let x = URL_SAFE_NO_PAD
+            .decode(keys[0]["x"].as_str().unwrap())
+            .unwrap();
+        let y = URL_SAFE_NO_PAD
+            .decode(keys[0]["y"].as_str().unwrap())
+            .unwrap();
+        let public_key = signer.key_pair.public_key().as_ref();
+        assert_eq!(x, &public_key[1..33]);
+        assert_eq!(y, &public_key[33..65]);
+    }
+}
@@ -1,2 +1,4 @@
+pub mod bundle;
+pub mod jwt;
 pub mod server;
 pub mod svid;
@@ -1,3 +1,5 @@
+mod bundle;
+mod jwt;
 mod server;
 mod svid;
 
@@ -23,13 +25,20 @@ struct Args {
         env = "SPIRE_MOCK_SOCKET_PATH"
     )]
     socket_path: PathBuf,
-    /// X.509 SVID rotation interval in seconds
+    /// Interval in seconds between freshly signed X.509 SVIDs pushed down the open stream
     #[arg(
-        long = "x509-internal",
+        long = "rotation-seconds",
+        alias = "x509-internal",
         default_value_t = 30,
-        env = "SPIRE_MOCK_X509_INTERNAL_SECONDS"
+        env = "SPIRE_MOCK_ROTATION_SECONDS"
     )]
-    x509_rotation_interval_seconds: u64,
+    rotation_seconds: u64,
+    /// Interval in seconds between trust bundle authority rotations (disabled when unset)
+    #[arg(long, env = "SPIRE_MOCK_BUNDLE_ROTATION_SECONDS")]
+    bundle_rotation_seconds: Option<u64>,
+    /// Additional federated trust domain to include in the served bundles
+    #[arg(long, env = "SPIRE_MOCK_FEDERATED_TRUST_DOMAIN")]
+    federated_trust_domain: Option<String>,
 }
 
 #[tokio::main]
@@ -56,9 +65,14 @@ async fn main() -> Result<()> {
     let uds = UnixListener::bind(&socket_path)?;
     let uds_stream = UnixListenerStream::new(uds);
 
-    let service = MockWorkloadApi::with_rotation_interval(Duration::from_secs(
-        args.x509_rotation_interval_seconds,
-    ));
+    let mut service =
+        MockWorkloadApi::with_rotation_interval(Duration::from_secs(args.rotation_seconds));
+    if let Some(seconds) = args.bundle_rotation_seconds {
+        service = service.with_bundle_rotation(Duration::from_secs(seconds));
+    }
+    if let Some(trust_domain) = &args.federated_trust_domain {
+        service = service.with_federated_trust_domain(trust_domain);
+    }
 
     Server::builder()
         .add_service(SpiffeWorkloadApiServer::new(service))
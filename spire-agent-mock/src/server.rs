@@ -1,10 +1,14 @@
+use std::collections::HashMap;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::time::Instant;
 use tokio_stream::Stream;
 use tonic::{Request, Response, Status};
 
-use crate::svid::{SvidConfig, SvidGenerator};
+use crate::bundle::RotatingAuthority;
+use crate::jwt::JwtSigner;
+use crate::svid::{SvidConfig, SvidGenerator, X509Svid};
 
 pub mod workload {
     tonic::include_proto!("_");
@@ -13,14 +17,25 @@ pub mod workload {
 use workload::spiffe_workload_api_server::SpiffeWorkloadApi;
 pub use workload::spiffe_workload_api_server::SpiffeWorkloadApiServer;
 use workload::{
-    JwtBundlesRequest, JwtBundlesResponse, JwtsvidRequest, JwtsvidResponse, ValidateJwtsvidRequest,
-    ValidateJwtsvidResponse, X509BundlesRequest, X509BundlesResponse, X509svid, X509svidRequest,
-    X509svidResponse,
+    JwtBundlesRequest, JwtBundlesResponse, Jwtsvid, JwtsvidRequest, JwtsvidResponse,
+    ValidateJwtsvidRequest, ValidateJwtsvidResponse, X509BundlesRequest, X509BundlesResponse,
+    X509svid, X509svidRequest, X509svidResponse,
 };
 
 pub struct MockWorkloadApi {
     svid_generator: Arc<SvidGenerator>,
+    jwt_signer: Arc<JwtSigner>,
     rotation_interval: Duration,
+    /// Number of SVIDs sent on each rotation and the delay between them
+    rotation_burst: (u32, Duration),
+    static_svid: Option<X509Svid>,
+    rotating_authority: Option<Arc<RotatingAuthority>>,
+    federated_bundles: HashMap<String, Vec<u8>>,
+    crl: Vec<Vec<u8>>,
+    /// Workload path and hint of each SVID issued after the default one
+    additional_svids: Vec<(String, String)>,
+    rejected_jwt_audiences: Vec<String>,
+    no_svids: bool,
 }
 
 impl MockWorkloadApi {
@@ -40,11 +55,110 @@ impl MockWorkloadApi {
     }
 
     pub fn with_config_and_rotation(config: SvidConfig, rotation_interval: Duration) -> Self {
+        let jwt_signer = JwtSigner::new(config.jwt_ttl_seconds);
         Self {
             svid_generator: Arc::new(SvidGenerator::new(config)),
+            jwt_signer: Arc::new(jwt_signer),
             rotation_interval,
+            rotation_burst: (1, Duration::ZERO),
+            static_svid: None,
+            rotating_authority: None,
+            federated_bundles: HashMap::new(),
+            crl: Vec::new(),
+            additional_svids: Vec::new(),
+            rejected_jwt_audiences: Vec::new(),
+            no_svids: false,
         }
     }
+
+    /// Resend the same SVID on every rotation interval instead of re-signing a fresh one
+    #[allow(dead_code)]
+    pub fn with_static_svid(mut self) -> Self {
+        self.static_svid = Some(self.svid_generator.generate_svid());
+        self
+    }
+
+    /// Send `count` fresh SVIDs `spacing` apart on every rotation instead of one, as an agent
+    /// may push several updates in quick succession
+    #[allow(dead_code)]
+    pub fn with_rotation_burst(mut self, count: u32, spacing: Duration) -> Self {
+        self.rotation_burst = (count.max(1), spacing);
+        self
+    }
+
+    /// Add an extra bundle authority that is replaced every `interval`, independently of
+    /// the leaf SVID rotation
+    pub fn with_bundle_rotation(mut self, interval: Duration) -> Self {
+        let trust_domain = self.svid_generator.trust_domain();
+        self.rotating_authority = Some(Arc::new(RotatingAuthority::new(trust_domain, interval)));
+        self
+    }
+
+    /// Serve a bundle for a federated trust domain alongside the local one
+    pub fn with_federated_trust_domain(mut self, trust_domain: &str) -> Self {
+        self.federated_bundles.insert(
+            format!("spiffe://{trust_domain}"),
+            SvidGenerator::generate_ca_der(trust_domain),
+        );
+        self
+    }
+
+    /// Serve a federated trust domain that has no bundle authorities yet
+    #[allow(dead_code)]
+    pub fn with_empty_federated_trust_domain(mut self, trust_domain: &str) -> Self {
+        self.federated_bundles
+            .insert(format!("spiffe://{trust_domain}"), Vec::new());
+        self
+    }
+
+    /// Issue another SVID for `workload_path` with `hint` after the default one, as an agent
+    /// does for a workload registered under several entries
+    #[allow(dead_code)]
+    pub fn with_additional_svid(mut self, workload_path: &str, hint: &str) -> Self {
+        self.additional_svids
+            .push((workload_path.to_string(), hint.to_string()));
+        self
+    }
+
+    /// Deny every JWT SVID request for `audience`, as an agent does for an audience the
+    /// workload is not entitled to
+    #[allow(dead_code)]
+    pub fn with_rejected_jwt_audience(mut self, audience: &str) -> Self {
+        self.rejected_jwt_audiences.push(audience.to_string());
+        self
+    }
+
+    /// Answer X.509 SVID requests with an empty SVID list, as an agent does for a workload
+    /// that no registration entry matches
+    #[allow(dead_code)]
+    pub fn with_no_svids(mut self) -> Self {
+        self.no_svids = true;
+        self
+    }
+
+    /// Send `crl` (DER) in the `crl` field of every X.509 SVID response
+    #[allow(dead_code)]
+    pub fn with_crl(mut self, crl: Vec<u8>) -> Self {
+        self.crl.push(crl);
+        self
+    }
+}
+
+/// The local trust bundle: the signing CA plus the current rotating authority, if any
+fn bundle_der(ca_cert_der: &[u8], rotating_authority: Option<&RotatingAuthority>) -> Vec<u8> {
+    let mut bundle = ca_cert_der.to_vec();
+    if let Some(authority) = rotating_authority {
+        bundle.extend_from_slice(&authority.current());
+    }
+    bundle
+}
+
+/// Resolves when the rotating authority is next replaced, or never if there is none
+async fn bundle_rotation(rotating_authority: Option<&RotatingAuthority>) {
+    match rotating_authority {
+        Some(authority) => tokio::time::sleep_until(authority.next_rotation()).await,
+        None => std::future::pending().await,
+    }
 }
 
 impl Default for MockWorkloadApi {
@@ -65,30 +179,79 @@ impl SpiffeWorkloadApi for MockWorkloadApi {
 
         let svid_generator = Arc::clone(&self.svid_generator);
         let rotation_interval = self.rotation_interval;
+        let (burst_count, burst_spacing) = self.rotation_burst;
+        let static_svid = self.static_svid.clone();
+        let rotating_authority = self.rotating_authority.clone();
+        let federated_bundles = self.federated_bundles.clone();
+        let crl = self.crl.clone();
+        let additional_svids = self.additional_svids.clone();
+
+        if self.no_svids {
+            let stream = async_stream::stream! {
+                yield Ok(X509svidResponse {
+                    svids: Vec::new(),
+                    crl: Vec::new(),
+                    federated_bundles: HashMap::new(),
+                });
+                std::future::pending::<()>().await;
+            };
+            return Ok(Response::new(Box::pin(stream)));
+        }
 
         let stream = async_stream::stream! {
-            loop {
-                let svid = svid_generator.generate_svid();
+            let mut svid = static_svid
+                .clone()
+                .unwrap_or_else(|| svid_generator.generate_svid());
+            let mut next_svid_rotation = Instant::now() + rotation_interval;
+            let mut burst_sent = 0;
 
+            loop {
                 let x509_svid = X509svid {
                     spiffe_id: svid.spiffe_id.clone(),
-                    x509_svid: svid.cert_chain_der,
-                    x509_svid_key: svid.private_key_der,
-                    bundle: svid.bundle_der,
+                    x509_svid: svid.cert_chain_der.clone(),
+                    x509_svid_key: svid.private_key_der.clone(),
+                    bundle: bundle_der(&svid.bundle_der, rotating_authority.as_deref()),
                     hint: String::new(),
                 };
 
+                let mut svids = vec![x509_svid];
+                for (workload_path, hint) in &additional_svids {
+                    let additional = svid_generator.generate_svid_for_path(workload_path);
+                    svids.push(X509svid {
+                        spiffe_id: additional.spiffe_id,
+                        x509_svid: additional.cert_chain_der,
+                        x509_svid_key: additional.private_key_der,
+                        bundle: bundle_der(&additional.bundle_der, rotating_authority.as_deref()),
+                        hint: hint.clone(),
+                    });
+                }
+
                 let response = X509svidResponse {
-                    svids: vec![x509_svid],
-                    crl: vec![],
-                    federated_bundles: std::collections::HashMap::new(),
+                    svids,
+                    crl: crl.clone(),
+                    federated_bundles: federated_bundles.clone(),
                 };
 
                 println!("Sending X509SVID: {}", svid.spiffe_id);
                 yield Ok(response);
 
-                // Wait for the rotation interval before sending the next certificate
-                tokio::time::sleep(rotation_interval).await;
+                tokio::select! {
+                    // Wait for the rotation interval before sending the next certificate
+                    () = tokio::time::sleep_until(next_svid_rotation) => {
+                        if static_svid.is_none() {
+                            svid = svid_generator.generate_svid();
+                        }
+                        burst_sent += 1;
+                        if burst_sent < burst_count {
+                            next_svid_rotation += burst_spacing;
+                        } else {
+                            burst_sent = 0;
+                            next_svid_rotation += rotation_interval;
+                        }
+                    }
+                    // Bundle rotations resend the current leaf with the new authorities
+                    () = bundle_rotation(rotating_authority.as_deref()) => {}
+                }
             }
         };
 
@@ -103,15 +266,68 @@ impl SpiffeWorkloadApi for MockWorkloadApi {
         _request: Request<X509BundlesRequest>,
     ) -> Result<Response<Self::FetchX509BundlesStream>, Status> {
         println!("Received FetchX509Bundles request");
-        Err(Status::unimplemented("not implemented"))
+
+        let trust_domain_id = self.svid_generator.trust_domain_id();
+        let ca_cert_der = self.svid_generator.ca_cert_der().to_vec();
+        let rotating_authority = self.rotating_authority.clone();
+        let federated_bundles = self.federated_bundles.clone();
+
+        let stream = async_stream::stream! {
+            loop {
+                let mut bundles = federated_bundles.clone();
+                bundles.insert(
+                    trust_domain_id.clone(),
+                    bundle_der(&ca_cert_der, rotating_authority.as_deref()),
+                );
+
+                println!("Sending X509 bundles: {} trust domain(s)", bundles.len());
+                yield Ok(X509BundlesResponse { crl: vec![], bundles });
+
+                // Without a rotating authority the bundles never change, but the stream stays open
+                bundle_rotation(rotating_authority.as_deref()).await;
+            }
+        };
+
+        Ok(Response::new(Box::pin(stream)))
     }
 
     async fn fetch_jwtsvid(
         &self,
-        _request: Request<JwtsvidRequest>,
+        request: Request<JwtsvidRequest>,
     ) -> Result<Response<JwtsvidResponse>, Status> {
         println!("Received FetchJWTSVID request");
-        Err(Status::unimplemented("not implemented"))
+
+        let request = request.into_inner();
+        if request.audience.is_empty() {
+            return Err(Status::invalid_argument("audience must be specified"));
+        }
+
+        if request
+            .audience
+            .iter()
+            .any(|audience| self.rejected_jwt_audiences.contains(audience))
+        {
+            return Err(Status::permission_denied("audience not allowed"));
+        }
+
+        let spiffe_id = self.svid_generator.spiffe_id();
+        if !request.spiffe_id.is_empty() && request.spiffe_id != spiffe_id {
+            return Err(Status::permission_denied("no identity issued"));
+        }
+
+        let token = self.jwt_signer.mint(&spiffe_id, &request.audience);
+        println!(
+            "Sending JWTSVID: {spiffe_id} audience={:?}",
+            request.audience
+        );
+
+        Ok(Response::new(JwtsvidResponse {
+            svids: vec![Jwtsvid {
+                spiffe_id,
+                svid: token,
+                hint: String::new(),
+            }],
+        }))
     }
 
     type FetchJWTBundlesStream =
@@ -122,7 +338,29 @@ impl SpiffeWorkloadApi for MockWorkloadApi {
         _request: Request<JwtBundlesRequest>,
     ) -> Result<Response<Self::FetchJWTBundlesStream>, Status> {
         println!("Received FetchJWTBundles request");
-        Err(Status::unimplemented("not implemented"))
+
+        let trust_domain_id = self.svid_generator.trust_domain_id();
+        let jwks = self.jwt_signer.jwks().to_string().into_bytes();
+        let rotation_interval = self.rotation_interval;
+
+        let stream = async_stream::stream! {
+            loop {
+                let response = JwtBundlesResponse {
+                    bundles: std::collections::HashMap::from([(
+                        trust_domain_id.clone(),
+                        jwks.clone(),
+                    )]),
+                };
+
+                println!("Sending JWT bundle: {trust_domain_id}");
+                yield Ok(response);
+
+                // The signing key never rotates, but keep the stream open like a real agent
+                tokio::time::sleep(rotation_interval).await;
+            }
+        };
+
+        Ok(Response::new(Box::pin(stream)))
     }
 
     async fn validate_jwtsvid(
@@ -133,3 +371,66 @@ impl SpiffeWorkloadApi for MockWorkloadApi {
         Err(Status::unimplemented("not implemented"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_stream::StreamExt;
+
+    fn leaf_serial(response: X509svidResponse) -> Vec<u8> {
+        let chain = &response.svids[0].x509_svid;
+        let (_, leaf) = x509_parser::parse_x509_certificate(chain).unwrap();
+        leaf.tbs_certificate.serial.to_bytes_be()
+    }
+
+    #[tokio::test]
+    async fn test_fetch_x509svid_rotates_leaf_on_interval() {
+        let api = MockWorkloadApi::with_rotation_interval(Duration::from_millis(100));
+        let mut stream = api
+            .fetch_x509svid(Request::new(X509svidRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let first = stream.next().await.unwrap().unwrap();
+        let second = tokio::time::timeout(Duration::from_secs(2), stream.next())
+            .await
+            .expect("No rotated SVID within the rotation window")
+            .unwrap()
+            .unwrap();
+
+        assert_ne!(
+            first.svids[0].x509_svid_key, second.svids[0].x509_svid_key,
+            "Rotated SVID reused the previous key"
+        );
+        assert_ne!(leaf_serial(first), leaf_serial(second));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_x509_bundles_rotates_authorities() {
+        let api = MockWorkloadApi::with_rotation_interval(Duration::from_secs(300))
+            .with_bundle_rotation(Duration::from_millis(100))
+            .with_federated_trust_domain("federated.org");
+        let mut stream = api
+            .fetch_x509_bundles(Request::new(X509BundlesRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let first = stream.next().await.unwrap().unwrap();
+        let second = tokio::time::timeout(Duration::from_secs(2), stream.next())
+            .await
+            .expect("No rotated bundle within the rotation window")
+            .unwrap()
+            .unwrap();
+
+        assert_ne!(
+            first.bundles["spiffe://example.org"],
+            second.bundles["spiffe://example.org"]
+        );
+        assert_eq!(
+            first.bundles["spiffe://federated.org"],
+            second.bundles["spiffe://federated.org"]
+        );
+    }
+}
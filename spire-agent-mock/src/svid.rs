@@ -1,10 +1,13 @@
 use rcgen::{
     BasicConstraints, Certificate, CertificateParams, DistinguishedName, DnType,
-    ExtendedKeyUsagePurpose, IsCa, KeyPair, KeyUsagePurpose, SanType,
+    ExtendedKeyUsagePurpose, IsCa, KeyPair, KeyUsagePurpose, SanType, SerialNumber,
 };
+use ring::rand::{SecureRandom, SystemRandom};
+use std::time::SystemTime;
 use time::{Duration, OffsetDateTime};
 
 /// Represents a SPIFFE X.509 SVID with its private key and CA bundle
+#[derive(Clone)]
 pub struct X509Svid {
     /// The SPIFFE ID (e.g., spiffe://example.org/workload)
     pub spiffe_id: String,
@@ -21,6 +24,8 @@ pub struct SvidConfig {
     pub trust_domain: String,
     pub workload_path: String,
     pub ttl_seconds: u32,
+    /// Lifetime of minted JWT SVIDs
+    pub jwt_ttl_seconds: u32,
 }
 
 impl Default for SvidConfig {
@@ -29,6 +34,7 @@ impl Default for SvidConfig {
             trust_domain: "example.org".to_string(),
             workload_path: "/workload".to_string(),
             ttl_seconds: 30,
+            jwt_ttl_seconds: 300,
         }
     }
 }
@@ -53,6 +59,11 @@ impl SvidGenerator {
         }
     }
 
+    /// Generate a standalone DER-encoded CA certificate for the trust domain
+    pub fn generate_ca_der(trust_domain: &str) -> Vec<u8> {
+        Self::generate_ca(trust_domain).2
+    }
+
     /// Generate a CA certificate for the trust domain
     fn generate_ca(trust_domain: &str) -> (Certificate, KeyPair, Vec<u8>) {
         let mut params = CertificateParams::default();
@@ -86,13 +97,64 @@ impl SvidGenerator {
         (ca_cert, key_pair, ca_cert_der)
     }
 
-    /// Generate a new X.509 SVID
-    pub fn generate_svid(&self) -> X509Svid {
-        let spiffe_id = format!(
+    /// The SPIFFE ID issued to the workload
+    pub fn spiffe_id(&self) -> String {
+        format!(
             "spiffe://{}{}",
             self.config.trust_domain, self.config.workload_path
-        );
+        )
+    }
+
+    /// The trust domain name
+    pub fn trust_domain(&self) -> &str {
+        &self.config.trust_domain
+    }
+
+    /// DER-encoded CA certificate that signs every SVID
+    pub fn ca_cert_der(&self) -> &[u8] {
+        &self.ca_cert_der
+    }
+
+    /// The SPIFFE ID of the trust domain, used to key bundle maps
+    pub fn trust_domain_id(&self) -> String {
+        format!("spiffe://{}", self.config.trust_domain)
+    }
 
+    /// Generate a new X.509 SVID
+    pub fn generate_svid(&self) -> X509Svid {
+        let now = SystemTime::now();
+        self.generate_svid_with_validity(
+            now,
+            now + std::time::Duration::from_secs(self.config.ttl_seconds.into()),
+        )
+    }
+
+    /// Generate a new X.509 SVID for another workload path in the same trust domain
+    pub fn generate_svid_for_path(&self, workload_path: &str) -> X509Svid {
+        let now = SystemTime::now();
+        self.generate_svid_for_id(
+            format!("spiffe://{}{}", self.config.trust_domain, workload_path),
+            now,
+            now + std::time::Duration::from_secs(self.config.ttl_seconds.into()),
+        )
+    }
+
+    /// Generate an X.509 SVID valid between `not_before` and `not_after`, e.g. one that has
+    /// already expired
+    pub fn generate_svid_with_validity(
+        &self,
+        not_before: SystemTime,
+        not_after: SystemTime,
+    ) -> X509Svid {
+        self.generate_svid_for_id(self.spiffe_id(), not_before, not_after)
+    }
+
+    fn generate_svid_for_id(
+        &self,
+        spiffe_id: String,
+        not_before: SystemTime,
+        not_after: SystemTime,
+    ) -> X509Svid {
         // Create workload certificate parameters
         let mut params = CertificateParams::default();
 
@@ -114,10 +176,14 @@ impl SvidGenerator {
             ExtendedKeyUsagePurpose::ClientAuth,
         ];
 
-        // Set validity period based on TTL
-        let now = OffsetDateTime::now_utc();
-        params.not_before = now;
-        params.not_after = now + Duration::seconds(self.config.ttl_seconds.into());
+        // Fresh random serial so every rotation is observable as a distinct certificate
+        let mut serial = [0u8; 16];
+        SystemRandom::new().fill(&mut serial).unwrap();
+        serial[0] &= 0x7f;
+        params.serial_number = Some(SerialNumber::from_slice(&serial));
+
+        params.not_before = OffsetDateTime::from(not_before);
+        params.not_after = OffsetDateTime::from(not_after);
 
         // SPIFFE ID as URI SAN - this is required by SPIFFE spec
         params.subject_alt_names = vec![SanType::URI(spiffe_id.parse().unwrap())];
@@ -189,6 +255,7 @@ mod tests {
             trust_domain: "test.domain".to_string(),
             workload_path: "/my/service".to_string(),
             ttl_seconds: 60,
+            jwt_ttl_seconds: 60,
         };
         let generator = SvidGenerator::new(config);
         let svid = generator.generate_svid();